@@ -6,6 +6,8 @@
 //! All indices are **character indices** (Unicode scalar values), matching
 //! `ropey`'s primary indexing model.
 
+use super::TextBuffer;
+
 /// A text edit expressed in character indices within the buffer.
 ///
 /// The `range` is half-open: `[start, end)`.
@@ -42,4 +44,94 @@ impl Edit {
             insert: text.into(),
         }
     }
+
+    /// The edit that undoes `self`, computed against `buffer` *before* `self` is
+    /// applied.
+    ///
+    /// The inverse replaces the freshly inserted text (whose length is counted in
+    /// chars) with whatever `self.range` currently covers. The range is clamped
+    /// and ordered the same way `TextBuffer::apply_edit` does it, so the two agree.
+    pub fn invert(&self, buffer: &TextBuffer) -> Edit {
+        let (start, end) = self.clamped_range(buffer);
+        let inserted = self.insert.chars().count();
+        Edit::replace(start..start + inserted, buffer.slice_chars(start, end))
+    }
+
+    /// Describe `self` in bytes for an incremental parser (tree-sitter's
+    /// `Tree::edit`), computed against `buffer` *before* `self` is applied.
+    ///
+    /// The range is clamped and ordered like `TextBuffer::apply_edit` does it.
+    /// Rows count `\n`s only, as tree-sitter does, not every line break ropey
+    /// knows (a lone `\r` or U+2028 doesn't start a row here).
+    pub fn input_edit(&self, buffer: &TextBuffer) -> InputEdit {
+        let (start, end) = self.clamped_range(buffer);
+        let rope = buffer.rope();
+        let start_byte = rope.char_to_byte(start);
+        let old_end_byte = rope.char_to_byte(end);
+
+        let start_point = rope
+            .byte_slice(..start_byte)
+            .chunks()
+            .fold(BytePoint { row: 0, column: 0 }, BytePoint::advance);
+        let old_end_point = rope
+            .byte_slice(start_byte..old_end_byte)
+            .chunks()
+            .fold(start_point, BytePoint::advance);
+
+        InputEdit {
+            start_byte,
+            old_end_byte,
+            new_end_byte: start_byte + self.insert.len(),
+            start_point,
+            old_end_point,
+            new_end_point: start_point.advance(&self.insert),
+        }
+    }
+
+    /// `self.range` clamped to `buffer` and ordered.
+    fn clamped_range(&self, buffer: &TextBuffer) -> (usize, usize) {
+        let maxc = buffer.len_chars();
+        let start = self.range.start.min(maxc);
+        let end = self.range.end.min(maxc);
+        if start <= end {
+            (start, end)
+        } else {
+            (end, start)
+        }
+    }
+}
+
+/// A line and a **byte** column within it, as tree-sitter's `Point` uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BytePoint {
+    pub row: usize,
+    pub column: usize,
+}
+
+impl BytePoint {
+    /// The point just past `text`, if it starts at `self`.
+    fn advance(self, text: &str) -> BytePoint {
+        match text.rfind('\n') {
+            Some(last_newline) => BytePoint {
+                row: self.row + text.bytes().filter(|&b| b == b'\n').count(),
+                column: text.len() - last_newline - 1,
+            },
+            None => BytePoint {
+                row: self.row,
+                column: self.column + text.len(),
+            },
+        }
+    }
+}
+
+/// An edit in byte offsets, mirroring tree-sitter's `InputEdit`; see
+/// [`Edit::input_edit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InputEdit {
+    pub start_byte: usize,
+    pub old_end_byte: usize,
+    pub new_end_byte: usize,
+    pub start_point: BytePoint,
+    pub old_end_point: BytePoint,
+    pub new_end_point: BytePoint,
 }
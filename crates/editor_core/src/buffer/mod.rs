@@ -11,13 +11,16 @@
 mod edit;
 mod pos;
 pub mod text_buffer;
-mod util;
+pub(crate) mod util;
 
 pub mod prelude;
 
-pub use edit::Edit;
-pub use pos::{Pos, Selection};
-pub use text_buffer::TextBuffer;
+pub use edit::{BytePoint, Edit, InputEdit};
+pub use pos::{MultiSelection, Pos, Selection};
+pub use text_buffer::{
+    AnchorId, Bias, BufferOptions, BufferSnapshot, CharClass, IndentReport, LineEnding,
+    SearchOptions, TextBuffer, TextObjectKind, WordChecker,
+};
 
 #[cfg(test)]
 mod tests;
@@ -65,3 +65,38 @@ impl Selection {
         self.anchor == self.cursor
     }
 }
+
+/// Several selections edited together (block-visual mode, multiple cursors).
+///
+/// Selections are kept in document order. `primary` indexes the one the view
+/// should follow; it's meaningless when there are no selections.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MultiSelection {
+    pub selections: Vec<Selection>,
+    pub primary: usize,
+}
+
+impl MultiSelection {
+    /// Wrap `selections`, with the first one as primary.
+    pub fn new(selections: Vec<Selection>) -> Self {
+        Self {
+            selections,
+            primary: 0,
+        }
+    }
+
+    /// The primary selection, if there are any selections.
+    pub fn primary(&self) -> Option<Selection> {
+        self.selections.get(self.primary).copied()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.selections.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.selections.is_empty()
+    }
+}
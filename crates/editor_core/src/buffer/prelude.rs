@@ -5,6 +5,7 @@
 //! - keep call sites clean without importing many individual symbols.
 
 pub use super::Edit;
+pub use super::MultiSelection;
 pub use super::Pos;
 pub use super::Selection;
 pub use super::TextBuffer;
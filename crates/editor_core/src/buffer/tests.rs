@@ -1,6 +1,7 @@
 //! Unit tests for the rope-backed buffer live here to keep the main modules smaller.
 
 use super::*;
+use crate::test_util::TempFile;
 
 #[test]
 fn pos_char_roundtrip_basic() {
@@ -71,6 +72,188 @@ fn word_motions_ascii() {
     assert_eq!(end, Pos::new(0, 11));
 }
 
+#[test]
+fn word_start_after_skips_run_then_whitespace() {
+    let b = TextBuffer::from_str("foo.bar  baz\n  qux");
+    assert_eq!(b.word_start_after(Pos::new(0, 1)), Pos::new(0, 3)); // "." is its own word
+    assert_eq!(b.word_start_after(Pos::new(0, 3)), Pos::new(0, 4));
+    assert_eq!(b.word_start_after(Pos::new(0, 4)), Pos::new(0, 9));
+    assert_eq!(b.word_start_after(Pos::new(0, 9)), Pos::new(1, 2)); // across the newline
+    assert_eq!(b.word_start_after(Pos::new(1, 2)), Pos::new(1, 5)); // EOF
+}
+
+#[test]
+fn w_stops_between_word_and_punctuation() {
+    let b = TextBuffer::from_str("foo.bar");
+    assert_eq!(b.word_start_after(Pos::new(0, 0)), Pos::new(0, 3));
+    assert_eq!(b.word_start_before(Pos::new(0, 4)), Pos::new(0, 3));
+    assert_eq!(b.word_end_after(Pos::new(0, 0)), Pos::new(0, 3));
+}
+
+#[test]
+fn char_class_at_positions() {
+    let b = TextBuffer::from_str("a .\n");
+    assert_eq!(b.char_class_at(Pos::new(0, 0)), Some(CharClass::Word));
+    assert_eq!(b.char_class_at(Pos::new(0, 1)), Some(CharClass::Whitespace));
+    assert_eq!(b.char_class_at(Pos::new(0, 2)), Some(CharClass::Punct));
+    assert_eq!(b.char_class_at(Pos::new(0, 3)), Some(CharClass::Newline));
+    assert_eq!(b.char_class_at(Pos::new(1, 0)), None);
+}
+
+#[test]
+fn word_at_expands_around_cursor() {
+    use crate::text::{CharIdx, CharRange};
+
+    let b = TextBuffer::from_str("let foo_bar = 1;\nnext");
+    let (range, word) = b.word_at(Pos::new(0, 6)).unwrap();
+    assert_eq!(word, "foo_bar");
+    assert_eq!(range, CharRange::new(CharIdx(4), CharIdx(11)));
+
+    // Either edge of the word still counts as inside it.
+    assert_eq!(b.word_at(Pos::new(0, 4)).unwrap().1, "foo_bar");
+    assert_eq!(b.word_at(Pos::new(0, 10)).unwrap().1, "foo_bar");
+    // Word on the next line doesn't bleed across the newline.
+    assert_eq!(b.word_at(Pos::new(1, 0)).unwrap().1, "next");
+}
+
+#[test]
+fn word_at_delimiter_or_whitespace_is_none() {
+    let b = TextBuffer::from_str("a = b;");
+    assert_eq!(b.word_at(Pos::new(0, 1)), None); // space right after "a"
+    assert_eq!(b.word_at(Pos::new(0, 2)), None); // "="
+    assert_eq!(b.word_at(Pos::new(0, 6)), None); // end of line
+}
+
+#[test]
+fn find_char_forward_and_till() {
+    let b = TextBuffer::from_str("a,b,c\nx,y");
+    assert_eq!(
+        b.find_char_forward(Pos::new(0, 0), ',', false),
+        Some(Pos::new(0, 1))
+    );
+    assert_eq!(
+        b.find_char_forward(Pos::new(0, 1), ',', false),
+        Some(Pos::new(0, 3))
+    );
+    assert_eq!(
+        b.find_char_forward(Pos::new(0, 0), 'c', true),
+        Some(Pos::new(0, 3))
+    );
+    // An adjacent match leaves a till search in place.
+    assert_eq!(
+        b.find_char_forward(Pos::new(0, 0), ',', true),
+        Some(Pos::new(0, 0))
+    );
+}
+
+#[test]
+fn find_char_backward_and_till() {
+    let b = TextBuffer::from_str("a,b,c");
+    assert_eq!(
+        b.find_char_backward(Pos::new(0, 4), ',', false),
+        Some(Pos::new(0, 3))
+    );
+    assert_eq!(
+        b.find_char_backward(Pos::new(0, 4), 'a', true),
+        Some(Pos::new(0, 1))
+    );
+    assert_eq!(b.find_char_backward(Pos::new(0, 0), 'a', false), None);
+}
+
+#[test]
+fn find_char_stays_on_line() {
+    let b = TextBuffer::from_str("ab\ncd\nab");
+    assert_eq!(b.find_char_forward(Pos::new(0, 0), 'c', false), None);
+    assert_eq!(b.find_char_forward(Pos::new(0, 0), '\n', false), None);
+    assert_eq!(b.find_char_backward(Pos::new(2, 1), 'd', false), None);
+    assert_eq!(b.find_char_forward(Pos::new(1, 0), 'z', true), None);
+}
+
+/// Text of the text object at `pos`, or `None` if there isn't one.
+fn object_text(b: &TextBuffer, pos: Pos, kind: TextObjectKind, inner: bool) -> Option<String> {
+    let r = b.text_object(pos, kind, inner)?;
+    Some(b.slice_chars(r.start.get(), r.end.get()))
+}
+
+#[test]
+fn word_object_inner_and_around() {
+    let b = TextBuffer::from_str("let foo.bar = 1;");
+    let word = TextObjectKind::Word;
+    assert_eq!(object_text(&b, Pos::new(0, 5), word, true).unwrap(), "foo");
+    // Punctuation is its own run.
+    assert_eq!(object_text(&b, Pos::new(0, 7), word, true).unwrap(), ".");
+    assert_eq!(
+        object_text(&b, Pos::new(0, 1), word, false).unwrap(),
+        "let "
+    );
+    // No whitespace after the last word: take the whitespace before it.
+    assert_eq!(object_text(&b, Pos::new(0, 15), word, false).unwrap(), ";");
+    assert_eq!(object_text(&b, Pos::new(0, 12), word, false).unwrap(), "= ");
+    assert_eq!(
+        object_text(&b, Pos::new(0, 3), word, false).unwrap(),
+        " foo"
+    );
+}
+
+#[test]
+fn quote_object_inner_and_around() {
+    let b = TextBuffer::from_str(r#"say("hi \"there\"", 'x')"#);
+    let dq = TextObjectKind::Quote('"');
+    assert_eq!(
+        object_text(&b, Pos::new(0, 6), dq, true).unwrap(),
+        r#"hi \"there\""#
+    );
+    assert_eq!(
+        object_text(&b, Pos::new(0, 4), dq, false).unwrap(),
+        r#""hi \"there\"""#
+    );
+    // Before the first pair, the next pair on the line is used.
+    assert_eq!(
+        object_text(&b, Pos::new(0, 0), dq, true).unwrap(),
+        r#"hi \"there\""#
+    );
+    assert_eq!(
+        object_text(&b, Pos::new(0, 0), TextObjectKind::Quote('`'), true),
+        None
+    );
+}
+
+#[test]
+fn bracket_object_on_nested_content() {
+    let b = TextBuffer::from_str("f(a, (b + c), d)");
+    let paren = TextObjectKind::Bracket('(', ')');
+    // Inside the nested pair.
+    assert_eq!(
+        object_text(&b, Pos::new(0, 7), paren, false).unwrap(),
+        "(b + c)"
+    );
+    assert_eq!(
+        object_text(&b, Pos::new(0, 7), paren, true).unwrap(),
+        "b + c"
+    );
+    // Outside it, the nested pair is skipped.
+    assert_eq!(
+        object_text(&b, Pos::new(0, 14), paren, false).unwrap(),
+        "(a, (b + c), d)"
+    );
+    // On a bracket, that bracket's pair is used.
+    assert_eq!(
+        object_text(&b, Pos::new(0, 11), paren, true).unwrap(),
+        "b + c"
+    );
+    assert_eq!(object_text(&b, Pos::new(0, 0), paren, true), None);
+}
+
+#[test]
+fn bracket_object_spans_lines() {
+    let b = TextBuffer::from_str("fn f() {\n    x\n}");
+    let brace = TextObjectKind::Bracket('{', '}');
+    assert_eq!(
+        object_text(&b, Pos::new(1, 4), brace, true).unwrap(),
+        "\n    x\n"
+    );
+}
+
 #[test]
 fn line_len_excludes_newline() {
     let b = TextBuffer::from_str("a\nbb\n");
@@ -87,6 +270,269 @@ fn line_range_excludes_newline() {
     assert_eq!(b.slice_chars(r1.start, r1.end), "bb");
 }
 
+#[test]
+fn snapshot_restore_rolls_back_content_and_content_id() {
+    let mut b = TextBuffer::from_str("hello");
+    b.insert(Pos::new(0, 5), "!");
+    let snap = b.snapshot();
+    let content_id = b.content_id();
+    assert_eq!(snap.generation(), b.generation());
+
+    b.insert(Pos::new(0, 0), "oh ");
+    b.delete_range(Pos::new(0, 3), Pos::new(0, 5));
+    assert_eq!(b.to_string(), "oh llo!");
+    let edited = b.generation();
+
+    b.restore(snap);
+    assert_eq!(b.to_string(), "hello!");
+    assert_eq!(b.content_id(), content_id);
+    // The generation never goes back, so it can't repeat one seen with other text.
+    assert!(b.generation() > edited);
+}
+
+#[test]
+fn content_ids_differ_after_restore_and_edit() {
+    let mut b = TextBuffer::from_str("a");
+    let snap = b.snapshot();
+    b.insert(Pos::new(0, 1), "b");
+    let saved = b.content_id();
+
+    // Back to the snapshot, then one different edit: the same number of edits as
+    // the saved content, but not the same text.
+    b.restore(snap);
+    b.insert(Pos::new(0, 1), "c");
+    assert_ne!(b.content_id(), saved);
+}
+
+#[test]
+fn char_index_edits_match_pos_edits() {
+    let mut by_pos = TextBuffer::from_str("ab\ncd");
+    let mut by_char = by_pos.clone();
+
+    let cursor = by_pos.insert(Pos::new(1, 1), "XY");
+    let end = by_char.insert_at(4, "XY");
+    assert_eq!(by_char.to_string(), by_pos.to_string());
+    assert_eq!(by_char.char_to_pos(end), cursor);
+
+    let cursor = by_pos.delete_range(Pos::new(1, 2), Pos::new(0, 1));
+    let start = by_char.delete_chars(1..5);
+    assert_eq!(by_char.to_string(), "aYd");
+    assert_eq!(by_char.to_string(), by_pos.to_string());
+    assert_eq!(by_char.char_to_pos(start), cursor);
+
+    // Out-of-range indices clamp to the end, like `apply_edit`.
+    assert_eq!(by_char.insert_at(99, "!"), 4);
+    assert_eq!(by_char.delete_chars(1..99), 1);
+    assert_eq!(by_char.to_string(), "a");
+}
+
+#[test]
+fn apply_edits_uses_original_coordinates() {
+    let mut b = TextBuffer::from_str("let x = 1;\nlet y = 2;");
+    let edits = vec![
+        Edit::replace(15..16, "z"),
+        Edit::replace(0..3, "const"),
+        Edit::insert(10, " // one"),
+        Edit::delete(20..21),
+        Edit::replace(8..9, "10"),
+    ];
+    b.apply_edits(edits).unwrap();
+    assert_eq!(b.to_string(), "const x = 10; // one\nlet z = 2");
+}
+
+#[test]
+fn apply_edits_rejects_overlap_without_applying() {
+    let mut b = TextBuffer::from_str("abcdef");
+    let generation = b.generation();
+
+    let overlap = vec![Edit::delete(1..4), Edit::replace(3..5, "x")];
+    assert!(b.apply_edits(overlap).is_err());
+    assert!(b.apply_edits(vec![Edit::insert(7, "!")]).is_err());
+    assert_eq!(b.to_string(), "abcdef");
+    assert_eq!(b.generation(), generation);
+
+    // Touching ranges are fine.
+    b.apply_edits(vec![Edit::delete(3..4), Edit::delete(1..3)])
+        .unwrap();
+    assert_eq!(b.to_string(), "aef");
+}
+
+#[test]
+fn inverted_edits_restore_content() {
+    let original = "héllo\nwörld";
+    for edit in [
+        Edit::insert(2, "ÿé"),
+        Edit::delete(3..8),
+        Edit::replace(6..11, "there, friend"),
+    ] {
+        let mut b = TextBuffer::from_str(original);
+        let inverse = edit.invert(&b);
+        b.apply_edit(edit);
+        assert_ne!(b.to_string(), original);
+        b.apply_edit(inverse);
+        assert_eq!(b.to_string(), original);
+    }
+}
+
+#[test]
+fn invert_captures_replaced_text() {
+    let b = TextBuffer::from_str("abcdef");
+    assert_eq!(Edit::insert(1, "xyz").invert(&b), Edit::replace(1..4, ""));
+    assert_eq!(Edit::delete(1..3).invert(&b), Edit::replace(1..1, "bc"));
+    assert_eq!(
+        Edit::replace(2..5, "Q").invert(&b),
+        Edit::replace(2..3, "cde")
+    );
+}
+
+#[test]
+fn matches_are_lazy_and_non_overlapping() {
+    let b = TextBuffer::from_str("aaaa Foo foo food");
+    let opts = SearchOptions::default();
+    let starts =
+        |needle, opts| -> Vec<usize> { b.matches(needle, opts).map(|r| r.start.get()).collect() };
+
+    assert_eq!(starts("aa", opts), vec![0, 2]);
+    assert_eq!(starts("foo", opts), vec![9, 13]);
+    let ignore_case = SearchOptions {
+        ignore_case: true,
+        ..opts
+    };
+    assert_eq!(starts("foo", ignore_case), vec![5, 9, 13]);
+    let whole_word = SearchOptions {
+        whole_word: true,
+        ..ignore_case
+    };
+    assert_eq!(starts("foo", whole_word), vec![5, 9]);
+    assert_eq!(starts("", opts), Vec::<usize>::new());
+    assert_eq!(b.matches("foo", opts).take(1).count(), 1);
+}
+
+#[test]
+fn matches_from_skips_the_text_before() {
+    let b = TextBuffer::from_str("foo xfoo foo foo");
+    let opts = SearchOptions {
+        whole_word: true,
+        ..SearchOptions::default()
+    };
+    let starts = |needle, from| -> Vec<usize> {
+        b.matches_from(needle, opts, from)
+            .map(|r| r.start.get())
+            .collect()
+    };
+
+    assert_eq!(starts("foo", 0), vec![0, 9, 13]);
+    // The "x" before char 5 still makes "foo" there part of a word.
+    assert_eq!(starts("foo", 5), vec![9, 13]);
+    assert_eq!(starts("foo", 10), vec![13]);
+    assert_eq!(starts("foo", 99), Vec::<usize>::new());
+}
+
+#[test]
+fn matches_straddling_chunk_boundaries_are_found() {
+    let b = TextBuffer::from_str(&"NEEDLE ".repeat(2000));
+    let found = b.find_all("NEEDLE", SearchOptions::default());
+    assert_eq!(found.len(), 2000);
+    assert!(
+        found
+            .iter()
+            .enumerate()
+            .all(|(i, r)| r.start.get() == i * 7)
+    );
+
+    // Make sure the test actually exercises a match split across chunks.
+    let mut boundary = 0;
+    let split = b.rope().chunks().any(|chunk| {
+        boundary += chunk.chars().count();
+        found
+            .iter()
+            .any(|r| r.start.get() < boundary && boundary < r.end.get())
+    });
+    assert!(split);
+}
+
+#[test]
+fn read_only_buffer_ignores_edits() {
+    let mut b = TextBuffer::from_str("abc");
+    b.set_read_only(true);
+    let generation = b.generation();
+
+    assert_eq!(b.insert(Pos::new(0, 1), "X"), Pos::new(0, 1));
+    let sel = b.backspace(Selection::empty(Pos::new(0, 2)));
+    assert_eq!(sel.cursor, Pos::new(0, 2));
+    assert_eq!(b.apply_edit(Edit::replace(0..3, "xyz")), Pos::new(0, 0));
+    b.insert_newline(Selection::empty(Pos::new(0, 0)));
+    assert!(b.try_insert(Pos::new(0, 0), "X").is_err());
+    assert!(b.try_delete(Pos::new(0, 0), Pos::new(0, 1)).is_err());
+    assert!(b.apply_edits(vec![Edit::delete(0..1)]).is_err());
+
+    assert_eq!(b.to_string(), "abc");
+    assert_eq!(b.generation(), generation);
+
+    b.set_read_only(false);
+    assert_eq!(b.try_insert(Pos::new(0, 3), "d").unwrap(), Pos::new(0, 4));
+    assert_eq!(b.to_string(), "abcd");
+}
+
+fn read_only(text: &str) -> TextBuffer {
+    let mut b = TextBuffer::from_str(text);
+    b.set_read_only(true);
+    b
+}
+
+#[test]
+fn read_only_expand_tabs_replaces_nothing() {
+    let mut b = read_only("\ta\tb");
+    assert_eq!(b.expand_tabs(4), 0);
+    assert_eq!(b.to_string(), "\ta\tb");
+}
+
+#[test]
+fn read_only_tabify_leading_changes_nothing() {
+    let mut b = read_only("    a\n        b");
+    assert_eq!(b.tabify_leading(4), 0);
+    assert_eq!(b.to_string(), "    a\n        b");
+}
+
+#[test]
+fn read_only_normalize_line_endings_converts_nothing() {
+    let mut b = read_only("a\r\nb\rc");
+    assert_eq!(b.normalize_line_endings(LineEnding::Lf), 0);
+    assert_eq!(b.to_string(), "a\r\nb\rc");
+}
+
+#[test]
+fn read_only_reflow_selects_the_untouched_lines() {
+    let mut b = read_only("one two three\nfour");
+    let sel = b.reflow_lines(0, 1, 5);
+    assert_eq!(sel, Selection::new(Pos::new(0, 0), Pos::new(1, 4)));
+    assert_eq!(b.to_string(), "one two three\nfour");
+}
+
+#[test]
+fn read_only_replace_all_smart_case_replaces_nothing() {
+    let mut b = read_only("foo Foo");
+    assert_eq!(b.replace_all_smart_case("foo", "bar"), 0);
+    assert_eq!(b.to_string(), "foo Foo");
+}
+
+#[test]
+fn read_only_replace_in_range_replaces_nothing() {
+    let mut b = read_only("a a a");
+    assert_eq!(
+        b.replace_in_range(0..5, "a", "b", SearchOptions::default()),
+        0
+    );
+    assert_eq!(b.to_string(), "a a a");
+}
+
+#[test]
+fn read_only_increment_number_finds_nothing_to_change() {
+    let mut b = read_only("x 41");
+    assert_eq!(b.increment_number(Pos::new(0, 0), 1), None);
+    assert_eq!(b.to_string(), "x 41");
+}
+
 #[test]
 fn apply_edit_replace() {
     let mut b = TextBuffer::from_str("kitten");
@@ -95,3 +541,1025 @@ fn apply_edit_replace() {
     assert_eq!(b.to_string(), "smitten");
     assert_eq!(cur, Pos::new(0, 4));
 }
+
+#[test]
+fn generation_bumps_on_mutation_only() {
+    let mut b = TextBuffer::from_str("abc");
+    let g0 = b.generation();
+
+    let _ = b.slice_chars(0, 2);
+    assert_eq!(b.generation(), g0);
+
+    b.insert(Pos::new(0, 3), "d");
+    let g1 = b.generation();
+    assert!(g1 > g0);
+
+    // No-op edits leave the generation alone.
+    b.delete_range(Pos::new(0, 1), Pos::new(0, 1));
+    b.backspace(Selection::empty(Pos::zero()));
+    assert_eq!(b.generation(), g1);
+
+    b.apply_edit(Edit::delete(0..1));
+    assert!(b.generation() > g1);
+}
+
+#[test]
+fn reflow_breaks_long_paragraph_at_words() {
+    let mut b = TextBuffer::from_str("  the quick brown fox jumps over the lazy dog\nnext");
+    let sel = b.reflow_lines(0, 0, 16);
+    assert_eq!(
+        b.to_string(),
+        "  the quick\n  brown fox\n  jumps over the\n  lazy dog\nnext"
+    );
+    assert_eq!(sel.ordered(), (Pos::new(0, 0), Pos::new(3, 10)));
+}
+
+#[test]
+fn reflow_joins_short_lines_and_keeps_blank_separators() {
+    let mut b = TextBuffer::from_str("a b\nc d\n\ne\nf\n");
+    b.reflow_lines(0, 4, 80);
+    assert_eq!(b.to_string(), "a b c d\n\ne f\n");
+}
+
+#[test]
+fn reflow_keeps_overlong_words_whole() {
+    let mut b = TextBuffer::from_str("hi supercalifragilistic yo");
+    b.reflow_lines(0, 0, 5);
+    assert_eq!(b.to_string(), "hi\nsupercalifragilistic\nyo");
+}
+
+#[test]
+fn blank_line_detection() {
+    let b = TextBuffer::from_str("\n  \t\nx\n  y \r\n \r\n");
+    assert!(b.is_blank_line(0));
+    assert!(b.is_blank_line(1));
+    assert!(!b.is_blank_line(2));
+    assert!(!b.is_blank_line(3));
+    assert!(b.is_blank_line(4));
+    // The empty last line, and anything past it after clamping.
+    assert!(b.is_blank_line(5));
+    assert!(b.is_blank_line(99));
+}
+
+#[test]
+fn nonblank_line_navigation() {
+    // Blank runs at the start (0-1), middle (3-5) and end (7-8).
+    let b = TextBuffer::from_str("\n \na\n\n\t\n\nb\n  \n");
+    assert_eq!(b.next_nonblank_line(0), Some(2));
+    assert_eq!(b.next_nonblank_line(2), Some(6));
+    assert_eq!(b.next_nonblank_line(6), None);
+    assert_eq!(b.prev_nonblank_line(8), Some(6));
+    assert_eq!(b.prev_nonblank_line(6), Some(2));
+    assert_eq!(b.prev_nonblank_line(2), None);
+    assert_eq!(b.prev_nonblank_line(99), Some(6));
+    assert_eq!(b.next_nonblank_line(99), None);
+}
+
+#[test]
+fn context_around_clamps_to_buffer() {
+    let b = TextBuffer::from_str("l0\nl1\nl2\nl3\nl4\nl5");
+    let lines = |ctx: Vec<(usize, String)>| ctx.into_iter().map(|(l, _)| l).collect::<Vec<_>>();
+
+    let top = b.context_around(1, 3, 1);
+    assert_eq!(
+        top,
+        vec![(0, "l0".into()), (1, "l1".into()), (2, "l2".into())]
+    );
+    assert_eq!(lines(b.context_around(3, 2, 1)), vec![1, 2, 3, 4]);
+    assert_eq!(lines(b.context_around(4, 0, 5)), vec![4, 5]);
+    assert_eq!(lines(b.context_around(2, 0, 0)), vec![2]);
+}
+
+#[test]
+fn expand_tabs_is_column_aware() {
+    let mut b = TextBuffer::from_str("\tx\nab\tc\na\tb\tc");
+    let n = b.expand_tabs(4);
+    assert_eq!(n, 4);
+    assert_eq!(b.to_string(), "    x\nab  c\na   b   c");
+}
+
+#[test]
+fn normalize_lf_to_crlf() {
+    let mut b = TextBuffer::from_str("a\nb\n\nc");
+    assert_eq!(b.normalize_line_endings(LineEnding::Crlf), 3);
+    assert_eq!(b.to_string(), "a\r\nb\r\n\r\nc");
+    assert_eq!(b.normalize_line_endings(LineEnding::Crlf), 0);
+}
+
+#[test]
+fn normalize_mixed_to_lf() {
+    let mut b = TextBuffer::from_str("a\r\nb\rc\nd\r\r\ne\r");
+    assert_eq!(b.normalize_line_endings(LineEnding::Lf), 5);
+    assert_eq!(b.to_string(), "a\nb\nc\nd\n\ne\n");
+    assert_eq!(b.len_lines(), 7);
+}
+
+#[test]
+fn tabify_leading_converts_indent_only() {
+    let mut b = TextBuffer::from_str("        x\n      y\n  \tz\na    b");
+    let n = b.tabify_leading(4);
+    assert_eq!(n, 3);
+    assert_eq!(b.to_string(), "\t\tx\n\t  y\n\tz\na    b");
+}
+
+#[test]
+fn content_hash_and_eq_ignore_edit_history() {
+    let big = "lorem ipsum dolor\n".repeat(2_000);
+
+    let a = TextBuffer::from_str(&big);
+    let mut b = TextBuffer::new();
+    for line in big.split_inclusive('\n').rev() {
+        b.insert(Pos::zero(), line);
+    }
+
+    assert_eq!(a.content_hash(), b.content_hash());
+    assert_eq!(a.content_hash(), crate::text::hash64(&big));
+    assert!(a.content_eq(&b));
+
+    b.insert(Pos::zero(), "x");
+    assert_ne!(a.content_hash(), b.content_hash());
+    assert!(!a.content_eq(&b));
+}
+
+#[test]
+fn reload_from_file_clamps_cursor_to_shorter_content() {
+    let file = TempFile::new("reload.txt");
+    let path = file.path();
+    std::fs::write(path, "short\n").unwrap();
+
+    let mut b = TextBuffer::from_str("a much longer line\nand another\nand a third\n");
+    let g = b.generation();
+    let cur = b.reload_from_file(path, Pos::new(2, 9)).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(b.to_string(), "short\n");
+    assert_eq!(cur, Pos::new(1, 0));
+    assert!(b.generation() > g);
+
+    // Missing files are an error and leave the buffer alone.
+    assert!(b.reload_from_file(path, cur).is_err());
+    assert_eq!(b.to_string(), "short\n");
+}
+
+#[test]
+fn read_file_into_inserts_lines_below() {
+    let file = TempFile::new("read_into.txt");
+    let path = file.path();
+    std::fs::write(path, "inserted 1\ninserted 2\n").unwrap();
+
+    let mut b = TextBuffer::from_str("first\nsecond\nthird\n");
+    let cur = b.read_file_into(path, 0).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(
+        b.to_string(),
+        "first\ninserted 1\ninserted 2\nsecond\nthird\n"
+    );
+    assert_eq!(cur, Pos::new(1, 0));
+
+    let err = b.read_file_into(path, 0).unwrap_err();
+    assert!(err.to_string().starts_with("failed to read file"));
+    assert_eq!(b.len_lines(), 6);
+}
+
+#[test]
+fn block_insert_pads_short_lines() {
+    let mut b = TextBuffer::from_str("abcdef\nab\nabcd\n");
+    let sels = b.block_insert(0, 3, 3, "XY", true);
+    assert_eq!(b.to_string(), "abcXYdef\nab XY\nabcXYd\n");
+    assert_eq!(sels.len(), 3);
+    assert_eq!(
+        sels.primary(),
+        Some(Selection::new(Pos::new(0, 3), Pos::new(0, 5)))
+    );
+    assert_eq!(sels.selections[1].cursor, Pos::new(1, 5));
+}
+
+#[test]
+fn block_insert_can_skip_short_lines() {
+    let mut b = TextBuffer::from_str("abcdef\nab\nabc");
+    let sels = b.block_insert(2, 0, 3, "|", false);
+    assert_eq!(b.to_string(), "abc|def\nab\nabc|");
+    let lines: Vec<usize> = sels.selections.iter().map(|s| s.anchor.line).collect();
+    assert_eq!(lines, vec![0, 2]);
+}
+
+#[test]
+fn block_delete_clamps_to_short_lines() {
+    let mut b = TextBuffer::from_str("abcdef\nab\nabcd\nabcdefgh");
+    let pos = b.block_delete(0, 3, 2, 5);
+    assert_eq!(b.to_string(), "abf\nab\nab\nabfgh");
+    assert_eq!(pos, Pos::new(0, 2));
+
+    let mut b = TextBuffer::from_str("x\nabcdef");
+    assert_eq!(b.block_delete(1, 0, 9, 3), Pos::new(0, 1));
+    assert_eq!(b.to_string(), "x\nabc");
+}
+
+/// Whether `program` can be spawned here; filter tests are skipped otherwise.
+fn has_program(program: &str) -> bool {
+    std::process::Command::new(program)
+        .arg("--version")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok()
+}
+
+#[test]
+fn filter_lines_through_sort() {
+    if !has_program("sort") {
+        return;
+    }
+    let mut b = TextBuffer::from_str("keep\ncherry\napple\nbanana\nkeep too\n");
+    let pos = b.filter_lines(3, 1, "sort", &[]).unwrap();
+    assert_eq!(pos, Pos::new(1, 0));
+    assert_eq!(b.to_string(), "keep\napple\nbanana\ncherry\nkeep too\n");
+}
+
+#[test]
+fn filter_lines_failure_leaves_buffer_unchanged() {
+    if !has_program("tr") {
+        return;
+    }
+    let mut b = TextBuffer::from_str("abc\ndef");
+    b.filter_lines(0, 1, "tr", &["a-z", "A-Z"]).unwrap();
+    assert_eq!(b.to_string(), "ABC\nDEF");
+
+    let generation = b.generation();
+    let err = b.filter_lines(0, 1, "tr", &["--no-such-flag"]);
+    assert!(err.is_err());
+    assert!(b.filter_lines(0, 1, "redox-no-such-program", &[]).is_err());
+    assert_eq!(b.to_string(), "ABC\nDEF");
+    assert_eq!(b.generation(), generation);
+}
+
+#[test]
+fn increment_plain_integer() {
+    let mut b = TextBuffer::from_str("x = 41;");
+    assert_eq!(b.increment_number(Pos::new(0, 0), 1), Some(Pos::new(0, 5)));
+    assert_eq!(b.to_string(), "x = 42;");
+
+    // On the number itself, and going negative.
+    assert_eq!(
+        b.increment_number(Pos::new(0, 5), -50),
+        Some(Pos::new(0, 5))
+    );
+    assert_eq!(b.to_string(), "x = -8;");
+    assert_eq!(b.increment_number(Pos::new(0, 4), 10), Some(Pos::new(0, 4)));
+    assert_eq!(b.to_string(), "x = 2;");
+}
+
+#[test]
+fn increment_keeps_leading_zero_width() {
+    let mut b = TextBuffer::from_str("frame_007.png");
+    assert_eq!(b.increment_number(Pos::new(0, 2), 3), Some(Pos::new(0, 8)));
+    assert_eq!(b.to_string(), "frame_010.png");
+    b.increment_number(Pos::new(0, 0), 995);
+    assert_eq!(b.to_string(), "frame_1005.png");
+}
+
+#[test]
+fn increment_without_number_is_noop() {
+    let mut b = TextBuffer::from_str("12 abc\nno digits");
+    assert_eq!(b.increment_number(Pos::new(0, 3), 1), None);
+    assert_eq!(b.increment_number(Pos::new(1, 0), 1), None);
+    assert_eq!(b.to_string(), "12 abc\nno digits");
+    assert_eq!(b.generation(), 0);
+}
+
+#[test]
+fn insert_repeated_counts() {
+    let mut b = TextBuffer::from_str("ab");
+    assert_eq!(b.insert_repeated(Pos::new(0, 1), "xy", 0), Pos::new(0, 1));
+    assert_eq!(b.generation(), 0);
+
+    assert_eq!(b.insert_repeated(Pos::new(0, 1), "xy", 1), Pos::new(0, 3));
+    assert_eq!(b.to_string(), "axyb");
+    assert_eq!(b.generation(), 1);
+
+    assert_eq!(b.insert_repeated(Pos::new(0, 0), "-\n", 3), Pos::new(3, 0));
+    assert_eq!(b.to_string(), "-\n-\n-\naxyb");
+    assert_eq!(b.generation(), 2);
+}
+
+#[test]
+fn yank_and_paste_two_lines() {
+    let mut b = TextBuffer::from_str("one\ntwo\nthree");
+    let yanked = b.yank_lines(0, 1);
+    assert_eq!(yanked, "one\ntwo\n");
+
+    assert_eq!(b.paste_lines(1, &yanked), Pos::new(2, 0));
+    assert_eq!(b.to_string(), "one\ntwo\none\ntwo\nthree");
+
+    // Below the unterminated last line.
+    assert_eq!(b.paste_lines(4, &yanked), Pos::new(5, 0));
+    assert_eq!(b.to_string(), "one\ntwo\none\ntwo\nthree\none\ntwo");
+}
+
+#[test]
+fn yank_final_line_adds_newline() {
+    let mut b = TextBuffer::from_str("a\nlast");
+    let yanked = b.yank_lines(9, 1);
+    assert_eq!(yanked, "last\n");
+
+    b.paste_lines(0, &yanked);
+    assert_eq!(b.to_string(), "a\nlast\nlast");
+    assert_eq!(b.yank_lines(0, 2), "a\nlast\nlast\n");
+}
+
+#[test]
+fn overwrite_in_middle_of_line() {
+    let mut b = TextBuffer::from_str("hello world\nnext");
+    assert_eq!(b.overwrite(Pos::new(0, 6), "W"), Pos::new(0, 7));
+    assert_eq!(b.to_string(), "hello World\nnext");
+    assert_eq!(b.overwrite(Pos::new(0, 0), "J"), Pos::new(0, 1));
+    assert_eq!(b.to_string(), "Jello World\nnext");
+}
+
+#[test]
+fn overwrite_past_line_end_inserts() {
+    let mut b = TextBuffer::from_str("abc\nnext");
+    assert_eq!(b.overwrite(Pos::new(0, 1), "XYZW"), Pos::new(0, 5));
+    assert_eq!(b.to_string(), "aXYZW\nnext");
+
+    // A typed newline splits the line, then typing goes on over what's left.
+    assert_eq!(b.overwrite(Pos::new(1, 0), "N\nQ"), Pos::new(2, 1));
+    assert_eq!(b.to_string(), "aXYZW\nN\nQxt");
+}
+
+#[test]
+fn indent_analysis_flags_mixed_lines() {
+    let b = TextBuffer::from_str(
+        "fn f() {\n\tlet a;\n    let b;\n\t  let c;\n  \tlet d;\n \t \n\t\tx\n}",
+    );
+    let report = b.indent_analysis();
+    assert_eq!(
+        report,
+        IndentReport {
+            tabs: 2,
+            spaces: 1,
+            mixed_lines: vec![3, 4],
+        }
+    );
+}
+
+#[test]
+fn anchor_bias_at_insertion_point() {
+    let mut b = TextBuffer::from_str("hello world");
+    let before = b.create_anchor(Pos::new(0, 6), Bias::Before);
+    let after = b.create_anchor(Pos::new(0, 6), Bias::After);
+    let later = b.create_anchor(Pos::new(0, 8), Bias::Before);
+
+    b.insert(Pos::new(0, 6), "big ");
+    assert_eq!(b.resolve(before), Some(Pos::new(0, 6)));
+    assert_eq!(b.resolve(after), Some(Pos::new(0, 10)));
+    assert_eq!(b.resolve(later), Some(Pos::new(0, 12)));
+
+    b.insert(Pos::new(0, 0), "oh,\n");
+    assert_eq!(b.resolve(before), Some(Pos::new(1, 6)));
+}
+
+#[test]
+fn anchor_inside_deletion_collapses() {
+    let mut b = TextBuffer::from_str("one two three");
+    let inside = b.create_anchor(Pos::new(0, 5), Bias::After);
+    let at_end = b.create_anchor(Pos::new(0, 8), Bias::Before);
+    let before = b.create_anchor(Pos::new(0, 2), Bias::After);
+
+    b.delete_range(Pos::new(0, 4), Pos::new(0, 8));
+    assert_eq!(b.to_string(), "one three");
+    assert_eq!(b.resolve(inside), Some(Pos::new(0, 4)));
+    assert_eq!(b.resolve(at_end), Some(Pos::new(0, 4)));
+    assert_eq!(b.resolve(before), Some(Pos::new(0, 2)));
+
+    b.remove_anchor(inside);
+    assert_eq!(b.resolve(inside), None);
+}
+
+#[test]
+fn smart_case_replace_follows_each_match() {
+    let mut b = TextBuffer::from_str("foo Foo FOO fOo\nfood");
+    assert_eq!(b.replace_all_smart_case("foo", "bar"), 5);
+    assert_eq!(b.to_string(), "bar Bar BAR bar\nbard");
+
+    let mut b = TextBuffer::from_str("Old_name, old_name, OLD_NAME");
+    assert_eq!(b.replace_all_smart_case("old_name", "new_id"), 3);
+    assert_eq!(b.to_string(), "New_id, new_id, NEW_ID");
+
+    // A lowercase match lowercases a capitalised replacement.
+    let mut b = TextBuffer::from_str("foo Foo");
+    assert_eq!(b.replace_all_smart_case("foo", "Bar"), 2);
+    assert_eq!(b.to_string(), "bar Bar");
+}
+
+#[test]
+fn join_multi_line_selection() {
+    let mut b = TextBuffer::from_str("fn f(\n    a,\n    b\n)\nnext");
+    let sel = Selection::new(Pos::new(3, 0), Pos::new(0, 2));
+    assert_eq!(b.join_selection(sel), Pos::new(0, 10));
+    assert_eq!(b.to_string(), "fn f( a, b)\nnext");
+}
+
+#[test]
+fn join_single_line_selection_joins_next() {
+    let mut b = TextBuffer::from_str("one \n\ttwo\n\nthree");
+    assert_eq!(
+        b.join_selection(Selection::empty(Pos::new(0, 1))),
+        Pos::new(0, 4)
+    );
+    assert_eq!(b.to_string(), "one two\n\nthree");
+
+    // Joining an empty line adds no space.
+    assert_eq!(b.join_lines(1, 1), Pos::new(1, 0));
+    assert_eq!(b.to_string(), "one two\nthree");
+
+    // Nothing to join on the last line.
+    assert_eq!(b.join_lines(1, 1), Pos::new(1, 5));
+    assert_eq!(b.to_string(), "one two\nthree");
+}
+
+#[test]
+fn join_last_line_keeps_trailing_newline() {
+    let mut b = TextBuffer::from_str("a\nb\n");
+    assert_eq!(b.join_lines(1, 1), Pos::new(1, 1));
+    assert_eq!(b.to_string(), "a\nb\n");
+
+    assert_eq!(b.join_lines(0, 5), Pos::new(0, 1));
+    assert_eq!(b.to_string(), "a b\n");
+}
+
+#[test]
+fn effective_len_lines_ignores_phantom_line() {
+    let with_newline = TextBuffer::from_str("a\n");
+    let without = TextBuffer::from_str("a");
+    assert_eq!(with_newline.len_lines(), 2);
+    assert_eq!(with_newline.effective_len_lines(), 1);
+    assert_eq!(without.len_lines(), 1);
+    assert_eq!(without.effective_len_lines(), 1);
+    assert_eq!(TextBuffer::new().effective_len_lines(), 1);
+    assert_eq!(TextBuffer::from_str("a\n\n").effective_len_lines(), 2);
+
+    // `j` stops on the real last line...
+    assert_eq!(with_newline.move_down(Pos::new(0, 0)), Pos::new(0, 0));
+    // ...but the phantom line is still addressable for editing.
+    assert_eq!(with_newline.clamp_line(5), 1);
+}
+
+#[test]
+fn append_line_end_positions() {
+    let b = TextBuffer::from_str("hello\n\nworld");
+    assert_eq!(b.append_line_end(0), Pos::new(0, 5));
+    assert_eq!(b.append_line_end(1), Pos::new(1, 0));
+    assert_eq!(b.append_line_end(9), Pos::new(2, 5));
+}
+
+#[test]
+fn open_line_below_and_above() {
+    let mut b = TextBuffer::from_str("fn f() {\n    x\n}");
+    assert_eq!(b.open_line_below(2, ""), Pos::new(3, 0));
+    assert_eq!(b.to_string(), "fn f() {\n    x\n}\n");
+
+    assert_eq!(b.open_line_below(1, "    "), Pos::new(2, 4));
+    assert_eq!(b.to_string(), "fn f() {\n    x\n    \n}\n");
+
+    assert_eq!(b.open_line_above(0, "\t"), Pos::new(0, 1));
+    assert_eq!(b.to_string(), "\t\nfn f() {\n    x\n    \n}\n");
+}
+
+#[test]
+fn grapheme_columns_diverge_after_combining_mark() {
+    // "cafe\u{301} ok": the accented e is one grapheme but two chars.
+    let b = TextBuffer::from_str("x\ncafe\u{301} ok");
+    assert_eq!(b.line_len_chars(1), 8);
+
+    assert_eq!(b.char_col_to_grapheme_col(Pos::new(1, 2)), 2);
+    assert_eq!(b.char_col_to_grapheme_col(Pos::new(1, 3)), 3);
+    // On the combining mark: still the `e` grapheme.
+    assert_eq!(b.char_col_to_grapheme_col(Pos::new(1, 4)), 3);
+    assert_eq!(b.char_col_to_grapheme_col(Pos::new(1, 6)), 5);
+    assert_eq!(b.char_col_to_grapheme_col(Pos::new(1, 99)), 7);
+
+    assert_eq!(b.grapheme_col_to_char_col(1, 3), 3);
+    assert_eq!(b.grapheme_col_to_char_col(1, 4), 5);
+    assert_eq!(b.grapheme_col_to_char_col(1, 6), 7);
+    assert_eq!(b.grapheme_col_to_char_col(1, 99), 8);
+}
+
+#[test]
+fn byte_slices_of_multibyte_text() {
+    // Byte widths: h 1, é 2, → 3, l 1, o 1, 🙂 4.
+    let b = TextBuffer::from_str("hé→lo🙂");
+    assert_eq!(b.len_chars(), 6);
+    assert_eq!(b.len_bytes(), 12);
+
+    assert_eq!(b.byte_slice(0..3), "hé");
+    assert_eq!(b.byte_slice(3..6), "→");
+    assert_eq!(b.byte_slice(8..12), "🙂");
+    // Offsets inside a char round down to its start.
+    assert_eq!(b.byte_slice(2..5), "é");
+    assert_eq!(b.byte_slice(9..100), "🙂");
+}
+
+#[test]
+fn input_edit_for_insert() {
+    let b = TextBuffer::from_str("héllo\nwörld");
+    // Insert "→\nab" after "wö" (char 8, byte 10).
+    let edit = Edit::insert(8, "→\nab").input_edit(&b);
+    assert_eq!(
+        edit,
+        InputEdit {
+            start_byte: 10,
+            old_end_byte: 10,
+            new_end_byte: 16,
+            start_point: BytePoint { row: 1, column: 3 },
+            old_end_point: BytePoint { row: 1, column: 3 },
+            new_end_point: BytePoint { row: 2, column: 2 },
+        }
+    );
+}
+
+#[test]
+fn input_edit_for_multi_line_delete() {
+    let b = TextBuffer::from_str("ab\nçd\nef");
+    // Delete from after "a" through "ç" on the next line.
+    let edit = Edit::delete(1..4).input_edit(&b);
+    assert_eq!(
+        edit,
+        InputEdit {
+            start_byte: 1,
+            old_end_byte: 5,
+            new_end_byte: 1,
+            start_point: BytePoint { row: 0, column: 1 },
+            old_end_point: BytePoint { row: 1, column: 2 },
+            new_end_point: BytePoint { row: 0, column: 1 },
+        }
+    );
+}
+
+#[test]
+fn input_edit_rows_only_break_at_newlines() {
+    // Ropey sees three lines before "x"; tree-sitter sees one row break.
+    let b = TextBuffer::from_str("a\rb\u{2028}c\nd\rx");
+    let edit = Edit::replace(8..9, "y\rz").input_edit(&b);
+    assert_eq!(
+        edit,
+        InputEdit {
+            start_byte: 10,
+            old_end_byte: 11,
+            new_end_byte: 13,
+            start_point: BytePoint { row: 1, column: 2 },
+            old_end_point: BytePoint { row: 1, column: 3 },
+            new_end_point: BytePoint { row: 1, column: 5 },
+        }
+    );
+}
+
+#[test]
+fn insert_tab_at_line_start() {
+    let mut b = TextBuffer::from_str("x");
+    assert_eq!(b.insert_tab_with(Pos::new(0, 0), 4, true), Pos::new(0, 4));
+    assert_eq!(b.to_string(), "    x");
+    assert_eq!(b.insert_tab_with(Pos::new(0, 0), 4, false), Pos::new(0, 1));
+    assert_eq!(b.to_string(), "\t    x");
+}
+
+#[test]
+fn insert_tab_after_tabs_mid_line() {
+    // Visual columns: `\t` 0-3, `ab` 4-5, `\t` 6-7, `c` 8; cursor after `c` is 9.
+    let mut b = TextBuffer::from_str("\tab\tcd");
+    assert_eq!(b.visual_col(Pos::new(0, 5), 4), 9);
+    assert_eq!(b.insert_tab_with(Pos::new(0, 5), 4, true), Pos::new(0, 8));
+    assert_eq!(b.to_string(), "\tab\tc   d");
+
+    // Right on a tab stop, a full tab width is inserted.
+    assert_eq!(b.insert_tab_with(Pos::new(0, 1), 4, true), Pos::new(0, 5));
+    assert_eq!(b.to_string(), "\t    ab\tc   d");
+}
+
+#[test]
+fn backspace_removes_whole_indent_level() {
+    let mut b = TextBuffer::from_str("        x");
+    let sel = b.backspace_indent_aware(Selection::empty(Pos::new(0, 8)), 4);
+    assert_eq!(sel.cursor, Pos::new(0, 4));
+    assert_eq!(b.to_string(), "    x");
+
+    // Off a tab stop, only back to the previous one.
+    let mut b = TextBuffer::from_str("      x");
+    let sel = b.backspace_indent_aware(Selection::empty(Pos::new(0, 6)), 4);
+    assert_eq!(sel.cursor, Pos::new(0, 4));
+    assert_eq!(b.to_string(), "    x");
+}
+
+#[test]
+fn backspace_indent_aware_mid_word_is_plain() {
+    let mut b = TextBuffer::from_str("    word");
+    let sel = b.backspace_indent_aware(Selection::empty(Pos::new(0, 6)), 4);
+    assert_eq!(sel.cursor, Pos::new(0, 5));
+    assert_eq!(b.to_string(), "    wrd");
+
+    // A tab before the cursor is just deleted.
+    let mut b = TextBuffer::from_str("  \tx");
+    let sel = b.backspace_indent_aware(Selection::empty(Pos::new(0, 3)), 4);
+    assert_eq!(sel.cursor, Pos::new(0, 2));
+    assert_eq!(b.to_string(), "  x");
+}
+
+#[test]
+fn leading_whitespace_predicate() {
+    let b = TextBuffer::from_str(" \t  let x;\n");
+    assert!(b.in_leading_whitespace(Pos::new(0, 0)));
+    assert!(b.in_leading_whitespace(Pos::new(0, 2)));
+    assert!(b.in_leading_whitespace(Pos::new(0, 4)));
+    assert!(!b.in_leading_whitespace(Pos::new(0, 5)));
+    assert!(!b.in_leading_whitespace(Pos::new(0, 8)));
+    assert!(b.in_leading_whitespace(Pos::new(1, 0)));
+}
+
+#[test]
+fn options_drive_insert_tab() {
+    let mut b = TextBuffer::from_str("ab");
+    assert_eq!(b.options(), &BufferOptions::default());
+    assert_eq!(b.insert_tab(Pos::new(0, 1)), Pos::new(0, 2));
+    assert_eq!(b.to_string(), "a\tb");
+
+    b.options_mut().expand_tab = true;
+    assert_eq!(b.insert_tab(Pos::new(0, 0)), Pos::new(0, 4));
+    assert_eq!(b.to_string(), "    a\tb");
+
+    b.options_mut().tab_width = 2;
+    assert_eq!(b.insert_tab(Pos::new(0, 5)), Pos::new(0, 6));
+    assert_eq!(b.to_string(), "    a \tb");
+}
+
+#[test]
+fn options_word_chars_extend_words() {
+    let mut b = TextBuffer::from_str("foo-bar baz");
+    assert_eq!(b.word_start_after(Pos::new(0, 0)), Pos::new(0, 3));
+
+    b.options_mut().word_chars.push('-');
+    assert_eq!(b.word_start_after(Pos::new(0, 0)), Pos::new(0, 8));
+    assert_eq!(
+        b.text_object(Pos::new(0, 1), TextObjectKind::Word, true)
+            .map(|r| (r.start.0, r.end.0)),
+        Some((0, 7))
+    );
+    let whole = SearchOptions {
+        whole_word: true,
+        ..SearchOptions::default()
+    };
+    assert!(b.find_all("bar", whole).is_empty());
+}
+
+#[test]
+fn line_ranges_exclude_newlines() {
+    let b = TextBuffer::from_str("ab\n\ncde");
+    assert_eq!(
+        b.line_ranges(0, 2).collect::<Vec<_>>(),
+        vec![0..2, 3..3, 4..7]
+    );
+    assert_eq!(b.line_ranges(9, 1).collect::<Vec<_>>(), vec![3..3, 4..7]);
+
+    // A trailing newline leaves an empty last line.
+    let b = TextBuffer::from_str("ab\n");
+    assert_eq!(b.line_ranges(0, 5).collect::<Vec<_>>(), vec![0..2, 3..3]);
+    for (line, range) in b.line_ranges(0, 1).enumerate() {
+        assert_eq!(range, b.line_char_range(line));
+    }
+}
+
+#[test]
+fn replace_range_across_lines() {
+    let mut b = TextBuffer::from_str("one\ntwo\nthree\nfour");
+    // Shorter: "e\ntwo\nth" -> "-".
+    assert_eq!(
+        b.replace_range(Pos::new(2, 2), Pos::new(0, 2), "-"),
+        Pos::new(0, 3)
+    );
+    assert_eq!(b.to_string(), "on-ree\nfour");
+    assert_eq!(b.generation(), 1);
+
+    // Longer, with new lines of its own.
+    assert_eq!(
+        b.replace_range(Pos::new(0, 2), Pos::new(1, 2), "1\n2\n3"),
+        Pos::new(2, 1)
+    );
+    assert_eq!(b.to_string(), "on1\n2\n3ur");
+    assert_eq!(b.generation(), 2);
+}
+
+#[test]
+fn last_edit_pos_follows_mutations() {
+    let mut b = TextBuffer::from_str("hello\nworld");
+    assert_eq!(b.last_edit_pos(), None);
+
+    b.insert(Pos::new(1, 2), "XY");
+    assert_eq!(b.last_edit_pos(), Some(Pos::new(1, 2)));
+
+    // No-op edits don't move it.
+    b.insert(Pos::new(0, 0), "");
+    assert_eq!(b.last_edit_pos(), Some(Pos::new(1, 2)));
+
+    b.delete_range(Pos::new(0, 1), Pos::new(0, 3));
+    assert_eq!(b.last_edit_pos(), Some(Pos::new(0, 1)));
+    assert_eq!(b.to_string(), "hlo\nwoXYrld");
+
+    let snap = b.snapshot();
+    b.insert(Pos::new(1, 0), "!");
+    b.restore(snap);
+    assert_eq!(b.last_edit_pos(), None);
+}
+
+#[test]
+fn line_starts_match_line_to_char() {
+    let b = TextBuffer::from_str("ä\n\nxyz\n🙂 end\n");
+    let starts = b.line_starts(0, b.len_lines() - 1);
+    let expected: Vec<usize> = (0..b.len_lines()).map(|l| b.line_to_char(l)).collect();
+    assert_eq!(starts, expected);
+    assert_eq!(starts, vec![0, 2, 3, 7, 13]);
+    assert_eq!(b.line_starts(3, 2), vec![3, 7]);
+}
+
+#[test]
+fn utf16_offsets_count_surrogate_pairs() {
+    // 🙂 and 𝄞 are outside the BMP: one char, two UTF-16 units each.
+    let b = TextBuffer::from_str("a🙂b\n𝄞é🙂x");
+    assert_eq!(b.char_to_utf16(1), 1);
+    assert_eq!(b.char_to_utf16(2), 3);
+    assert_eq!(b.char_to_utf16(4), 5);
+    assert_eq!(b.char_to_utf16(99), 11);
+
+    assert_eq!(b.utf16_to_char(3), 2);
+    assert_eq!(b.utf16_to_char(2), 1); // inside the 🙂 pair
+    assert_eq!(b.utf16_to_char(99), 8);
+
+    assert_eq!(b.pos_to_lsp(Pos::new(0, 3)), (0, 4));
+    assert_eq!(b.pos_to_lsp(Pos::new(1, 1)), (1, 2));
+    assert_eq!(b.pos_to_lsp(Pos::new(1, 3)), (1, 5));
+    assert_eq!(b.pos_to_lsp(Pos::new(1, 99)), (1, 6));
+}
+
+#[test]
+fn lsp_positions_map_back_to_char_columns() {
+    // 🙂 is two UTF-16 units; the combining accent is one unit and one char.
+    let b = TextBuffer::from_str("🙂e\u{301}x\nok");
+    assert_eq!(b.lsp_to_pos(0, 0), Pos::new(0, 0));
+    assert_eq!(b.lsp_to_pos(0, 1), Pos::new(0, 0)); // inside the pair
+    assert_eq!(b.lsp_to_pos(0, 2), Pos::new(0, 1));
+    assert_eq!(b.lsp_to_pos(0, 4), Pos::new(0, 3));
+    assert_eq!(b.lsp_to_pos(0, 5), Pos::new(0, 4));
+    assert_eq!(b.lsp_to_pos(0, 50), Pos::new(0, 4));
+    assert_eq!(b.lsp_to_pos(7, 1), Pos::new(1, 1));
+
+    for col in 0..=4 {
+        let (line, utf16) = b.pos_to_lsp(Pos::new(0, col));
+        assert_eq!(b.lsp_to_pos(line, utf16), Pos::new(0, col));
+    }
+}
+
+#[test]
+fn lsp_edit_replaces_across_lines() {
+    let mut b = TextBuffer::from_str("fn 🙂a() {\n    body\n}\n");
+    // From just after the emoji (UTF-16 col 5) to the start of line 2.
+    let cursor = b.apply_lsp_edit((0, 5), (2, 0), "b() {}\n");
+    assert_eq!(b.to_string(), "fn 🙂b() {}\n}\n");
+    assert_eq!(cursor, Pos::new(1, 0));
+    assert_eq!(b.generation(), 1);
+}
+
+#[test]
+fn search_word_under_cursor_finds_whole_words_and_wraps() {
+    let b = TextBuffer::from_str("foo bar\nfoobar foo\nbaz foo\n");
+    let at = |line, col| {
+        b.char_to_pos(
+            b.search_word_under_cursor(Pos::new(line, col), true)
+                .unwrap()
+                .start
+                .0,
+        )
+    };
+
+    // `foobar` isn't a whole-word match for `foo`.
+    assert_eq!(at(0, 1), Pos::new(1, 7));
+    assert_eq!(at(1, 7), Pos::new(2, 4));
+    // From the last occurrence, `*` wraps to the first.
+    assert_eq!(at(2, 5), Pos::new(0, 0));
+
+    let back = b.search_word_under_cursor(Pos::new(0, 0), false).unwrap();
+    assert_eq!(b.char_to_pos(back.start.0), Pos::new(2, 4));
+
+    // A single occurrence finds itself; whitespace isn't a word.
+    let baz = b.search_word_under_cursor(Pos::new(2, 0), true).unwrap();
+    assert_eq!(b.slice_chars(baz.start.0, baz.end.0), "baz");
+    assert_eq!(b.search_word_under_cursor(Pos::new(0, 3), true), None);
+}
+
+#[test]
+fn scroll_percent_edge_cases() {
+    let lines: String = (0..20).map(|i| format!("{i}\n")).collect();
+    let b = TextBuffer::from_str(&lines);
+
+    // Whole file fits: All.
+    assert_eq!(TextBuffer::from_str("a\nb\n").scroll_percent(0, 10), 100);
+    assert_eq!(b.scroll_percent(0, 20), 100);
+    // Top.
+    assert_eq!(b.scroll_percent(0, 10), 0);
+    // Exactly at the bottom (lines 10..20 visible), and past it.
+    assert_eq!(b.scroll_percent(10, 10), 100);
+    assert_eq!(b.scroll_percent(15, 10), 100);
+    // 5 lines above, 5 below.
+    assert_eq!(b.scroll_percent(5, 10), 50);
+    assert_eq!(b.scroll_percent(1, 10), 10);
+}
+
+#[test]
+fn string_buffers_have_no_path() {
+    let mut b = TextBuffer::from_str("text");
+    assert_eq!(b.path(), None);
+    assert_eq!(b.name(), "[No Name]");
+
+    b.set_path(Some("src/notes.txt".into()));
+    assert_eq!(b.path(), Some(std::path::Path::new("src/notes.txt")));
+    assert_eq!(b.name(), "notes.txt");
+}
+
+#[test]
+fn swap_is_written_every_n_edits_when_enabled() {
+    let file = TempFile::new("swap_due.txt");
+    let path = file.path();
+    let swap = crate::io::swap_path(path);
+
+    let mut b = TextBuffer::from_str("");
+    b.insert(Pos::zero(), "a");
+    // Off by default, and buffers without a path have nowhere to swap.
+    assert!(!b.swap_if_due().unwrap());
+    b.options_mut().swap_every = Some(2);
+    assert!(!b.swap_if_due().unwrap());
+    assert!(b.write_swap().is_err());
+
+    b.set_path(Some(path.to_path_buf()));
+    b.insert(Pos::zero(), "b");
+    assert!(b.swap_if_due().unwrap());
+    assert!(swap.exists());
+    b.insert(Pos::zero(), "c");
+    assert!(!b.swap_if_due().unwrap());
+    b.insert(Pos::zero(), "d");
+    assert!(b.swap_if_due().unwrap());
+
+    crate::io::remove_swap(path).unwrap();
+    assert!(!swap.exists());
+}
+
+#[test]
+fn max_line_width_finds_the_long_line() {
+    let long = "x".repeat(500);
+    let b = TextBuffer::from_str(&format!("short\n\tab\n{long}\n日本\nend"));
+
+    assert_eq!(b.max_line_width(4), 500);
+    // Bounded to lines that skip the long one.
+    assert_eq!(b.max_line_width_in_range(0, 1, 4), 6);
+    assert_eq!(b.max_line_width_in_range(1, 0, 8), 10);
+    assert_eq!(b.max_line_width_in_range(3, 99, 4), 4);
+    assert_eq!(TextBuffer::new().max_line_width(4), 0);
+}
+
+#[test]
+fn words_iterates_word_runs_with_ranges() {
+    use crate::text::{CharIdx, CharRange};
+
+    let b = TextBuffer::from_str("let foo_1 = bar(baz);\n  qux\n");
+    let words: Vec<(CharRange, String)> = b.words().collect();
+    let names: Vec<&str> = words.iter().map(|(_, w)| w.as_str()).collect();
+
+    assert_eq!(names, ["let", "foo_1", "bar", "baz", "qux"]);
+    assert_eq!(words[1].0, CharRange::new(CharIdx(4), CharIdx(9)));
+    assert_eq!(words[4].0, CharRange::new(CharIdx(24), CharIdx(27)));
+    assert_eq!(TextBuffer::from_str(" ;; ").words().count(), 0);
+
+    // A word spanning rope chunks comes out whole.
+    let long = "w".repeat(5000);
+    let b = TextBuffer::from_str(&format!("{long} end"));
+    let lens: Vec<usize> = b.words().map(|(_, w)| w.len()).collect();
+    assert_eq!(lens, [5000, 3]);
+}
+
+#[test]
+fn completion_candidates_rank_nearest_first() {
+    let b = TextBuffer::from_str("format forest\nfoo fo\nfortune format Forge\n");
+    // Completing `fo` at the end of line 1. `format` is listed once, at its
+    // nearer occurrence on line 2.
+    let cursor = Pos::new(1, 6);
+
+    assert_eq!(
+        b.completion_candidates(cursor, 10),
+        ["fortune", "foo", "forest", "format", "Forge"]
+    );
+    assert_eq!(b.completion_candidates(cursor, 2), ["fortune", "foo"]);
+
+    // An uppercase letter makes the match case-sensitive.
+    let b = TextBuffer::from_str("Forge forge Format\nFo");
+    assert_eq!(
+        b.completion_candidates(Pos::new(1, 2), 10),
+        ["Format", "Forge"]
+    );
+
+    // Nothing typed yet.
+    assert!(b.completion_candidates(Pos::new(0, 5), 10).is_empty());
+}
+
+#[test]
+fn misspellings_reports_flagged_words_in_range() {
+    struct Stub;
+    impl WordChecker for Stub {
+        fn is_misspelled(&self, word: &str) -> bool {
+            word == "teh"
+        }
+    }
+
+    let b = TextBuffer::from_str("teh cat saw teh dog\nand teh end");
+    let ranges = |range| -> Vec<(usize, usize)> {
+        b.misspellings(&Stub, range)
+            .iter()
+            .map(|r| (r.start.0, r.end.0))
+            .collect()
+    };
+
+    assert_eq!(ranges(0..b.len_chars()), [(0, 3), (12, 15), (24, 27)]);
+    // Words cut by the range are checked whole.
+    assert_eq!(ranges(13..25), [(12, 15), (24, 27)]);
+    assert_eq!(ranges(4..11), []);
+    assert_eq!(ranges(25..500), [(24, 27)]);
+}
+
+#[test]
+fn replace_in_range_leaves_text_outside_untouched() {
+    let mut b = TextBuffer::from_str("cat cat\ncat concat cat\ncat");
+    // Covers the second line plus the tail of the first; the first line's
+    // second `cat` starts before the range and is skipped.
+    let n = b.replace_in_range(5..22, "cat", "dog", SearchOptions::default());
+    assert_eq!(n, 3);
+    assert_eq!(b.to_string(), "cat cat\ndog condog dog\ncat");
+
+    let mut b = TextBuffer::from_str("cat cat\ncat concat cat\ncat");
+    let whole = SearchOptions {
+        whole_word: true,
+        ..SearchOptions::default()
+    };
+    assert_eq!(b.replace_in_range(8..23, "cat", "dog", whole), 2);
+    assert_eq!(b.to_string(), "cat cat\ndog concat dog\ncat");
+
+    assert_eq!(b.replace_in_range(4..4, "cat", "dog", whole), 0);
+    // Partly outside the range: skipped.
+    assert_eq!(b.replace_in_range(8..21, "dog", "cat", whole), 1);
+    assert_eq!(b.replace_in_range(23..500, "cat", "x", whole), 1);
+    assert_eq!(b.to_string(), "cat cat\ncat concat dog\nx");
+}
+
+#[test]
+fn matching_indent_block_spans_nested_lines_and_blanks() {
+    let b = TextBuffer::from_str(
+        "fn main() {\n    let a = 1;\n    if a {\n        one();\n\n        two();\n    }\n\n    done();\n}\n",
+    );
+    // Depth 4: the whole body, across the blank line and the nested block.
+    assert_eq!(b.matching_indent_block(1), Some((1, 8)));
+    assert_eq!(b.matching_indent_block(6), Some((1, 8)));
+    // Depth 8: just the `if` body, blank line included.
+    assert_eq!(b.matching_indent_block(3), Some((3, 5)));
+    assert_eq!(b.matching_indent_block(5), Some((3, 5)));
+    // Depth 0: everything.
+    assert_eq!(b.matching_indent_block(0), Some((0, 9)));
+    assert_eq!(b.matching_indent_block(4), None);
+
+    // Tabs count to the next tab stop.
+    let b = TextBuffer::from_str("a\n\tb\n    c\nd");
+    assert_eq!(b.matching_indent_block(2), Some((1, 2)));
+}
+
+#[test]
+fn extend_to_line_covers_whole_lines_in_either_direction() {
+    let b = TextBuffer::from_str("zero\none\ntwo!\nthree\nfour");
+    let sel = Selection::empty(Pos::new(2, 1));
+
+    // Down from the anchor.
+    let down = b.extend_to_line(sel, 3);
+    assert_eq!(down, Selection::new(Pos::new(2, 0), Pos::new(3, 5)));
+    // Just the anchor's line.
+    assert_eq!(
+        b.extend_to_line(sel, 2),
+        Selection::new(Pos::new(2, 0), Pos::new(2, 4))
+    );
+    // Up from the anchor.
+    let up = b.extend_to_line(sel, 1);
+    assert_eq!(up, Selection::new(Pos::new(2, 4), Pos::new(1, 0)));
+
+    // Crossing the anchor keeps it on its line and flips direction.
+    assert_eq!(
+        b.extend_to_line(down, 0).ordered(),
+        (Pos::new(0, 0), Pos::new(2, 4))
+    );
+    assert_eq!(
+        b.extend_to_line(up, 9).ordered(),
+        (Pos::new(2, 0), Pos::new(4, 4))
+    );
+}
@@ -0,0 +1,87 @@
+//! Positions that move with the text (`create_anchor` / `resolve`).
+//!
+//! An anchor is a char index owned by the buffer. Every edit goes through
+//! `splice`, which hands the edit's shape to [`Anchors::adjust`], so anchors stay
+//! on the same text without callers shifting anything themselves:
+//! - text inserted or deleted before an anchor shifts it;
+//! - an anchor inside a deleted range collapses to the start of the deletion;
+//! - text inserted exactly at an anchor lands after it with [`Bias::Before`] and
+//!   before it with [`Bias::After`].
+//!
+//! Bulk content swaps (`restore`, `reload_from_file`) can't be mapped, so anchors
+//! are just clamped into the new text. The same goes for edits made through
+//! `rope_mut`, which bypass `splice` entirely.
+
+use super::TextBuffer;
+use crate::buffer::Pos;
+
+/// Which side of text inserted exactly at an anchor the anchor ends up on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bias {
+    /// Stay before the inserted text (like a selection start).
+    Before,
+    /// Move past the inserted text (like a cursor typing).
+    After,
+}
+
+/// Handle to an anchor created with [`TextBuffer::create_anchor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnchorId(usize);
+
+/// The buffer's live anchors, as `(char index, bias)`; removed ones leave a hole.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Anchors {
+    slots: Vec<Option<(usize, Bias)>>,
+}
+
+impl Anchors {
+    /// Move anchors for the chars `[start, end)` being replaced by `inserted` chars.
+    pub(super) fn adjust(&mut self, start: usize, end: usize, inserted: usize) {
+        for (at, bias) in self.slots.iter_mut().flatten() {
+            *at = if *at < start {
+                *at
+            } else if *at > end || (*at == end && start < end) {
+                *at - (end - start) + inserted
+            } else {
+                match bias {
+                    Bias::Before => start,
+                    Bias::After => start + inserted,
+                }
+            };
+        }
+    }
+
+    /// Clamp every anchor into a text of `len_chars` chars.
+    pub(super) fn clamp(&mut self, len_chars: usize) {
+        for (at, _) in self.slots.iter_mut().flatten() {
+            *at = (*at).min(len_chars);
+        }
+    }
+}
+
+impl TextBuffer {
+    /// Start tracking `pos` (clamped) as an anchor with the given `bias`.
+    pub fn create_anchor(&mut self, pos: Pos, bias: Bias) -> AnchorId {
+        let at = self.pos_to_char(pos);
+        let slots = &mut self.anchors.slots;
+        let id = slots.iter().position(Option::is_none).unwrap_or_else(|| {
+            slots.push(None);
+            slots.len() - 1
+        });
+        slots[id] = Some((at, bias));
+        AnchorId(id)
+    }
+
+    /// Where the anchor is now, or `None` if it has been removed.
+    pub fn resolve(&self, id: AnchorId) -> Option<Pos> {
+        let (at, _) = self.anchors.slots.get(id.0).copied().flatten()?;
+        Some(self.char_to_pos(at.min(self.len_chars())))
+    }
+
+    /// Stop tracking an anchor. Its id may be handed out again later.
+    pub fn remove_anchor(&mut self, id: AnchorId) {
+        if let Some(slot) = self.anchors.slots.get_mut(id.0) {
+            *slot = None;
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! Content hashing and comparison for `TextBuffer`.
+//!
+//! Both helpers walk the rope's chunks directly rather than materializing the
+//! buffer as a `String`, so they are cheap enough to call on every save/reload
+//! check (eg. "nothing changed since save", or skipping a no-op file-watch reload).
+
+use super::TextBuffer;
+use crate::text::Fnv64;
+
+impl TextBuffer {
+    /// FNV-1a hash of the buffer's UTF-8 content.
+    ///
+    /// Independent of how the rope happens to be chunked, so two buffers holding
+    /// the same text hash equal regardless of their edit history. Matches
+    /// `text::hash64(&self.to_string())`.
+    pub fn content_hash(&self) -> u64 {
+        let mut h = Fnv64::new();
+        for chunk in self.rope.chunks() {
+            h.write(chunk.as_bytes());
+        }
+        h.finish()
+    }
+
+    /// Whether `self` and `other` hold exactly the same text.
+    ///
+    /// Compares lengths first, then the two chunk streams (ropey handles
+    /// mismatched chunk boundaries), without allocating.
+    pub fn content_eq(&self, other: &TextBuffer) -> bool {
+        self.rope.len_bytes() == other.rope.len_bytes() && self.rope == other.rope
+    }
+}
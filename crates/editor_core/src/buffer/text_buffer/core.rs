@@ -7,9 +7,15 @@
 //! Everything else (line indexing, movement, slicing, editing) should live in
 //! sibling modules as additional `impl TextBuffer` blocks.
 
+use std::path::{Path, PathBuf};
+
 use anyhow::{Context as _, Result};
 use ropey::Rope;
 
+use super::anchors::Anchors;
+use super::options::BufferOptions;
+use crate::buffer::Pos;
+
 /// A Ropey-backed text buffer.
 ///
 /// Invariants and conventions:
@@ -25,6 +31,33 @@ use ropey::Rope;
 #[derive(Debug, Clone)]
 pub struct TextBuffer {
     pub(super) rope: Rope,
+
+    /// Monotonic edit counter, bumped on every mutation.
+    ///
+    /// Lets caches (grapheme segmentation, wrap layout, etc.) cheaply detect
+    /// "nothing changed since last time" without rehashing line contents.
+    pub(super) generation: u64,
+
+    /// Generation the current content was first produced at (see `content_id`).
+    pub(super) content_id: u64,
+
+    /// When set, edits are ignored (see `set_read_only`).
+    pub(super) read_only: bool,
+
+    /// Live anchors, kept in step with every edit (see `create_anchor`).
+    pub(super) anchors: Anchors,
+
+    /// Buffer-local settings (see `options`).
+    pub(super) options: BufferOptions,
+
+    /// Char index where the most recent edit started (see `last_edit_pos`).
+    pub(super) last_edit: Option<usize>,
+
+    /// File the buffer belongs to, if any (see `path`).
+    pub(super) path: Option<PathBuf>,
+
+    /// Generation the last swap file was written at (see `swap_if_due`).
+    pub(super) swap_generation: u64,
 }
 
 impl Default for TextBuffer {
@@ -37,14 +70,49 @@ impl TextBuffer {
     /// Create an empty buffer
     #[inline]
     pub fn new() -> Self {
-        Self { rope: Rope::new() }
+        Self {
+            rope: Rope::new(),
+            generation: 0,
+            content_id: 0,
+            read_only: false,
+            anchors: Anchors::default(),
+            options: BufferOptions::default(),
+            last_edit: None,
+            path: None,
+            swap_generation: 0,
+        }
     }
 
     /// Create a buffer from UTF-8 text
     #[inline]
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         Self {
             rope: Rope::from_str(s),
+            generation: 0,
+            content_id: 0,
+            read_only: false,
+            anchors: Anchors::default(),
+            options: BufferOptions::default(),
+            last_edit: None,
+            path: None,
+            swap_generation: 0,
+        }
+    }
+
+    /// Wrap an existing rope (eg. one streamed in with `Rope::from_reader`).
+    #[inline]
+    pub fn from_rope(rope: Rope) -> Self {
+        Self {
+            rope,
+            generation: 0,
+            content_id: 0,
+            read_only: false,
+            anchors: Anchors::default(),
+            options: BufferOptions::default(),
+            last_edit: None,
+            path: None,
+            swap_generation: 0,
         }
     }
 
@@ -56,7 +124,9 @@ impl TextBuffer {
     ///
     /// NOTE: If/when I add encoding detection or incremental IO, those should likely
     /// live in a separate IO-focused module.
-    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+    ///
+    /// The buffer remembers `path` (see `path`).
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
         let path = path.as_ref();
 
         let bytes = std::fs::read(path)
@@ -65,7 +135,25 @@ impl TextBuffer {
         let s = String::from_utf8(bytes)
             .with_context(|| format!("file is not valid UTF-8: {}", path.to_string_lossy()))?;
 
-        Ok(Self::from_str(&s))
+        let mut buffer = Self::from_str(&s);
+        buffer.path = Some(path.to_path_buf());
+        Ok(buffer)
+    }
+
+    /// Replace the buffer's content with the file at `path`, keeping the cursor
+    /// roughly where it was.
+    ///
+    /// For now the cursor is simply clamped into the new content; mapping it through
+    /// a diff of old vs. new text would be a nicer follow-up.
+    ///
+    /// On error, the buffer is left unchanged.
+    pub fn reload_from_file(&mut self, path: impl AsRef<Path>, cursor: Pos) -> Result<Pos> {
+        let fresh = Self::from_file(path)?;
+        self.rope = fresh.rope;
+        self.bump_generation();
+        self.anchors.clamp(self.rope.len_chars());
+        self.last_edit = None;
+        Ok(self.clamp_pos(cursor))
     }
 
     /// Access the underlying rope.
@@ -80,11 +168,94 @@ impl TextBuffer {
     ///
     /// Prefer dedicated editing APIs so invariants and future bookkeeping (eg.
     /// undo/redo, marks, spans) remain easy to maintain.
+    ///
+    /// This conservatively bumps the generation, since the caller may mutate.
     #[inline]
     pub fn rope_mut(&mut self) -> &mut Rope {
+        self.bump_generation();
         &mut self.rope
     }
 
+    /// Current edit generation.
+    ///
+    /// Starts at 0 and increases on every mutation. Two reads returning the same
+    /// value mean the content has not changed in between.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Identifies the current content: the generation it was first produced at.
+    ///
+    /// Unlike `generation`, this goes back to an earlier value when `restore`
+    /// brings back earlier content, so "unchanged since saved" checks can compare
+    /// it against the value recorded at save time. Fresh edits always get a new
+    /// generation, so two different contents never share an id.
+    #[inline]
+    pub fn content_id(&self) -> u64 {
+        self.content_id
+    }
+
+    /// Move to a new generation for content that was just changed.
+    #[inline]
+    pub(super) fn bump_generation(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        self.content_id = self.generation;
+    }
+
+    /// Where the most recent edit happened (Vim's `` `. `` mark): the start of the
+    /// inserted or deleted text.
+    ///
+    /// `None` until the first edit, and again after the content is swapped out
+    /// wholesale (`restore`, `reload_from_file`).
+    #[inline]
+    pub fn last_edit_pos(&self) -> Option<Pos> {
+        self.last_edit
+            .map(|c| self.char_to_pos(c.min(self.len_chars())))
+    }
+
+    /// The file this buffer was loaded from (or should be saved to), if any.
+    ///
+    /// Set by `from_file` and the `io` loaders; buffers built from strings or
+    /// ropes have none until `set_path`.
+    #[inline]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Point the buffer at a different file (eg. after "save as"), or at none.
+    #[inline]
+    pub fn set_path(&mut self, path: Option<PathBuf>) {
+        self.path = path;
+    }
+
+    /// Display name: the file name of `path`, or `"[No Name]"` without one (or
+    /// when it isn't valid UTF-8).
+    pub fn name(&self) -> &str {
+        self.path
+            .as_deref()
+            .and_then(Path::file_name)
+            .and_then(|name| name.to_str())
+            .unwrap_or("[No Name]")
+    }
+
+    /// Whether edits are currently rejected.
+    #[inline]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Make the buffer read-only (for logs, generated output, ...) or editable again.
+    ///
+    /// While read-only, the infallible editing methods (`insert`, `backspace`,
+    /// `apply_edit`, ...) leave the content alone and return the cursor where it
+    /// was; the `try_*` variants and `apply_edits` return an error instead.
+    /// `rope_mut`, `reload_from_file` and `restore` aren't edits and still work.
+    #[inline]
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
     /// Total number of chars in the buffer.
     ///
     /// Kept here because it is a fundamental primitive used by most other modules.
@@ -93,6 +264,14 @@ impl TextBuffer {
         self.rope.len_chars()
     }
 
+    /// Total number of UTF-8 bytes in the buffer.
+    ///
+    /// For byte-oriented interop (tree-sitter, hashing); see `byte_slice`.
+    #[inline]
+    pub fn len_bytes(&self) -> usize {
+        self.rope.len_bytes()
+    }
+
     /// Whether or not the buffer contains zero characters (is empty).
     #[inline]
     pub fn is_empty(&self) -> bool {
@@ -9,11 +9,44 @@
 //! - return updated `Pos`/`Selection` to make call sites explicit
 //! - keep it easy to extend later (undo/redo, transactions, multiple cursors, etc.)
 
-use ropey::Rope;
+use anyhow::{Result, bail};
 
-use crate::buffer::{Edit, Pos, Selection, TextBuffer};
+use crate::buffer::{Edit, MultiSelection, Pos, Selection, TextBuffer};
 
 impl TextBuffer {
+    /// Remove the char range `[start, end)` and insert `text` at `start`.
+    ///
+    /// Every mutation funnels through here so per-edit bookkeeping (the generation
+    /// counter, anchors, the last edit position and the read-only check) lives in
+    /// one place. Indices must already be valid and ordered.
+    ///
+    /// Returns the char index just past the inserted text (`start` if read-only).
+    pub(super) fn splice(&mut self, start: usize, end: usize, text: &str) -> usize {
+        if self.read_only {
+            return start;
+        }
+
+        if start < end {
+            self.rope.remove(start..end);
+        }
+
+        // Measure the insertion via the rope itself so counting stays consistent
+        // with ropey's char model.
+        let before = self.rope.len_chars();
+        if !text.is_empty() {
+            self.rope.insert(start, text);
+        }
+        let inserted_chars = self.rope.len_chars() - before;
+
+        if start < end || inserted_chars > 0 {
+            self.bump_generation();
+            self.anchors.adjust(start, end, inserted_chars);
+            self.last_edit = Some(start);
+        }
+
+        start + inserted_chars
+    }
+
     /// Insert `text` at the given logical position.
     ///
     /// Returns the new cursor position (at the end of inserted text).
@@ -21,13 +54,120 @@ impl TextBuffer {
     /// NOTE: This is a primitive operation I can build higher-level commands on top of
     /// (e.g. replace-selection-then-insert, paste, auto-indent, etc).
     pub fn insert(&mut self, pos: Pos, text: &str) -> Pos {
+        if self.read_only {
+            return self.clamp_pos(pos);
+        }
         let at = self.pos_to_char(pos);
-        self.rope.insert(at, text);
+        let end = self.splice(at, at, text);
+        self.char_to_pos(end)
+    }
 
-        // Compute end position by converting at + inserted_chars.
-        // We avoid `text.chars().count()` to keep indexing consistent with ropey.
-        let inserted_chars = Rope::from_str(text).len_chars();
-        self.char_to_pos(at + inserted_chars)
+    /// Insert `text` at char index `char_idx` (clamped to the buffer length).
+    ///
+    /// The char-index twin of `insert`, for callers that already work in char
+    /// indices. Returns the char index just past the inserted text.
+    pub fn insert_at(&mut self, char_idx: usize, text: &str) -> usize {
+        let at = char_idx.min(self.len_chars());
+        self.splice(at, at, text)
+    }
+
+    /// Type `text` over the existing chars from `pos` on (Vim's `R` mode).
+    ///
+    /// Each char of `text` replaces one char of the line; once the line end is
+    /// reached the rest is inserted, so the newline is never overwritten. A `'\n'`
+    /// in `text` splits the line without replacing anything, and typing carries on
+    /// over the rest of the line, as in Vim. This works in chars, not graphemes, so
+    /// a combining mark counts as a char of its own.
+    ///
+    /// Returns the cursor at the end of the typed text.
+    pub fn overwrite(&mut self, pos: Pos, text: &str) -> Pos {
+        if self.read_only {
+            return self.clamp_pos(pos);
+        }
+        let pos = self.clamp_pos(pos);
+        let at = self.pos_to_char(pos);
+
+        // Everything replaced comes from the one original line, left to right.
+        let mut remaining = self.line_len_chars(pos.line) - pos.col;
+        let mut replaced = 0;
+        for segment in text.split('\n') {
+            let n = segment.chars().count().min(remaining);
+            remaining -= n;
+            replaced += n;
+        }
+        let end = self.splice(at, at + replaced, text);
+        self.char_to_pos(end)
+    }
+
+    /// Where `A` starts inserting: just past the last char of `line` (clamped).
+    pub fn append_line_end(&self, line: usize) -> Pos {
+        let line = self.clamp_line(line);
+        Pos::new(line, self.line_len_chars(line))
+    }
+
+    /// Open a new line below `line` starting with `indent` (Vim's `o`).
+    ///
+    /// The break goes before `line`'s own newline (or at the end of the buffer),
+    /// so this works the same on a last line with no trailing `'\n'`. Returns the
+    /// cursor on the new line, after the indent.
+    pub fn open_line_below(&mut self, line: usize, indent: &str) -> Pos {
+        let end = self.append_line_end(line);
+        self.insert(end, &format!("\n{indent}"))
+    }
+
+    /// Open a new line above `line` starting with `indent` (Vim's `O`).
+    ///
+    /// Returns the cursor on the new line, after the indent.
+    pub fn open_line_above(&mut self, line: usize, indent: &str) -> Pos {
+        let line = self.clamp_line(line);
+        if self.read_only {
+            return Pos::new(line, 0);
+        }
+        self.insert(Pos::new(line, 0), &format!("{indent}\n"));
+        Pos::new(line, indent.chars().count())
+    }
+
+    /// Insert `text` `count` times at `pos` (Vim's `3ihello<Esc>`).
+    ///
+    /// The repeated text is built once and inserted in a single splice. A `count`
+    /// of 0 inserts nothing. Returns the cursor at the end of the inserted text.
+    pub fn insert_repeated(&mut self, pos: Pos, text: &str, count: usize) -> Pos {
+        if count == 0 || text.is_empty() {
+            return self.clamp_pos(pos);
+        }
+        self.insert(pos, &text.repeat(count))
+    }
+
+    /// Delete the chars in `range`, clamped and ordered the same way as `apply_edit`.
+    ///
+    /// The char-index twin of `delete_range`. Returns the start of the deletion.
+    pub fn delete_chars(&mut self, range: std::ops::Range<usize>) -> usize {
+        let maxc = self.len_chars();
+        let start = range.start.min(maxc);
+        let end = range.end.min(maxc);
+        let (start, end) = if start <= end {
+            (start, end)
+        } else {
+            (end, start)
+        };
+
+        self.splice(start, end, "")
+    }
+
+    /// Like `insert`, but fails instead of doing nothing on a read-only buffer.
+    pub fn try_insert(&mut self, pos: Pos, text: &str) -> Result<Pos> {
+        if self.read_only {
+            bail!("buffer is read-only");
+        }
+        Ok(self.insert(pos, text))
+    }
+
+    /// Like `delete_range`, but fails instead of doing nothing on a read-only buffer.
+    pub fn try_delete(&mut self, a: Pos, b: Pos) -> Result<Pos> {
+        if self.read_only {
+            bail!("buffer is read-only");
+        }
+        Ok(self.delete_range(a, b))
     }
 
     /// Delete a range between two positions (order-independent).
@@ -37,10 +177,7 @@ impl TextBuffer {
         let start = self.pos_to_char(crate::buffer::util::min_pos(self, a, b));
         let end = self.pos_to_char(crate::buffer::util::max_pos(self, a, b));
 
-        if start < end {
-            self.rope.remove(start..end);
-        }
-
+        self.splice(start, end, "");
         self.char_to_pos(start)
     }
 
@@ -61,6 +198,9 @@ impl TextBuffer {
     ///
     /// Returns an empty selection at the updated cursor.
     pub fn backspace(&mut self, sel: Selection) -> Selection {
+        if self.read_only {
+            return Selection::empty(self.clamp_pos(sel.cursor));
+        }
         if !sel.is_empty() {
             let (cursor, _) = self.delete_selection(sel);
             return Selection::empty(cursor);
@@ -73,7 +213,7 @@ impl TextBuffer {
         }
 
         let start = at - 1;
-        self.rope.remove(start..at);
+        self.splice(start, at, "");
         let new_cursor = self.char_to_pos(start);
         Selection::empty(new_cursor)
     }
@@ -84,6 +224,9 @@ impl TextBuffer {
     ///
     /// Returns an empty selection at the updated cursor.
     pub fn delete(&mut self, sel: Selection) -> Selection {
+        if self.read_only {
+            return Selection::empty(self.clamp_pos(sel.cursor));
+        }
         if !sel.is_empty() {
             let (cursor, _) = self.delete_selection(sel);
             return Selection::empty(cursor);
@@ -97,7 +240,7 @@ impl TextBuffer {
             return Selection::empty(cursor);
         }
 
-        self.rope.remove(at..at + 1);
+        self.splice(at, at + 1, "");
         let new_cursor = self.char_to_pos(at);
         Selection::empty(new_cursor)
     }
@@ -134,17 +277,58 @@ impl TextBuffer {
             (end, start)
         };
 
-        if start < end {
-            self.rope.remove(start..end);
+        let end = self.splice(start, end, &edit.insert);
+        self.char_to_pos(end)
+    }
+
+    /// Apply several edits whose ranges all refer to the buffer *before* any of them.
+    ///
+    /// The edits are sorted and applied back to front, so callers (formatters, LSP
+    /// text edits, ...) never have to shift indices themselves. Edits that merely
+    /// touch are fine; insertions at the same index keep their given order.
+    ///
+    /// Nothing is applied if the buffer is read-only, or if any range is reversed,
+    /// runs past the end of the buffer, or overlaps another.
+    pub fn apply_edits(&mut self, mut edits: Vec<Edit>) -> Result<()> {
+        if self.read_only {
+            bail!("buffer is read-only");
+        }
+        let maxc = self.len_chars();
+        for edit in &edits {
+            let range = &edit.range;
+            if range.start > range.end || range.end > maxc {
+                bail!("edit range {range:?} is invalid for a buffer of {maxc} chars");
+            }
         }
 
-        if !edit.insert.is_empty() {
-            self.rope.insert(start, &edit.insert);
-            let inserted_chars = Rope::from_str(&edit.insert).len_chars();
-            self.char_to_pos(start + inserted_chars)
-        } else {
-            self.char_to_pos(start)
+        edits.sort_by_key(|edit| (edit.range.start, edit.range.end));
+        for pair in edits.windows(2) {
+            let (a, b) = (&pair[0].range, &pair[1].range);
+            if a.end > b.start {
+                bail!("edit ranges {a:?} and {b:?} overlap");
+            }
+        }
+
+        for edit in edits.iter().rev() {
+            self.splice(edit.range.start, edit.range.end, &edit.insert);
+        }
+        Ok(())
+    }
+
+    /// Replace the text between two positions (order-independent) with `text`.
+    ///
+    /// One range conversion and one splice, so it's a single mutation: the
+    /// generation moves once, and an undo history would record one entry.
+    ///
+    /// Returns the cursor at the end of the inserted text.
+    pub fn replace_range(&mut self, a: Pos, b: Pos, text: &str) -> Pos {
+        if self.read_only {
+            return crate::buffer::util::min_pos(self, a, b);
         }
+        let start = self.pos_to_char(crate::buffer::util::min_pos(self, a, b));
+        let end = self.pos_to_char(crate::buffer::util::max_pos(self, a, b));
+        let end = self.splice(start, end, text);
+        self.char_to_pos(end)
     }
 
     /// Replace the current selection with `text` (if selection is empty, behaves like insert).
@@ -152,14 +336,195 @@ impl TextBuffer {
     ///
     /// Returns an empty selection at the updated cursor.
     pub fn replace_selection(&mut self, sel: Selection, text: &str) -> Selection {
-        if !sel.is_empty() {
-            let (start, end) = sel.ordered();
-            let cursor = self.delete_range(start, end);
-            let cursor = self.insert(cursor, text);
-            Selection::empty(cursor)
+        let (start, end) = sel.ordered();
+        Selection::empty(self.replace_range(start, end, text))
+    }
+
+    /// Insert `text` at column `col` on every line `start_line..=end_line`
+    /// (block-visual `I`/`A`).
+    ///
+    /// Lines shorter than `col` are padded with spaces to reach it when
+    /// `pad_short_lines` is set, and left alone otherwise. The empty line after a
+    /// trailing newline isn't a line here and is never edited. Lines are edited bottom
+    /// to top so each line's char indices stay valid. `text` should not contain a
+    /// newline, or the returned positions won't line up.
+    ///
+    /// The lines are taken as by `line_span`. Returns one selection per edited
+    /// line, spanning the inserted text (empty if read-only).
+    pub fn block_insert(
+        &mut self,
+        start_line: usize,
+        end_line: usize,
+        col: usize,
+        text: &str,
+        pad_short_lines: bool,
+    ) -> MultiSelection {
+        if self.read_only {
+            return MultiSelection::default();
+        }
+
+        let (first, last) = self.line_span(start_line, end_line);
+        // Don't pad the empty line after a trailing newline into a real one.
+        let last = last.min(self.effective_len_lines() - 1);
+        let text_len = text.chars().count();
+
+        let mut selections = Vec::new();
+        for line in (first..=last).rev() {
+            let range = self.line_char_range(line);
+            let len = range.end - range.start;
+            if len < col {
+                if !pad_short_lines {
+                    continue;
+                }
+                let padded = format!("{}{text}", " ".repeat(col - len));
+                self.splice(range.end, range.end, &padded);
+            } else {
+                self.splice(range.start + col, range.start + col, text);
+            }
+            selections.push(Selection::new(
+                Pos::new(line, col),
+                Pos::new(line, col + text_len),
+            ));
+        }
+
+        selections.reverse();
+        MultiSelection::new(selections)
+    }
+
+    /// Remove columns `[start_col, end_col)` from every line `start_line..=end_line`
+    /// (block-visual `d`).
+    ///
+    /// The column range is clamped to each line's own length, so lines that end
+    /// inside (or before) the rectangle just lose whatever part of it they have.
+    /// Lines are edited bottom to top so each line's char indices stay valid.
+    ///
+    /// The lines are taken as by `line_span`, and the columns may likewise come
+    /// in either order. Returns the top-left corner of the rectangle, clamped to
+    /// the first line.
+    pub fn block_delete(
+        &mut self,
+        start_line: usize,
+        end_line: usize,
+        start_col: usize,
+        end_col: usize,
+    ) -> Pos {
+        let (first, last) = self.line_span(start_line, end_line);
+        let (left, right) = if start_col <= end_col {
+            (start_col, end_col)
+        } else {
+            (end_col, start_col)
+        };
+
+        for line in (first..=last).rev() {
+            let range = self.line_char_range(line);
+            let start = (range.start + left).min(range.end);
+            let end = (range.start + right).min(range.end);
+            if start < end {
+                self.splice(start, end, "");
+            }
+        }
+
+        Pos::new(first, left.min(self.line_len_chars(first)))
+    }
+
+    /// Paste linewise `text` as whole lines below `line` (`p` after `yy`).
+    ///
+    /// `text` is treated as newline-terminated lines (a missing final `'\n'` is
+    /// implied). Below a last line that has no `'\n'`, the break goes before the
+    /// pasted lines instead of after, so the buffer still doesn't end in one.
+    ///
+    /// `line` is clamped. Returns the start of the first pasted line.
+    pub fn paste_lines(&mut self, line: usize, text: &str) -> Pos {
+        let line = self.clamp_line(line);
+        if self.read_only || text.is_empty() {
+            return Pos::new(line, 0);
+        }
+
+        if line + 1 < self.len_lines() {
+            let at = self.line_to_char(line + 1);
+            if text.ends_with('\n') {
+                self.splice(at, at, text);
+            } else {
+                self.splice(at, at, &format!("{text}\n"));
+            }
         } else {
-            let cursor = self.insert(sel.cursor, text);
-            Selection::empty(cursor)
+            let at = self.len_chars();
+            let body = text.strip_suffix('\n').unwrap_or(text);
+            self.splice(at, at, &format!("\n{body}"));
         }
+        Pos::new(line + 1, 0)
+    }
+
+    /// Insert the contents of the file at `path` as new lines below `line`
+    /// (Vim's `:r file`), the same way `paste_lines` places them.
+    ///
+    /// Returns the start of the first inserted line, or `line`'s start if the
+    /// file is empty. A missing or unreadable file is an error and leaves the
+    /// buffer unchanged.
+    pub fn read_file_into(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        line: usize,
+    ) -> Result<Pos> {
+        let text = crate::io::load_buffer(path)?.to_string();
+        Ok(self.paste_lines(line, &text))
+    }
+
+    /// Join lines `start_line..=end_line` into one (Vim's `J`).
+    ///
+    /// Each joined line loses its leading whitespace and is separated from the
+    /// text before it by one space. No space is added when the text so far is
+    /// empty or already ends in whitespace, when the joined line is blank, or when
+    /// it starts with `)`. Joining a single line joins it with the next.
+    ///
+    /// The lines are taken as by `line_span`. Returns the position of the last
+    /// join (where the separating space went).
+    pub fn join_lines(&mut self, start_line: usize, end_line: usize) -> Pos {
+        let (first, last) = self.line_span(start_line, end_line);
+        let last = if first == last { first + 1 } else { last };
+        // A trailing newline isn't a line to join with.
+        let joins = last
+            .min(self.effective_len_lines() - 1)
+            .saturating_sub(first);
+
+        let line_start = self.line_to_char(first);
+        let mut cursor = Pos::new(first, self.line_len_chars(first));
+        for _ in 0..joins {
+            if self.read_only {
+                break;
+            }
+            let end = self.line_char_range(first).end;
+            let next = self.line_char_range(first + 1);
+            let indent = self
+                .rope
+                .slice(next.clone())
+                .chars()
+                .take_while(|c| c.is_whitespace())
+                .count();
+
+            let prev = (end > line_start).then(|| self.rope.char(end - 1));
+            let rest = self
+                .rope
+                .slice(next.start + indent..next.end)
+                .chars()
+                .next();
+            let sep = match (prev, rest) {
+                (None, _) | (_, None) | (_, Some(')')) => "",
+                (Some(c), _) if c.is_whitespace() => "",
+                _ => " ",
+            };
+
+            self.splice(end, next.start + indent, sep);
+            cursor = Pos::new(first, end - line_start);
+        }
+        cursor
+    }
+
+    /// Join every line the selection touches (visual `J`).
+    ///
+    /// A selection within one line joins that line with the next, as in Vim.
+    pub fn join_selection(&mut self, sel: Selection) -> Pos {
+        let (start, end) = sel.ordered();
+        self.join_lines(start.line, end.line)
     }
 }
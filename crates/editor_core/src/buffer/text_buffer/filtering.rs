@@ -0,0 +1,78 @@
+//! Filtering lines through an external program (Vim's `:{range}!cmd`).
+//!
+//! The selected lines are written to the program's stdin and replaced by its
+//! stdout. The program is spawned directly (no shell), so pipes and globbing
+//! are up to the caller. Stdin is fed from a separate thread: a program like
+//! `sort` reads everything before writing, but one that streams could fill its
+//! stdout pipe while we're still writing, and both sides would block.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context as _, Result, bail};
+
+use super::TextBuffer;
+use crate::buffer::Pos;
+
+impl TextBuffer {
+    /// Replace lines `start_line..=end_line` with the output of `program args..`
+    /// run with those lines on stdin.
+    ///
+    /// Each line is sent with a trailing `'\n'`, and one trailing `'\n'` is dropped
+    /// from the output, so a filter that keeps the line count leaves the
+    /// surrounding line breaks as they were.
+    ///
+    /// The lines are taken as by `line_span`. The buffer is left unchanged if
+    /// it's read-only, the program can't be run, exits with a non-zero status, or
+    /// prints invalid UTF-8.
+    ///
+    /// Returns the start of the first filtered line.
+    pub fn filter_lines(
+        &mut self,
+        start_line: usize,
+        end_line: usize,
+        program: &str,
+        args: &[&str],
+    ) -> Result<Pos> {
+        if self.read_only {
+            bail!("buffer is read-only");
+        }
+
+        let (first, last) = self.line_span(start_line, end_line);
+
+        let start = self.line_to_char(first);
+        let end = self.line_char_range(last).end;
+        let mut input = self.slice_chars(start, end);
+        input.push('\n');
+
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to run `{program}`"))?;
+
+        let mut stdin = child.stdin.take().context("child has no stdin")?;
+        let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+        let output = child
+            .wait_with_output()
+            .with_context(|| format!("failed to run `{program}`"))?;
+        // A filter may exit without reading all of its input (`head`), so a broken
+        // pipe here isn't an error on its own; the exit status decides.
+        let _ = writer.join();
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("`{program}` failed ({}): {}", output.status, stderr.trim());
+        }
+
+        let stdout = String::from_utf8(output.stdout)
+            .with_context(|| format!("`{program}` printed invalid UTF-8"))?;
+        let replacement = stdout.strip_suffix('\n').unwrap_or(&stdout);
+
+        self.splice(start, end, replacement);
+        Ok(Pos::new(first, 0))
+    }
+}
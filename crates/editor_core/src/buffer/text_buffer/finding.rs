@@ -0,0 +1,39 @@
+//! Find-char-on-line helpers for `TextBuffer` (Vim's `f`/`F`/`t`/`T`).
+//!
+//! Searches never leave the cursor's line: the newline (and anything past it)
+//! is out of reach, just like in Vim.
+
+use super::TextBuffer;
+use crate::buffer::Pos;
+
+impl TextBuffer {
+    /// Find the next `ch` after `pos` on the same line.
+    ///
+    /// With `till`, stop one char before the match (`t`); otherwise land on it
+    /// (`f`). A match right next to the cursor leaves a till search where it is.
+    /// Returns `None` if the rest of the line has no `ch`.
+    pub fn find_char_forward(&self, pos: Pos, ch: char, till: bool) -> Option<Pos> {
+        let pos = self.clamp_pos(pos);
+        let line_start = self.line_to_char(pos.line);
+        let line_len = self.line_len_chars(pos.line);
+
+        let col = (pos.col + 1..line_len).find(|&col| self.rope.char(line_start + col) == ch)?;
+        let col = if till { col - 1 } else { col };
+        Some(Pos::new(pos.line, col))
+    }
+
+    /// Find the previous `ch` before `pos` on the same line.
+    ///
+    /// With `till`, stop one char after the match (`T`); otherwise land on it
+    /// (`F`). Returns `None` if the line has no `ch` before `pos`.
+    pub fn find_char_backward(&self, pos: Pos, ch: char, till: bool) -> Option<Pos> {
+        let pos = self.clamp_pos(pos);
+        let line_start = self.line_to_char(pos.line);
+
+        let col = (0..pos.col)
+            .rev()
+            .find(|&col| self.rope.char(line_start + col) == ch)?;
+        let col = if till { col + 1 } else { col };
+        Some(Pos::new(pos.line, col))
+    }
+}
@@ -0,0 +1,270 @@
+//! Text layout transforms for `TextBuffer` (reflow, retab, line endings, etc).
+//!
+//! Unlike the TUI's soft wrap, these operations actually rewrite the buffer.
+//!
+//! Design notes:
+//! - Widths are measured in **chars**, matching the `(line, col)` model used
+//!   everywhere else in the core. Visual width (wide glyphs, tabs) is a view concern.
+//! - Each operation computes its edits up front and then applies them back-to-front
+//!   (so earlier char indices stay valid), keeping it one logical step for
+//!   undo/redo once that exists.
+//! - Tab stops are column-aware: a tab advances to the next multiple of
+//!   `tab_width`, so its width depends on what precedes it on the line.
+
+use crate::buffer::{Selection, TextBuffer};
+
+/// A line break representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    /// `\n` (Unix).
+    #[default]
+    Lf,
+    /// `\r\n` (Windows).
+    Crlf,
+    /// A lone `\r` (classic Mac OS).
+    Cr,
+}
+
+/// How the buffer's lines are indented, from [`TextBuffer::indent_analysis`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IndentReport {
+    /// Lines indented with tabs only.
+    pub tabs: usize,
+    /// Lines indented with spaces only.
+    pub spaces: usize,
+    /// Lines whose indentation mixes tabs and spaces, in order.
+    pub mixed_lines: Vec<usize>,
+}
+
+impl LineEnding {
+    /// The line break text itself.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+}
+
+impl TextBuffer {
+    /// Hard-wrap (`gq`-style) the lines `start_line..=end_line` to `width` columns.
+    ///
+    /// Behavior:
+    /// - The range is split into paragraphs at blank (empty or whitespace-only)
+    ///   lines, which are preserved as-is.
+    /// - Each paragraph's words are joined and re-broken greedily so no line exceeds
+    ///   `width` chars, using the leading indentation of the paragraph's first line.
+    /// - A single word that can't fit is placed on its own (over-long) line rather
+    ///   than being split.
+    ///
+    /// The lines are taken as by `line_span`.
+    ///
+    /// Returns a selection spanning the reflowed text (the untouched lines on a
+    /// read-only buffer).
+    pub fn reflow_lines(&mut self, start_line: usize, end_line: usize, width: usize) -> Selection {
+        let (first, last) = self.line_span(start_line, end_line);
+
+        let start = self.line_to_char(first);
+        let end = self.line_char_range(last).end;
+        if self.read_only {
+            return Selection::new(self.char_to_pos(start), self.char_to_pos(end));
+        }
+
+        let mut out: Vec<String> = Vec::new();
+        let mut paragraph: Vec<String> = Vec::new();
+        for line in first..=last {
+            let text = self.line_string(line);
+            if self.is_blank_line(line) {
+                reflow_paragraph(&paragraph, width, &mut out);
+                paragraph.clear();
+                out.push(text);
+            } else {
+                paragraph.push(text);
+            }
+        }
+        reflow_paragraph(&paragraph, width, &mut out);
+
+        let new_end = self.splice(start, end, &out.join("\n"));
+        Selection::new(self.char_to_pos(start), self.char_to_pos(new_end))
+    }
+
+    /// Replace every tab with spaces up to the next tab stop (`:retab` with `expandtab`).
+    ///
+    /// Returns the number of tabs replaced (0 on a read-only buffer). A
+    /// `tab_width` of 0 is treated as 1.
+    pub fn expand_tabs(&mut self, tab_width: usize) -> usize {
+        if self.read_only {
+            return 0;
+        }
+        let tab_width = tab_width.max(1);
+
+        // (char index of the tab, spaces to replace it with)
+        let mut edits: Vec<(usize, usize)> = Vec::new();
+        for range in self.line_ranges(0, usize::MAX) {
+            let mut col = 0usize;
+            for (i, ch) in self.rope.slice(range.clone()).chars().enumerate() {
+                if ch == '\t' {
+                    let spaces = tab_width - col % tab_width;
+                    edits.push((range.start + i, spaces));
+                    col += spaces;
+                } else {
+                    col += 1;
+                }
+            }
+        }
+
+        for &(at, spaces) in edits.iter().rev() {
+            self.splice(at, at + 1, &" ".repeat(spaces));
+        }
+
+        edits.len()
+    }
+
+    /// Rewrite each line's leading whitespace as tabs, padding with spaces for any
+    /// remainder that doesn't reach a full tab stop.
+    ///
+    /// Mixed tab/space indentation is measured by visual width first, so
+    /// `"  \t"` at width 4 becomes a single tab.
+    ///
+    /// Returns the number of lines changed (0 on a read-only buffer). A
+    /// `tab_width` of 0 is treated as 1.
+    pub fn tabify_leading(&mut self, tab_width: usize) -> usize {
+        if self.read_only {
+            return 0;
+        }
+        let tab_width = tab_width.max(1);
+
+        // (line start, leading whitespace length in chars, replacement)
+        let mut edits: Vec<(usize, usize, String)> = Vec::new();
+        for range in self.line_ranges(0, usize::MAX) {
+            let mut len = 0usize;
+            let mut width = 0usize;
+            for ch in self.rope.slice(range.clone()).chars() {
+                match ch {
+                    ' ' => width += 1,
+                    '\t' => width += tab_width - width % tab_width,
+                    _ => break,
+                }
+                len += 1;
+            }
+
+            let mut indent = "\t".repeat(width / tab_width);
+            indent.push_str(&" ".repeat(width % tab_width));
+
+            if self.rope.slice(range.start..range.start + len) != indent.as_str() {
+                edits.push((range.start, len, indent));
+            }
+        }
+
+        for (start, len, indent) in edits.iter().rev() {
+            self.splice(*start, start + len, indent);
+        }
+
+        edits.len()
+    }
+}
+
+impl TextBuffer {
+    /// Rewrite every line break (`\n`, `\r\n`, or a lone `\r`) as `target`.
+    ///
+    /// Ropey's lines (with its default `unicode_lines` feature) end at a lone
+    /// `\r` and at Unicode separators too, and don't say which break ended
+    /// them, so the whole text is scanned char by char instead, with a `\r`
+    /// immediately followed by `\n` counted as one `\r\n` break. Other
+    /// separators (U+2028 and friends) are left alone.
+    ///
+    /// Returns the number of line breaks that were converted (0 on a read-only
+    /// buffer).
+    pub fn normalize_line_endings(&mut self, target: LineEnding) -> usize {
+        if self.read_only {
+            return 0;
+        }
+        // (char range of a line break that isn't `target` yet)
+        let mut edits: Vec<(usize, usize)> = Vec::new();
+        let mut chars = self.rope.chars().enumerate().peekable();
+        while let Some((i, ch)) = chars.next() {
+            let found = match ch {
+                '\n' => LineEnding::Lf,
+                '\r' if chars.next_if(|&(_, next)| next == '\n').is_some() => LineEnding::Crlf,
+                '\r' => LineEnding::Cr,
+                _ => continue,
+            };
+            if found != target {
+                edits.push((i, i + found.as_str().len()));
+            }
+        }
+
+        for &(start, end) in edits.iter().rev() {
+            self.splice(start, end, target.as_str());
+        }
+
+        edits.len()
+    }
+}
+
+impl TextBuffer {
+    /// Tally how each line is indented, flagging lines that mix tabs and spaces.
+    ///
+    /// Unindented lines and blank (whitespace-only) lines are not counted: the
+    /// whitespace on a blank line isn't indenting anything.
+    pub fn indent_analysis(&self) -> IndentReport {
+        let mut report = IndentReport::default();
+        for line in 0..self.len_lines() {
+            if self.is_blank_line(line) {
+                continue;
+            }
+            let range = self.line_char_range(line);
+            let (mut tabs, mut spaces) = (false, false);
+            for ch in self.rope.slice(range).chars() {
+                match ch {
+                    '\t' => tabs = true,
+                    ' ' => spaces = true,
+                    _ => break,
+                }
+            }
+            match (tabs, spaces) {
+                (true, true) => report.mixed_lines.push(line),
+                (true, false) => report.tabs += 1,
+                (false, true) => report.spaces += 1,
+                (false, false) => {}
+            }
+        }
+        report
+    }
+}
+
+/// Greedily re-break one paragraph's words into lines of at most `width` chars.
+fn reflow_paragraph(lines: &[String], width: usize, out: &mut Vec<String>) {
+    let Some(first) = lines.first() else {
+        return;
+    };
+
+    let indent: String = first.chars().take_while(|c| c.is_whitespace()).collect();
+    let indent_len = indent.chars().count();
+
+    let mut current = indent.clone();
+    let mut current_len = indent_len;
+    for word in lines.iter().flat_map(|l| l.split_whitespace()) {
+        let word_len = word.chars().count();
+
+        if current_len > indent_len {
+            if current_len + 1 + word_len <= width {
+                current.push(' ');
+                current.push_str(word);
+                current_len += 1 + word_len;
+                continue;
+            }
+            out.push(std::mem::replace(&mut current, indent.clone()));
+            current_len = indent_len;
+        }
+
+        // First word on a line always goes in, even if it's too long to fit.
+        current.push_str(word);
+        current_len += word_len;
+    }
+
+    if current_len > indent_len {
+        out.push(current);
+    }
+}
@@ -0,0 +1,45 @@
+//! Grapheme-cluster columns for `TextBuffer`.
+//!
+//! `Pos::col` counts chars, but a user-perceived character can be several chars
+//! (`e` + U+0301 COMBINING ACUTE ACCENT is one grapheme, two chars). These
+//! helpers convert between the two column systems on a single line, so a view
+//! that counts graphemes and the core can agree on "column 3".
+//!
+//! Graphemes are extended clusters (`unicode-segmentation`), the same as the TUI
+//! uses for layout.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::TextBuffer;
+use crate::buffer::Pos;
+
+impl TextBuffer {
+    /// The grapheme column of `pos` on its line.
+    ///
+    /// A char column in the middle of a grapheme (on a combining mark) maps to
+    /// that grapheme. `pos` is clamped, so the end of the line maps to the
+    /// line's grapheme count.
+    pub fn char_col_to_grapheme_col(&self, pos: Pos) -> usize {
+        let pos = self.clamp_pos(pos);
+        let line = self.line_string(pos.line);
+        let mut chars = 0;
+        line.graphemes(true)
+            .position(|grapheme| {
+                chars += grapheme.chars().count();
+                chars > pos.col
+            })
+            .unwrap_or_else(|| line.graphemes(true).count())
+    }
+
+    /// The char column where grapheme `grapheme_col` of `line` starts.
+    ///
+    /// `line` is clamped, and a grapheme column past the end maps to the end of
+    /// the line.
+    pub fn grapheme_col_to_char_col(&self, line: usize, grapheme_col: usize) -> usize {
+        self.line_string(line)
+            .graphemes(true)
+            .take(grapheme_col)
+            .map(|g| g.chars().count())
+            .sum()
+    }
+}
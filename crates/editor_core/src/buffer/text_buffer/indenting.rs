@@ -0,0 +1,97 @@
+//! Tab-stop-aware editing for `TextBuffer` (Vim's `expandtab`/`softtabstop`).
+//!
+//! Whether Tab inserts a tab or spaces comes from the buffer's `BufferOptions`
+//! (`insert_tab`), or is passed in for a one-off (`insert_tab_with`).
+//!
+//! Visual columns count a tab as reaching the next multiple of `tab_width` and
+//! every other char as one cell, same as `expand_tabs`.
+
+use super::TextBuffer;
+use super::options::BufferOptions;
+use crate::buffer::{Pos, Selection};
+
+impl TextBuffer {
+    /// The visual column of `pos`, with tabs expanded to `tab_width` stops.
+    ///
+    /// `pos` is clamped. A `tab_width` of 0 is treated as 1.
+    pub fn visual_col(&self, pos: Pos, tab_width: usize) -> usize {
+        let tab_width = tab_width.max(1);
+        let pos = self.clamp_pos(pos);
+        let start = self.line_to_char(pos.line);
+        self.rope
+            .slice(start..start + pos.col)
+            .chars()
+            .fold(0, |col, ch| match ch {
+                '\t' => col + tab_width - col % tab_width,
+                _ => col + 1,
+            })
+    }
+
+    /// Press Tab at `pos`, following the buffer's `tab_width` and `expand_tab`.
+    ///
+    /// Returns the cursor after the insert.
+    pub fn insert_tab(&mut self, pos: Pos) -> Pos {
+        let BufferOptions {
+            tab_width,
+            expand_tab,
+            ..
+        } = self.options;
+        self.insert_tab_with(pos, tab_width, expand_tab)
+    }
+
+    /// Press Tab at `pos`: insert a `'\t'`, or with `expand`, as many spaces as
+    /// it takes to reach the next tab stop from the cursor's visual column.
+    ///
+    /// A `tab_width` of 0 is treated as 1. Returns the cursor after the insert.
+    pub fn insert_tab_with(&mut self, pos: Pos, tab_width: usize, expand: bool) -> Pos {
+        if !expand {
+            return self.insert(pos, "\t");
+        }
+        let tab_width = tab_width.max(1);
+        let col = self.visual_col(pos, tab_width);
+        self.insert(pos, &" ".repeat(tab_width - col % tab_width))
+    }
+
+    /// Backspace that treats space indentation like tabs (`softtabstop`).
+    ///
+    /// With an empty selection and only whitespace before the cursor, spaces are
+    /// deleted back to the previous tab stop (a whole indent level) instead of one
+    /// at a time. Anywhere else, or when a tab is right before the cursor, this is
+    /// plain `backspace`. A `tab_width` of 0 is treated as 1.
+    pub fn backspace_indent_aware(&mut self, sel: Selection, tab_width: usize) -> Selection {
+        let cursor = self.clamp_pos(sel.cursor);
+        if !sel.is_empty() || self.read_only || !self.in_leading_whitespace(cursor) {
+            return self.backspace(sel);
+        }
+
+        let tab_width = tab_width.max(1);
+        let line_start = self.line_to_char(cursor.line);
+        let mut col = cursor.col;
+        let mut visual = self.visual_col(cursor, tab_width);
+        let stop = visual.saturating_sub(1) / tab_width * tab_width;
+        while col > 0 && visual > stop && self.rope.char(line_start + col - 1) == ' ' {
+            col -= 1;
+            visual -= 1;
+        }
+        if col == cursor.col {
+            return self.backspace(sel);
+        }
+
+        self.splice(line_start + col, line_start + cursor.col, "");
+        Selection::empty(Pos::new(cursor.line, col))
+    }
+
+    /// Whether `pos` is within its line's leading indentation, i.e. everything
+    /// before it on the line is whitespace.
+    ///
+    /// Column 0 counts, and so does the first non-blank char itself (the cursor
+    /// sits right after the indentation there). `pos` is clamped.
+    pub fn in_leading_whitespace(&self, pos: Pos) -> bool {
+        let pos = self.clamp_pos(pos);
+        let start = self.line_to_char(pos.line);
+        self.rope
+            .slice(start..start + pos.col)
+            .chars()
+            .all(char::is_whitespace)
+    }
+}
@@ -9,9 +9,11 @@
 //!   `line_len_chars()` excludes it when present.
 //! - All functions are defensive, meaning they clamp out-of-range inputs.
 
+use std::borrow::Cow;
 use std::cmp::min;
 
 use crate::buffer::TextBuffer;
+use crate::text::str_display_width;
 
 impl TextBuffer {
     /// Number of lines in the buffer.
@@ -23,16 +25,51 @@ impl TextBuffer {
         self.rope.len_lines()
     }
 
+    /// Number of lines as an editor shows them: like `len_lines`, but without the
+    /// phantom empty line Ropey reports after a trailing `'\n'`.
+    ///
+    /// `"a\n"` and `"a"` are both 1 line here; an empty buffer is still 1 line.
+    /// Cursor motions that shouldn't land past the real last line (`j`, `:N`) clamp
+    /// against this.
+    ///
+    /// Only a trailing `'\n'` counts, as in `line_char_range`. Ropey also starts
+    /// a new line after a trailing lone `\r` or U+2028; that empty last line is
+    /// kept here, like any other.
+    pub fn effective_len_lines(&self) -> usize {
+        let len = self.len_chars();
+        if len > 0 && self.rope.char(len - 1) == '\n' {
+            self.len_lines() - 1
+        } else {
+            self.len_lines()
+        }
+    }
+
     /// Clamp a line index to the valid range `[0, len_lines - 1]`.
     ///
     /// If the buffer is empty, Ropey still reports `len_lines() == 1`, so this
     /// always returns a valid line index.
+    ///
+    /// This deliberately still allows the phantom line after a trailing `'\n'`:
+    /// pressing Enter at the end of the last line puts the cursor there, and the
+    /// editing APIs need to address it. Use `effective_len_lines` to keep motions
+    /// off it.
     #[inline]
     pub fn clamp_line(&self, line: usize) -> usize {
         let last = self.len_lines().saturating_sub(1);
         min(line, last)
     }
 
+    /// `(first, last)` of the inclusive line range between `a` and `b`.
+    ///
+    /// Every API that takes a `start_line`/`end_line` pair goes through this:
+    /// both ends are clamped like `clamp_line` and may be passed in either order,
+    /// so a selection's lines can be handed over without sorting them first.
+    #[inline]
+    pub fn line_span(&self, a: usize, b: usize) -> (usize, usize) {
+        let (a, b) = (self.clamp_line(a), self.clamp_line(b));
+        if a <= b { (a, b) } else { (b, a) }
+    }
+
     /// Returns the absolute char index at the start of `line`.
     ///
     /// `line` is clamped into a valid range.
@@ -97,4 +134,170 @@ impl TextBuffer {
 
         start..end
     }
+
+    /// Content char ranges (excluding the trailing `'\n'`) of lines
+    /// `start_line..=end_line`, in order.
+    ///
+    /// Walks the rope's line iterator once instead of converting each line index
+    /// separately, so it's the cheap way to visit a run of lines. The lines are
+    /// taken as by `line_span`.
+    pub fn line_ranges(
+        &self,
+        start_line: usize,
+        end_line: usize,
+    ) -> impl Iterator<Item = std::ops::Range<usize>> + '_ {
+        let (first, last) = self.line_span(start_line, end_line);
+
+        let mut at = self.rope.line_to_char(first);
+        self.rope
+            .lines_at(first)
+            .take(last - first + 1)
+            .map(move |line| {
+                let len = line.len_chars();
+                let content = if len > 0 && line.char(len - 1) == '\n' {
+                    len - 1
+                } else {
+                    len
+                };
+                let range = at..at + content;
+                at += len;
+                range
+            })
+    }
+
+    /// Char indices of the starts of lines `start_line..=end_line`, in order.
+    ///
+    /// Equivalent to calling `line_to_char` for each line, but done in one pass
+    /// (see `line_ranges`).
+    pub fn line_starts(&self, start_line: usize, end_line: usize) -> Vec<usize> {
+        self.line_ranges(start_line, end_line)
+            .map(|range| range.start)
+            .collect()
+    }
+
+    /// Returns `true` if `line` is empty or contains only whitespace.
+    ///
+    /// Scans the rope slice directly, so nothing is allocated. A trailing `'\r'`
+    /// (CRLF files) counts as whitespace. `line` is clamped into a valid range.
+    pub fn is_blank_line(&self, line: usize) -> bool {
+        self.rope
+            .slice(self.line_char_range(line))
+            .chars()
+            .all(char::is_whitespace)
+    }
+
+    /// The first non-blank line after `line`, or `None` if the rest is blank.
+    ///
+    /// `line` is clamped into a valid range.
+    pub fn next_nonblank_line(&self, line: usize) -> Option<usize> {
+        let line = self.clamp_line(line);
+        (line + 1..self.len_lines()).find(|&l| !self.is_blank_line(l))
+    }
+
+    /// The last non-blank line before `line`, or `None` if everything above is blank.
+    ///
+    /// `line` is clamped into a valid range.
+    pub fn prev_nonblank_line(&self, line: usize) -> Option<usize> {
+        let line = self.clamp_line(line);
+        (0..line).rev().find(|&l| !self.is_blank_line(l))
+    }
+
+    /// Start and end lines (inclusive) of the block around `line`: the
+    /// contiguous lines indented at least as deep as `line` (for `[i`/`]i` and
+    /// indent text objects).
+    ///
+    /// Blank lines don't end the block, but it never starts or ends on one.
+    /// Indentation is measured in cells, with tabs at the buffer's `tab_width`.
+    /// Returns `None` if `line` is blank. `line` is clamped into a valid range.
+    pub fn matching_indent_block(&self, line: usize) -> Option<(usize, usize)> {
+        let line = self.clamp_line(line);
+        if self.is_blank_line(line) {
+            return None;
+        }
+        let tab_width = self.options.tab_width;
+        let indent = self.indent_width(line, tab_width);
+        let in_block =
+            |l: usize| self.is_blank_line(l) || self.indent_width(l, tab_width) >= indent;
+
+        let mut start = line;
+        while start > 0 && in_block(start - 1) {
+            start -= 1;
+        }
+        let mut end = line;
+        while end + 1 < self.len_lines() && in_block(end + 1) {
+            end += 1;
+        }
+        while self.is_blank_line(start) {
+            start += 1;
+        }
+        while self.is_blank_line(end) {
+            end -= 1;
+        }
+        Some((start, end))
+    }
+
+    /// Display width of `line`'s leading whitespace, with tabs advancing to the
+    /// next multiple of `tab_width` (0 is treated as 1).
+    pub(crate) fn indent_width(&self, line: usize, tab_width: usize) -> usize {
+        let tab_width = tab_width.max(1);
+        let mut width = 0;
+        for ch in self.rope.slice(self.line_char_range(line)).chars() {
+            match ch {
+                ' ' => width += 1,
+                '\t' => width += tab_width - width % tab_width,
+                _ => break,
+            }
+        }
+        width
+    }
+
+    /// How far through the file a viewport showing `viewport_height` lines from
+    /// `top_line` is, as 0-100 (Vim's ruler, but numeric).
+    ///
+    /// - `Top` (first line visible) is 0, unless the whole file fits;
+    /// - `All` (whole file fits) and `Bot` (last line visible) are 100;
+    /// - otherwise it's lines above the viewport as a share of all lines
+    ///   outside it, like Vim.
+    ///
+    /// Lines are counted with `effective_len_lines`.
+    pub fn scroll_percent(&self, top_line: usize, viewport_height: usize) -> u8 {
+        let len = self.effective_len_lines();
+        let above = top_line.min(len - 1);
+        let below = len.saturating_sub(above + viewport_height.max(1));
+        if below == 0 {
+            100
+        } else if above == 0 {
+            0
+        } else {
+            (above * 100 / (above + below)) as u8
+        }
+    }
+
+    /// Display width (in cells, tabs expanded to `tab_width` stops) of the
+    /// widest line, eg. for sizing a horizontal scrollbar.
+    ///
+    /// This measures every line (O(n)); use `max_line_width_in_range` to bound
+    /// the work to the visible lines.
+    pub fn max_line_width(&self, tab_width: usize) -> usize {
+        self.max_line_width_in_range(0, self.len_lines() - 1, tab_width)
+    }
+
+    /// Like `max_line_width`, over lines `start_line..=end_line` (see
+    /// `line_span`) only.
+    pub fn max_line_width_in_range(
+        &self,
+        start_line: usize,
+        end_line: usize,
+        tab_width: usize,
+    ) -> usize {
+        let (first, last) = self.line_span(start_line, end_line);
+        (first..=last)
+            .map(|line| {
+                // Only allocates for lines that span rope chunks.
+                let text: Cow<str> = self.rope.slice(self.line_char_range(line)).into();
+                str_display_width(&text, tab_width)
+            })
+            .max()
+            .unwrap_or(0)
+    }
 }
@@ -0,0 +1,74 @@
+//! UTF-16 offsets for LSP interop.
+//!
+//! Language servers address text as `(line, character)` where `character`
+//! counts UTF-16 code units, so anything outside the Basic Multilingual Plane
+//! (most emoji) is two units but one char. Ropey tracks UTF-16 lengths in its
+//! tree, so whole-buffer conversions are O(log n); per-line columns are measured
+//! on the line's slice.
+//!
+//! Line numbers are the same in both models: LSP lines are 0-based and split on
+//! line breaks just like the rope's.
+
+use super::TextBuffer;
+use crate::buffer::Pos;
+
+impl TextBuffer {
+    /// UTF-16 code-unit offset of char index `char_idx` (clamped to the buffer).
+    pub fn char_to_utf16(&self, char_idx: usize) -> usize {
+        self.rope.char_to_utf16_cu(char_idx.min(self.len_chars()))
+    }
+
+    /// Char index at UTF-16 offset `utf16_idx` (clamped to the buffer).
+    ///
+    /// An offset in the middle of a surrogate pair maps to the char it belongs to.
+    pub fn utf16_to_char(&self, utf16_idx: usize) -> usize {
+        self.rope
+            .utf16_cu_to_char(utf16_idx.min(self.rope.len_utf16_cu()))
+    }
+
+    /// `pos` (clamped) as an LSP position: its line and UTF-16 column.
+    pub fn pos_to_lsp(&self, pos: Pos) -> (usize, usize) {
+        let pos = self.clamp_pos(pos);
+        let start = self.line_to_char(pos.line);
+        let col = self.rope.slice(start..start + pos.col).len_utf16_cu();
+        (pos.line, col)
+    }
+
+    /// The `Pos` of an LSP position: `line` and a UTF-16 column on it.
+    ///
+    /// Walks the line's chars adding up their UTF-16 lengths. A column in the
+    /// middle of a surrogate pair lands on that char; one past the end of the line
+    /// is clamped to the line end (the newline isn't part of the line). `line` is
+    /// clamped too.
+    pub fn lsp_to_pos(&self, line: usize, utf16_col: usize) -> Pos {
+        let line = self.clamp_line(line);
+        let range = self.line_char_range(line);
+        let mut units = 0;
+        let col = self
+            .rope
+            .slice(range.clone())
+            .chars()
+            .position(|ch| {
+                units += ch.len_utf16();
+                units > utf16_col
+            })
+            .unwrap_or(range.len());
+        Pos::new(line, col)
+    }
+
+    /// Apply an LSP `TextEdit`: replace the text between the `(line, utf16_col)`
+    /// positions `start` and `end` with `new_text`.
+    ///
+    /// Both ends are converted with `lsp_to_pos` and the replacement is a single
+    /// `replace_range`. Returns the cursor at the end of the inserted text.
+    pub fn apply_lsp_edit(
+        &mut self,
+        start: (usize, usize),
+        end: (usize, usize),
+        new_text: &str,
+    ) -> Pos {
+        let start = self.lsp_to_pos(start.0, start.1);
+        let end = self.lsp_to_pos(end.0, end.1);
+        self.replace_range(start, end, new_text)
+    }
+}
@@ -3,20 +3,58 @@
 //! The goal of this submodule is to keep the `TextBuffer` implementation easy to
 //! navigate and extend by separating it into focused files:
 //! - `core.rs`: struct definition + basic constructors/accessors
+//! - `anchors.rs`: positions that move with edits (`create_anchor`/`resolve`)
+//! - `indenting.rs`: tab-stop-aware editing (`insert_tab`, expandtab)
 //! - `lines.rs`: line indexing helpers
+//! - `options.rs`: buffer-local settings (`BufferOptions`)
 //! - `pos.rs`: (line, col) conversions and cursor-ish movement
+//! - `graphemes.rs`: char columns <-> grapheme-cluster columns
 //! - `slice.rs`: extracting text
+//! - `comparing.rs`: content hashing/equality without allocating
 //! - `edit.rs`: mutation operations (insert/delete/apply edits)
 //! - `word.rs`: word-ish motions (intentionally minimal, easy to swap later)
+//! - `filtering.rs`: piping lines through an external program (`filter_lines`)
+//! - `finding.rs`: find-char-on-line motions (`f`/`F`/`t`/`T`)
+//! - `lsp.rs`: UTF-16 offsets and LSP positions
+//! - `numbers.rs`: incrementing the number under the cursor (`Ctrl-A`/`Ctrl-X`)
+//! - `searching.rs`: plain-text search (`matches`, `find_all`)
+//! - `spelling.rs`: running a pluggable spell checker over words (`misspellings`)
+//! - `swap.rs`: crash-recovery swap files (`write_swap`)
+//! - `text_objects.rs`: inner/around ranges (`iw`, `i"`, `a(`)
+//! - `snapshots.rs`: cheap content checkpoints (`snapshot`/`restore`)
+//! - `formatting.rs`: layout transforms that rewrite text (reflow, line endings, etc.)
 //!
 //! `TextBuffer` remains a single public type re-exported by `buffer::mod.rs`.
 //! All methods are inherent impls spread across these modules.
 
+mod anchors;
+mod comparing;
 mod core;
 mod editing;
+mod filtering;
+mod finding;
+mod formatting;
+mod graphemes;
+mod indenting;
 mod lines;
+mod lsp;
+mod numbers;
+mod options;
 mod positions;
+mod searching;
 mod slicing;
+mod snapshots;
+mod spelling;
+mod swap;
+mod text_objects;
 mod words;
 
+pub use anchors::{AnchorId, Bias};
 pub use core::TextBuffer;
+pub use formatting::{IndentReport, LineEnding};
+pub use options::BufferOptions;
+pub use searching::SearchOptions;
+pub use snapshots::BufferSnapshot;
+pub use spelling::WordChecker;
+pub use text_objects::TextObjectKind;
+pub use words::CharClass;
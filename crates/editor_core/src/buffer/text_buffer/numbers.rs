@@ -0,0 +1,54 @@
+//! Number increment/decrement for `TextBuffer` (Vim's `Ctrl-A`/`Ctrl-X`).
+//!
+//! Only decimal integers are recognised for now. A `-` right before the digits
+//! makes the number negative, as in Vim. Hex/octal/binary literals are just
+//! digits (and letters) here, so `0x1f` bumps the leading `0`.
+
+use super::TextBuffer;
+use crate::buffer::Pos;
+
+impl TextBuffer {
+    /// Add `delta` to the number under or after `pos` on its line.
+    ///
+    /// A number with leading zeros keeps its digit count (`007` + 1 is `008`) as
+    /// long as the result still fits. Results saturate at the `i64` bounds.
+    ///
+    /// Returns the cursor on the last char of the new number, or `None` if there
+    /// is no number at or after `pos` on the line (or it doesn't fit in an `i64`)
+    /// or the buffer is read-only.
+    pub fn increment_number(&mut self, pos: Pos, delta: i64) -> Option<Pos> {
+        if self.read_only {
+            return None;
+        }
+        let pos = self.clamp_pos(pos);
+        let line_start = self.line_to_char(pos.line);
+        let line: Vec<char> = self.line_string(pos.line).chars().collect();
+
+        // Start of the digit run under the cursor, else the first one after it.
+        let mut start = (pos.col..line.len()).find(|&c| line[c].is_ascii_digit())?;
+        while start > 0 && line[start - 1].is_ascii_digit() {
+            start -= 1;
+        }
+        let end = (start..line.len())
+            .find(|&c| !line[c].is_ascii_digit())
+            .unwrap_or(line.len());
+        let negative = start > 0 && line[start - 1] == '-';
+
+        let digits: String = line[start..end].iter().collect();
+        let magnitude: i64 = digits.parse().ok()?;
+        let value = if negative { -magnitude } else { magnitude };
+        let new = value.saturating_add(delta);
+
+        let width = if digits.len() > 1 && digits.starts_with('0') {
+            digits.len()
+        } else {
+            0
+        };
+        let sign = if new < 0 { "-" } else { "" };
+        let text = format!("{sign}{:0width$}", new.unsigned_abs());
+
+        let start = if negative { start - 1 } else { start };
+        self.splice(line_start + start, line_start + end, &text);
+        Some(Pos::new(pos.line, start + text.chars().count() - 1))
+    }
+}
@@ -0,0 +1,75 @@
+//! Buffer-local settings for `TextBuffer` (Vim's `:setlocal` options).
+//!
+//! Methods that have a natural default read it from here, so callers configure
+//! the buffer once instead of threading the same values through every call:
+//! - `insert_tab` uses `tab_width`/`expand_tab`;
+//! - word motions, word text objects, whole-word search and auto-pairing use
+//!   `word_chars`;
+//! - saving uses `final_newline`/`line_ending`;
+//! - `swap_if_due` uses `swap_every`.
+//!
+//! Operations that take an explicit width (`expand_tabs`, `visual_col`, ...)
+//! still do, so a one-off value doesn't mean changing the buffer's options.
+
+use super::TextBuffer;
+use super::formatting::LineEnding;
+use crate::buffer::util::is_word_char;
+
+/// Per-buffer settings; see the module docs for who reads what.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferOptions {
+    /// Columns between tab stops (`tabstop`). Defaults to 4, matching the TUI.
+    pub tab_width: usize,
+    /// Insert spaces instead of a `'\t'` for Tab (`expandtab`).
+    pub expand_tab: bool,
+    /// Chars that count as word chars on top of `[A-Za-z0-9_]` (like `iskeyword`).
+    pub word_chars: String,
+    /// Make sure a saved file ends in a line break (`fixendofline`). Off by
+    /// default, so saving writes exactly what's in the buffer.
+    pub final_newline: bool,
+    /// The line break `final_newline` adds.
+    pub line_ending: LineEnding,
+    /// Write a crash-recovery swap file every this many edits (like
+    /// `updatecount`). `None`, the default, means no swap file.
+    pub swap_every: Option<u64>,
+}
+
+impl Default for BufferOptions {
+    fn default() -> Self {
+        Self {
+            tab_width: 4,
+            expand_tab: false,
+            word_chars: String::new(),
+            final_newline: false,
+            line_ending: LineEnding::default(),
+            swap_every: None,
+        }
+    }
+}
+
+impl BufferOptions {
+    /// Whether `ch` is a word char under these options.
+    pub fn is_word_char(&self, ch: char) -> bool {
+        is_word_char(ch) || self.word_chars.contains(ch)
+    }
+}
+
+impl TextBuffer {
+    /// The buffer's options.
+    #[inline]
+    pub fn options(&self) -> &BufferOptions {
+        &self.options
+    }
+
+    /// Mutable access to the buffer's options.
+    #[inline]
+    pub fn options_mut(&mut self) -> &mut BufferOptions {
+        &mut self.options
+    }
+
+    /// Replace all of the buffer's options at once.
+    #[inline]
+    pub fn set_options(&mut self, options: BufferOptions) {
+        self.options = options;
+    }
+}
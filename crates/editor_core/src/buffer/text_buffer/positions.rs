@@ -9,12 +9,12 @@
 //!   matching Ropey's indexing model.
 //! - Methods clamp inputs defensively, so higher-level code can stay simpler.
 //! - Visual column/grapheme cluster concerns are deliberately out of scope here;
-//!   those can be layered on later (eg. a view layer that maps `Pos` <-> screen).
+//!   `graphemes.rs` converts columns, and the view maps `Pos` <-> screen.
 
 use std::cmp::min;
 
 use super::TextBuffer;
-use crate::buffer::Pos;
+use crate::buffer::{Pos, Selection};
 
 impl TextBuffer {
     /// Clamp a position to a valid location in the buffer.
@@ -97,10 +97,13 @@ impl TextBuffer {
     ///
     /// This is a simple version (no goal/preferred column tracking).
     /// NOTE: Same as above :)
+    ///
+    /// Never moves onto the phantom line after a trailing `'\n'` (see
+    /// `effective_len_lines`).
     #[inline]
     pub fn move_down(&self, pos: Pos) -> Pos {
         let pos = self.clamp_pos(pos);
-        let last = self.len_lines().saturating_sub(1);
+        let last = self.effective_len_lines().saturating_sub(1);
         if pos.line >= last {
             return pos;
         }
@@ -131,4 +134,24 @@ impl TextBuffer {
             Some(self.rope.char(c - 1))
         }
     }
+
+    /// Extend `sel` linewise so it covers every line from the anchor's line to
+    /// `line` (Vim's `V` then moving the cursor).
+    ///
+    /// Going down (or staying on the anchor's line), the anchor moves to the
+    /// start of its line and the cursor to the end of `line`. Going up, the
+    /// ends swap: the anchor sits at the end of its line and the cursor at the
+    /// start of `line`. Either way the cursor stays on `line`, and extending
+    /// back across the anchor just flips direction. `line` is clamped.
+    pub fn extend_to_line(&self, sel: Selection, line: usize) -> Selection {
+        let anchor_line = self.clamp_line(sel.anchor.line);
+        let line = self.clamp_line(line);
+        let line_start = |l| Pos::new(l, 0);
+        let line_end = |l| Pos::new(l, self.line_len_chars(l));
+        if line >= anchor_line {
+            Selection::new(line_start(anchor_line), line_end(line))
+        } else {
+            Selection::new(line_end(anchor_line), line_start(line))
+        }
+    }
 }
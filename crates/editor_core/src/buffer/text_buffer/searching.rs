@@ -0,0 +1,263 @@
+//! Plain-text search for `TextBuffer`.
+//!
+//! Matching is done char by char over Ropey's char iterator, keeping a sliding
+//! window the length of the needle. The window carries over from one rope chunk
+//! to the next, so a match straddling a chunk boundary is found like any other,
+//! and nothing is collected up front: `matches(..).take(n)` only scans as far as
+//! the n-th match.
+//!
+//! Matches don't overlap: scanning resumes just past the end of each match.
+//!
+//! `replace_all_smart_case` and `replace_in_range` are the editing operations
+//! here: they're search-driven, and the first needs the matched text to pick a
+//! casing.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use ropey::iter::Chars;
+
+use super::TextBuffer;
+use super::options::BufferOptions;
+use crate::buffer::Pos;
+use crate::text::{CharIdx, CharRange};
+
+/// How a needle is compared against buffer text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SearchOptions {
+    /// Compare chars case-insensitively (simple per-char lowercasing).
+    pub ignore_case: bool,
+    /// Only match where the needle isn't preceded or followed by a word char
+    /// (per the buffer's `word_chars`).
+    pub whole_word: bool,
+}
+
+impl SearchOptions {
+    fn chars_eq(self, a: char, b: char) -> bool {
+        a == b || (self.ignore_case && a.to_lowercase().eq(b.to_lowercase()))
+    }
+}
+
+/// Lazy iterator behind [`TextBuffer::matches`].
+#[derive(Debug, Clone)]
+struct Matches<'a> {
+    chars: Chars<'a>,
+    needle: Vec<char>,
+    opts: SearchOptions,
+    /// For `whole_word`'s idea of a word char.
+    options: &'a BufferOptions,
+    /// The last `needle.len()` chars scanned.
+    window: VecDeque<char>,
+    /// Char index just past the window.
+    pos: usize,
+    /// The char right before the window, for whole-word checks.
+    before: Option<char>,
+}
+
+impl Matches<'_> {
+    fn window_matches(&self) -> bool {
+        self.window.len() == self.needle.len()
+            && self
+                .window
+                .iter()
+                .zip(&self.needle)
+                .all(|(&a, &b)| self.opts.chars_eq(a, b))
+    }
+
+    /// Slide the window forward by one char; `false` at the end of the buffer.
+    fn advance(&mut self) -> bool {
+        let Some(ch) = self.chars.next() else {
+            return false;
+        };
+        if self.window.len() == self.needle.len() {
+            self.before = self.window.pop_front();
+        }
+        self.window.push_back(ch);
+        self.pos += 1;
+        true
+    }
+}
+
+impl Iterator for Matches<'_> {
+    type Item = CharRange;
+
+    fn next(&mut self) -> Option<CharRange> {
+        if self.needle.is_empty() {
+            return None;
+        }
+
+        while self.advance() {
+            if !self.window_matches() {
+                continue;
+            }
+            if self.opts.whole_word {
+                let after = self.chars.clone().next();
+                let is_word = |ch: char| self.options.is_word_char(ch);
+                if self.before.is_some_and(is_word) || after.is_some_and(is_word) {
+                    continue;
+                }
+            }
+
+            let range = CharRange::new(CharIdx(self.pos - self.needle.len()), CharIdx(self.pos));
+            // Start the next match after this one.
+            self.before = self.window.back().copied();
+            self.window.clear();
+            return Some(range);
+        }
+        None
+    }
+}
+
+impl TextBuffer {
+    /// Lazily iterate over the non-overlapping matches of `needle`, in order.
+    ///
+    /// An empty needle matches nothing.
+    pub fn matches<'a>(
+        &'a self,
+        needle: &'a str,
+        opts: SearchOptions,
+    ) -> impl Iterator<Item = CharRange> + 'a {
+        self.matches_from(needle, opts, 0)
+    }
+
+    /// Like [`TextBuffer::matches`], but only matches starting at or after char
+    /// `from` (clamped), and without scanning the text before it.
+    ///
+    /// Whole-word checks still see the char before `from`. Since matches don't
+    /// overlap, starting mid-way can pick different ones where they'd overlap
+    /// (`aa` in `aaa` from 1 finds 1..3, not 0..2).
+    pub fn matches_from<'a>(
+        &'a self,
+        needle: &'a str,
+        opts: SearchOptions,
+        from: usize,
+    ) -> impl Iterator<Item = CharRange> + 'a {
+        let from = from.min(self.len_chars());
+        let needle: Vec<char> = needle.chars().collect();
+        Matches {
+            chars: self.rope.chars_at(from),
+            window: VecDeque::with_capacity(needle.len()),
+            needle,
+            opts,
+            options: &self.options,
+            pos: from,
+            before: from.checked_sub(1).map(|i| self.rope.char(i)),
+        }
+    }
+
+    /// Every match of `needle`, collected.
+    ///
+    /// Prefer [`TextBuffer::matches`] when only the first few are needed.
+    pub fn find_all(&self, needle: &str, opts: SearchOptions) -> Vec<CharRange> {
+        self.matches(needle, opts).collect()
+    }
+
+    /// The next (or, with `forward` false, previous) whole-word occurrence of
+    /// the word under `pos`, wrapping around the buffer (Vim's `*` and `#`).
+    ///
+    /// The match is case-sensitive. If the word occurs only once, its own range
+    /// is returned. `None` if `pos` isn't on a word char.
+    pub fn search_word_under_cursor(&self, pos: Pos, forward: bool) -> Option<CharRange> {
+        let (range, word) = self.word_at(pos)?;
+        let opts = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        let mut matches = self.matches(&word, opts);
+        if forward {
+            let first = matches.next();
+            let next = std::iter::once(first?)
+                .chain(matches)
+                .find(|m| m.start > range.start);
+            next.or(first)
+        } else {
+            let all: Vec<CharRange> = matches.collect();
+            all.iter()
+                .rev()
+                .find(|m| m.start < range.start)
+                .or(all.last())
+                .copied()
+        }
+    }
+
+    /// Replace every match of `find` (ignoring case) with `replace`, recased to
+    /// follow each match: `foo` lowercases it all, `Foo` capitalises its first
+    /// letter and `FOO` uppercases it all. Matches with any other casing get
+    /// `replace` unchanged.
+    ///
+    /// Returns the number of replacements (0 on a read-only buffer).
+    pub fn replace_all_smart_case(&mut self, find: &str, replace: &str) -> usize {
+        if self.read_only {
+            return 0;
+        }
+        let opts = SearchOptions {
+            ignore_case: true,
+            ..SearchOptions::default()
+        };
+        let edits: Vec<(CharRange, String)> = self
+            .matches(find, opts)
+            .map(|range| {
+                let matched = self.slice_chars(range.start.0, range.end.0);
+                (range, match_case(&matched, replace))
+            })
+            .collect();
+
+        for (range, text) in edits.iter().rev() {
+            self.splice(range.start.0, range.end.0, text);
+        }
+        edits.len()
+    }
+
+    /// Replace every match of `find` that lies entirely within the char range
+    /// `range` with `replace` (`:'<,'>s/find/replace/g`).
+    ///
+    /// Matches are found as by `matches` over the whole buffer, so a match that
+    /// starts or ends outside `range` is left alone rather than cut short, and
+    /// whole-word checks see the text around the range. `range` is clamped to
+    /// the buffer. Returns the number of replacements (0 on a read-only buffer).
+    pub fn replace_in_range(
+        &mut self,
+        range: Range<usize>,
+        find: &str,
+        replace: &str,
+        opts: SearchOptions,
+    ) -> usize {
+        if self.read_only {
+            return 0;
+        }
+        let end = range.end.min(self.len_chars());
+        let edits: Vec<CharRange> = self
+            .matches(find, opts)
+            .take_while(|m| m.start.0 < end)
+            .filter(|m| m.start.0 >= range.start && m.end.0 <= end)
+            .collect();
+
+        for m in edits.iter().rev() {
+            self.splice(m.start.0, m.end.0, replace);
+        }
+        edits.len()
+    }
+}
+
+/// `replacement` with the casing of `matched` (see `replace_all_smart_case`).
+fn match_case(matched: &str, replacement: &str) -> String {
+    let mut letters = matched.chars().filter(|c| c.is_alphabetic());
+    let Some(first) = letters.next() else {
+        return replacement.to_string();
+    };
+    let rest: Vec<char> = letters.collect();
+
+    if first.is_lowercase() && rest.iter().all(|c| c.is_lowercase()) {
+        replacement.to_lowercase()
+    } else if first.is_uppercase() && rest.iter().all(|c| c.is_lowercase()) {
+        let mut chars = replacement.chars();
+        chars
+            .next()
+            .map(|c| c.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    } else if first.is_uppercase() && rest.iter().all(|c| c.is_uppercase()) {
+        replacement.to_uppercase()
+    } else {
+        replacement.to_string()
+    }
+}
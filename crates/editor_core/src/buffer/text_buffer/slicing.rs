@@ -17,6 +17,7 @@ impl TextBuffer {
     /// For large buffers, this allocates. Kept as an inherent method so call sites
     /// can use `b.to_string()` without depending on `Display`/`ToString`.
     #[inline]
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         self.rope().to_string()
     }
@@ -37,6 +38,20 @@ impl TextBuffer {
         self.rope().slice(start..end).to_string()
     }
 
+    /// Get a `String` for a UTF-8 byte range `[start, end)`.
+    ///
+    /// - Offsets are clamped to the buffer bounds, and reversed ranges swapped.
+    /// - An offset inside a multi-byte char is rounded down to the start of that
+    ///   char, so the result is always valid UTF-8.
+    ///
+    /// Char indices remain the primary model; this is for byte-based interop.
+    pub fn byte_slice(&self, byte_range: std::ops::Range<usize>) -> String {
+        let maxb = self.len_bytes();
+        let start = min(byte_range.start, maxb);
+        let end = min(byte_range.end, maxb);
+        self.slice_chars(self.rope.byte_to_char(start), self.rope.byte_to_char(end))
+    }
+
     /// Get the selected text for a selection (ordered).
     ///
     /// This is a convenience API; it allocates.
@@ -56,4 +71,38 @@ impl TextBuffer {
         let end = self.pos_to_char(b);
         self.slice_chars(start, end)
     }
+
+    /// The lines around `line` (grep's `-B`/`-A`), as `(line index, text)` pairs.
+    ///
+    /// The window is `before` lines above through `after` lines below, clamped to
+    /// the buffer, so near the top or bottom fewer lines come back. Line text
+    /// excludes the trailing `'\n'`. `line` itself is clamped into a valid range.
+    pub fn context_around(&self, line: usize, before: usize, after: usize) -> Vec<(usize, String)> {
+        let line = self.clamp_line(line);
+        let first = line.saturating_sub(before);
+        let last = self.clamp_line(line.saturating_add(after));
+        (first..=last).map(|l| (l, self.line_string(l))).collect()
+    }
+
+    /// The text of lines `start_line..=end_line` for a linewise register (`yy`).
+    ///
+    /// Every line comes back newline-terminated, including a final line that has
+    /// no `'\n'` in the buffer, so the result always pastes as whole lines. The
+    /// lines are taken as by `line_span`.
+    pub fn yank_lines(&self, start_line: usize, end_line: usize) -> String {
+        let (first, last) = self.line_span(start_line, end_line);
+
+        let start = self.line_to_char(first);
+        let end = if last + 1 < self.len_lines() {
+            self.line_to_char(last + 1)
+        } else {
+            self.len_chars()
+        };
+
+        let mut text = self.slice_chars(start, end);
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text
+    }
 }
@@ -0,0 +1,67 @@
+//! Cheap content checkpoints for `TextBuffer`.
+//!
+//! A `Rope` clone shares its tree nodes with the original, so taking a snapshot
+//! is O(1) and allocates almost nothing up front. The cost shows up later: every
+//! edit to the live buffer copies the nodes on its path instead of mutating them
+//! in place, and the snapshot keeps the old nodes alive. Memory therefore grows
+//! with how much has been edited since the snapshot, up to a full second copy of
+//! the text. Drop snapshots once they're no longer needed.
+//!
+//! This is independent of any undo history: it's meant for "try an operation and
+//! bail" style code paths.
+
+use ropey::Rope;
+
+use super::TextBuffer;
+
+/// A saved copy of a buffer's content and generation; see [`TextBuffer::snapshot`].
+#[derive(Debug, Clone)]
+pub struct BufferSnapshot {
+    rope: Rope,
+    generation: u64,
+    content_id: u64,
+}
+
+impl BufferSnapshot {
+    /// The buffer generation at the time of the snapshot.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// The buffer's `content_id` at the time of the snapshot, which `restore`
+    /// brings back.
+    #[inline]
+    pub fn content_id(&self) -> u64 {
+        self.content_id
+    }
+}
+
+impl TextBuffer {
+    /// Take a checkpoint of the current content.
+    #[inline]
+    pub fn snapshot(&self) -> BufferSnapshot {
+        BufferSnapshot {
+            rope: self.rope.clone(),
+            generation: self.generation,
+            content_id: self.content_id,
+        }
+    }
+
+    /// Roll the buffer's content back to `snap`.
+    ///
+    /// The generation still moves forward, never back: caches keyed on it trust a
+    /// matching generation without looking at the text, and the generations after
+    /// the snapshot may already have been seen with other content. What comes back
+    /// is the snapshot's `content_id`, so a dirty check against a saved id sees the
+    /// buffer as clean again. Anchors aren't part of the snapshot; they're clamped
+    /// into the restored text.
+    #[inline]
+    pub fn restore(&mut self, snap: BufferSnapshot) {
+        self.rope = snap.rope;
+        self.bump_generation();
+        self.content_id = snap.content_id;
+        self.anchors.clamp(self.rope.len_chars());
+        self.last_edit = None;
+    }
+}
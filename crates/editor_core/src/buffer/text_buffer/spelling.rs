@@ -0,0 +1,31 @@
+//! Spell-check hook for `TextBuffer`.
+//!
+//! The core has no dictionary: callers plug one in through [`WordChecker`] and
+//! get back the ranges of flagged words, which the TUI can underline. Words are
+//! the buffer's word-char runs (see `TextBuffer::words`).
+
+use std::ops::Range;
+
+use super::TextBuffer;
+use crate::text::CharRange;
+
+/// Decides which words are misspelled.
+pub trait WordChecker {
+    fn is_misspelled(&self, word: &str) -> bool;
+}
+
+impl TextBuffer {
+    /// Char ranges of the words in `range` that `checker` flags, in order.
+    ///
+    /// A word cut by either end of `range` is checked (and returned) whole, so
+    /// checking just the visible text still sees complete words. `range` is
+    /// clamped to the buffer.
+    pub fn misspellings(&self, checker: &dyn WordChecker, range: Range<usize>) -> Vec<CharRange> {
+        let end = range.end.min(self.len_chars());
+        self.words_from(range.start.min(end))
+            .take_while(|(range, _)| range.start.0 < end)
+            .filter(|(_, word)| checker.is_misspelled(word))
+            .map(|(range, _)| range)
+            .collect()
+    }
+}
@@ -0,0 +1,41 @@
+//! Crash-recovery swap files for `TextBuffer`.
+//!
+//! A swap file holds the buffer's current text and the path of the file it
+//! belongs to, written next to that file as `.<name>.swp` (see `io::swap_path`).
+//! Writing is opt-in: `swap_if_due` only writes when `BufferOptions::swap_every`
+//! is set, every that many edits. Saving the buffer to its own path deletes the
+//! swap file, and `io::recover_from_swap` reads one back.
+
+use anyhow::{Result, bail};
+
+use super::TextBuffer;
+
+impl TextBuffer {
+    /// Write the buffer's text and path to its swap file now.
+    ///
+    /// Errors if the buffer has no path (there's nowhere to put the swap file).
+    pub fn write_swap(&self) -> Result<()> {
+        let Some(path) = self.path() else {
+            bail!("no file name");
+        };
+        crate::io::write_swap_file(path, self)
+    }
+
+    /// Write the swap file if `swap_every` edits have happened since the last
+    /// one. Buffers without a path or with swap files off are skipped.
+    ///
+    /// Returns whether a swap file was written. Meant to be called after edits
+    /// (or on a timer); it's cheap when nothing is due.
+    pub fn swap_if_due(&mut self) -> Result<bool> {
+        let Some(every) = self.options.swap_every else {
+            return Ok(false);
+        };
+        let edits = self.generation.wrapping_sub(self.swap_generation);
+        if self.path.is_none() || edits < every.max(1) {
+            return Ok(false);
+        }
+        self.write_swap()?;
+        self.swap_generation = self.generation;
+        Ok(true)
+    }
+}
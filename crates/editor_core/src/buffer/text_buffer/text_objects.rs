@@ -0,0 +1,170 @@
+//! Text-object resolution for `TextBuffer` (Vim's `iw`, `a"`, `i(`, ...).
+//!
+//! A text object is a range around a position rather than a motion away from it.
+//! Each kind comes in two flavours:
+//! - inner: just the contents (the word, or what's between the delimiters);
+//! - around: the contents plus the delimiters (for words, the adjacent whitespace).
+//!
+//! Quote objects stay on the cursor's line; bracket objects may span lines.
+
+use super::TextBuffer;
+use super::words::CharClass;
+use crate::buffer::Pos;
+use crate::text::{CharIdx, CharRange};
+
+/// What a text object selects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextObjectKind {
+    /// A run of word chars, of other punctuation, or of whitespace (`iw`/`aw`).
+    Word,
+    /// Text between a pair of this quote char on the line (`i"`/`a'`).
+    Quote(char),
+    /// Text between an enclosing `open`/`close` pair (`i(`/`a{`).
+    Bracket(char, char),
+}
+
+impl TextBuffer {
+    /// The range of the text object of `kind` at `pos`.
+    ///
+    /// With `inner`, the range excludes delimiters (and, for words, surrounding
+    /// whitespace). Returns `None` if there is no such object at `pos`: an empty
+    /// line for `Word`, no quote pair on the line, or no enclosing bracket pair.
+    pub fn text_object(&self, pos: Pos, kind: TextObjectKind, inner: bool) -> Option<CharRange> {
+        let pos = self.clamp_pos(pos);
+        let (start, end) = match kind {
+            TextObjectKind::Word => self.word_object(pos, inner)?,
+            TextObjectKind::Quote(quote) => {
+                let (open, close) = self.quote_pair(pos, quote)?;
+                if inner {
+                    (open + 1, close)
+                } else {
+                    (open, close + 1)
+                }
+            }
+            TextObjectKind::Bracket(open, close) => {
+                let (open, close) = self.enclosing_brackets(pos, open, close)?;
+                if inner {
+                    (open + 1, close)
+                } else {
+                    (open, close + 1)
+                }
+            }
+        };
+        Some(CharRange::new(CharIdx(start), CharIdx(end)))
+    }
+
+    /// Char bounds of the word object at `pos`.
+    ///
+    /// `aw` on a word takes the whitespace after it, or the whitespace before it
+    /// if there is none after; `aw` on whitespace takes the word after it.
+    fn word_object(&self, pos: Pos, inner: bool) -> Option<(usize, usize)> {
+        let run = self.char_class(self.char_at(pos)?);
+        let line = self.line_char_range(pos.line);
+        let (line_start, line_end) = (line.start, line.end);
+        let class_at = |c: usize| self.class_of_char(c);
+
+        let at = self.pos_to_char(pos);
+        let mut start = at;
+        while start > line_start && class_at(start - 1) == run {
+            start -= 1;
+        }
+        let mut end = at + 1;
+        while end < line_end && class_at(end) == run {
+            end += 1;
+        }
+        if inner {
+            return Some((start, end));
+        }
+
+        if run == CharClass::Whitespace {
+            if end < line_end {
+                let next = class_at(end);
+                while end < line_end && class_at(end) == next {
+                    end += 1;
+                }
+            }
+            return Some((start, end));
+        }
+
+        let word_end = end;
+        while end < line_end && class_at(end) == CharClass::Whitespace {
+            end += 1;
+        }
+        if end == word_end {
+            while start > line_start && class_at(start - 1) == CharClass::Whitespace {
+                start -= 1;
+            }
+        }
+        Some((start, end))
+    }
+
+    /// Char indices of the `quote` pair around (or after) `pos` on its line.
+    ///
+    /// Quotes pair up left to right, skipping backslash-escaped ones. If `pos`
+    /// isn't inside a pair, the first pair after it is used, like Vim.
+    fn quote_pair(&self, pos: Pos, quote: char) -> Option<(usize, usize)> {
+        let line = self.line_char_range(pos.line);
+        let at = self.pos_to_char(pos);
+
+        let mut quotes = Vec::new();
+        let mut escaped = false;
+        for c in line {
+            let ch = self.rope.char(c);
+            if ch == quote && !escaped {
+                quotes.push(c);
+            }
+            escaped = ch == '\\' && !escaped;
+        }
+
+        quotes
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .find(|&(_, close)| at <= close)
+    }
+
+    /// Char indices of the innermost `open`/`close` pair enclosing `pos`.
+    ///
+    /// A bracket under the cursor counts as enclosing it. Nested pairs are
+    /// skipped by depth counting; brackets inside strings aren't special.
+    fn enclosing_brackets(&self, pos: Pos, open: char, close: char) -> Option<(usize, usize)> {
+        let at = self.pos_to_char(pos);
+        let len = self.len_chars();
+
+        let open_idx = match self.char_at(pos) {
+            Some(ch) if ch == open => at,
+            Some(ch) if ch == close => return Some((self.matching_open(at, open, close)?, at)),
+            _ => self.matching_open(at, open, close)?,
+        };
+
+        let mut depth = 0usize;
+        for c in open_idx + 1..len {
+            let ch = self.rope.char(c);
+            if ch == open {
+                depth += 1;
+            } else if ch == close {
+                if depth == 0 {
+                    return Some((open_idx, c));
+                }
+                depth -= 1;
+            }
+        }
+        None
+    }
+
+    /// The unmatched `open` before char index `at`, if any.
+    fn matching_open(&self, at: usize, open: char, close: char) -> Option<usize> {
+        let mut depth = 0usize;
+        for c in (0..at).rev() {
+            let ch = self.rope.char(c);
+            if ch == close {
+                depth += 1;
+            } else if ch == open {
+                if depth == 0 {
+                    return Some(c);
+                }
+                depth -= 1;
+            }
+        }
+        None
+    }
+}
@@ -1,49 +1,93 @@
 //! Word-motion helpers for `TextBuffer`.
 //!
 //! Current behavior
-//! - “Word characters” are defined by `buffer::util::is_word_char`.
-//!   Right now that is ASCII-ish (`[A-Za-z0-9_]`), but it’s centralized so I
-//!   can later swap it for Vim-like `'iskeyword'` rules, Unicode word
-//!   segmentation, identifier rules, etc.
+//! - “Word characters” are `[A-Za-z0-9_]` (`buffer::util::is_word_char`) plus
+//!   the buffer's `BufferOptions::word_chars`, a small `'iskeyword'` stand-in.
+//!   Unicode word segmentation or identifier rules could replace it later.
+//! - Motions stop at [`CharClass`] transitions, like Vim's `w`/`b`/`e`: a run of
+//!   word chars and a run of punctuation are separate words (`foo.bar` is three).
 //! - Motions operate on **char indices** via Ropey.
 
-use super::super::util::is_word_char;
 use super::TextBuffer;
+use super::options::BufferOptions;
 use crate::buffer::Pos;
+use crate::text::{CharIdx, CharRange};
+
+/// What kind of char sits at a position, as far as word motions care.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CharClass {
+    /// A word char (see `BufferOptions::is_word_char`).
+    Word,
+    /// Whitespace other than a newline.
+    Whitespace,
+    /// Anything else: operators, brackets, quotes, ...
+    Punct,
+    /// The `'\n'` ending a line.
+    Newline,
+}
+
+impl CharClass {
+    /// The class of `ch` with the default word chars; see
+    /// [`TextBuffer::char_class`] for the buffer's own.
+    pub fn of(ch: char) -> Self {
+        Self::with_word_chars(ch, &BufferOptions::default())
+    }
+
+    fn with_word_chars(ch: char, options: &BufferOptions) -> Self {
+        if ch == '\n' {
+            CharClass::Newline
+        } else if ch.is_whitespace() {
+            CharClass::Whitespace
+        } else if options.is_word_char(ch) {
+            CharClass::Word
+        } else {
+            CharClass::Punct
+        }
+    }
+
+    /// Whitespace or a newline: the gaps word motions skip over.
+    pub fn is_blank(self) -> bool {
+        matches!(self, CharClass::Whitespace | CharClass::Newline)
+    }
+}
 
 impl TextBuffer {
-    /// Find the start of the “word” before `pos`.
+    /// The class of the char at `pos`.
     ///
-    /// Word characters are defined by `is_word_char`.
+    /// The end of a line (other than the last) is its `Newline`; returns `None`
+    /// at the end of the buffer.
+    pub fn char_class_at(&self, pos: Pos) -> Option<CharClass> {
+        let c = self.pos_to_char(pos);
+        (c < self.len_chars()).then(|| self.class_of_char(c))
+    }
+
+    /// The class of `ch`, using this buffer's `word_chars`.
+    pub fn char_class(&self, ch: char) -> CharClass {
+        CharClass::with_word_chars(ch, &self.options)
+    }
+
+    /// The class of the char at absolute char index `c` (must be in bounds).
+    pub(super) fn class_of_char(&self, c: usize) -> CharClass {
+        self.char_class(self.rope.char(c))
+    }
+
+    /// Find the start of the “word” before `pos`.
     ///
-    /// Rough semantics:
-    /// - If immediately left of `pos` is a delimiter, skip delimiters left.
-    /// - Then skip word characters left.
+    /// Rough semantics (Vim's `b`):
+    /// - Skip whitespace (including newlines) left.
+    /// - Then skip the run of the same class as the char reached.
     /// - Return the resulting position.
-    ///
-    /// This is meant to map cleanly to editor motions like “b”.
     pub fn word_start_before(&self, pos: Pos) -> Pos {
         let mut c = self.pos_to_char(pos);
-        if c == 0 {
-            return Pos::zero();
-        }
 
-        // If we're at a delimiter, first skip delimiters left.
-        while c > 0 {
-            let ch = self.rope.char(c - 1);
-            if is_word_char(ch) {
-                break;
-            }
+        while c > 0 && self.class_of_char(c - 1).is_blank() {
             c -= 1;
         }
-
-        // ...then skip word chars left.
-        while c > 0 {
-            let ch = self.rope.char(c - 1);
-            if !is_word_char(ch) {
-                break;
+        if c > 0 {
+            let class = self.class_of_char(c - 1);
+            while c > 0 && self.class_of_char(c - 1) == class {
+                c -= 1;
             }
-            c -= 1;
         }
 
         self.char_to_pos(c)
@@ -51,37 +95,175 @@ impl TextBuffer {
 
     /// Find the end of the “word” after `pos`.
     ///
-    /// Word characters are defined by `is_word_char`.
-    ///
     /// Rough semantics:
-    /// - From `pos`, skip delimiters right until a word character or EOF.
-    /// - Then skip word characters right.
-    /// - Return the resulting position.
+    /// - From `pos`, skip whitespace (including newlines) right.
+    /// - Then skip the run of the same class as the char reached.
+    /// - Return the resulting position (just past the word).
     ///
-    /// This is meant to map cleanly to editor motions like “w/e” depending on how
-    /// I apply it.
+    /// This is meant to map cleanly to editor motions like “e”.
     pub fn word_end_after(&self, pos: Pos) -> Pos {
         let mut c = self.pos_to_char(pos);
         let maxc = self.len_chars();
 
-        // Skip delimiters right.
-        while c < maxc {
-            let ch = self.rope.char(c);
-            if is_word_char(ch) {
-                break;
-            }
+        while c < maxc && self.class_of_char(c).is_blank() {
             c += 1;
         }
+        if c < maxc {
+            let class = self.class_of_char(c);
+            while c < maxc && self.class_of_char(c) == class {
+                c += 1;
+            }
+        }
+
+        self.char_to_pos(c)
+    }
 
-        // Skip word chars right.
-        while c < maxc {
-            let ch = self.rope.char(c);
-            if !is_word_char(ch) {
-                break;
+    /// Find the start of the next “word” after `pos`.
+    ///
+    /// Rough semantics (Vim's `w`):
+    /// - Skip the run of the same class `pos` is in (unless it's whitespace).
+    /// - Then skip whitespace (including newlines).
+    /// - Return the resulting position (EOF if there is no next word).
+    pub fn word_start_after(&self, pos: Pos) -> Pos {
+        let mut c = self.pos_to_char(pos);
+        let maxc = self.len_chars();
+
+        if c < maxc {
+            let class = self.class_of_char(c);
+            if !class.is_blank() {
+                while c < maxc && self.class_of_char(c) == class {
+                    c += 1;
+                }
             }
+        }
+
+        while c < maxc && self.class_of_char(c).is_blank() {
             c += 1;
         }
 
         self.char_to_pos(c)
     }
+
+    /// The word under `pos`: its char range and text.
+    ///
+    /// Expands left and right from the char at `pos` over word characters (the same
+    /// `BufferOptions::is_word_char` predicate the motions use). Returns `None` if the char at
+    /// `pos` is a delimiter, whitespace, or past the end of the line.
+    ///
+    /// This is meant for things like `*` search and hover.
+    pub fn word_at(&self, pos: Pos) -> Option<(CharRange, String)> {
+        let is_word_char = |ch: char| self.options.is_word_char(ch);
+        let ch = self.char_at(pos)?;
+        if !is_word_char(ch) {
+            return None;
+        }
+
+        let at = self.pos_to_char(pos);
+        let mut start = at;
+        while start > 0 && is_word_char(self.rope.char(start - 1)) {
+            start -= 1;
+        }
+        let mut end = at + 1;
+        while end < self.len_chars() && is_word_char(self.rope.char(end)) {
+            end += 1;
+        }
+
+        let range = CharRange::new(CharIdx(start), CharIdx(end));
+        Some((range, self.slice_chars(start, end)))
+    }
+
+    /// Every run of word chars in the buffer, with its char range, in order
+    /// (eg. for completing from buffer words or spell-checking).
+    ///
+    /// Scans with Ropey's chunked char iterator, so only the current word is
+    /// held in memory, never the whole text.
+    pub fn words(&self) -> impl Iterator<Item = (CharRange, String)> + '_ {
+        self.words_from(0)
+    }
+
+    /// Like `words`, starting with the word containing char index `from` (which
+    /// must be in bounds).
+    pub(super) fn words_from(&self, from: usize) -> impl Iterator<Item = (CharRange, String)> + '_ {
+        let mut from = from;
+        while from > 0 && self.options.is_word_char(self.rope.char(from - 1)) {
+            from -= 1;
+        }
+        let mut chars = self
+            .rope
+            .chars_at(from)
+            .enumerate()
+            .map(move |(i, ch)| (from + i, ch));
+        std::iter::from_fn(move || {
+            let (start, first) = chars.find(|&(_, ch)| self.options.is_word_char(ch))?;
+            let mut word = String::from(first);
+            let mut end = start + 1;
+            for (_, ch) in chars.by_ref() {
+                if !self.options.is_word_char(ch) {
+                    break;
+                }
+                word.push(ch);
+                end += 1;
+            }
+            Some((CharRange::new(CharIdx(start), CharIdx(end)), word))
+        })
+    }
+
+    /// Words from the buffer that complete the partial word before `pos`
+    /// (Vim's `Ctrl-N`/`Ctrl-P`), nearest to the cursor first, at most `max`.
+    ///
+    /// The partial word is the run of word chars ending at `pos`. Matching is
+    /// smart-case: a partial word without uppercase letters matches any case,
+    /// otherwise case must match exactly. Each distinct word appears once, at its
+    /// nearest occurrence; the word being typed and words equal to the partial
+    /// one are left out. No partial word means no candidates.
+    pub fn completion_candidates(&self, pos: Pos, max: usize) -> Vec<String> {
+        let pos = self.clamp_pos(pos);
+        let at = self.pos_to_char(pos);
+        let line_start = self.line_to_char(pos.line);
+        let mut start = at;
+        while start > line_start && self.options.is_word_char(self.rope.char(start - 1)) {
+            start -= 1;
+        }
+        if start == at || max == 0 {
+            return Vec::new();
+        }
+
+        let prefix = self.slice_chars(start, at);
+        let ignore_case = !prefix.chars().any(char::is_uppercase);
+        let fold = |s: &str| {
+            if ignore_case {
+                s.to_lowercase()
+            } else {
+                s.to_string()
+            }
+        };
+        let folded_prefix = fold(&prefix);
+
+        let mut candidates: Vec<(usize, String)> = self
+            .words()
+            .filter(|(range, word)| {
+                range.start.0 != start
+                    && word.chars().count() > prefix.chars().count()
+                    && fold(word).starts_with(&folded_prefix)
+            })
+            .map(|(range, word)| {
+                let distance = if range.end.0 <= at {
+                    at - range.end.0
+                } else {
+                    range.start.0 - at
+                };
+                (distance, word)
+            })
+            .collect();
+        // Stable, so at equal distance the earlier word wins.
+        candidates.sort_by_key(|&(distance, _)| distance);
+
+        let mut seen = std::collections::HashSet::new();
+        candidates
+            .into_iter()
+            .filter(|(_, word)| seen.insert(word.clone()))
+            .map(|(_, word)| word)
+            .take(max)
+            .collect()
+    }
 }
@@ -0,0 +1,439 @@
+//! Line-level diffing between buffers.
+//!
+//! This powers "show changes since save"-style features (eg. gutter markers in
+//! the TUI). Lines are compared by their FNV hash (see `text::Fnv64`), which keeps
+//! the inner loop cheap; a 64-bit collision between two different lines is
+//! possible in theory but not worth paying full string comparisons for here.
+//!
+//! The algorithm is Myers' diff in its linear-space form (recursing on the
+//! "middle snake"): O((n + m) * D) time for D changed lines and O(n + m) memory,
+//! so a whole-file formatter run that touches a few lines stays cheap no matter
+//! how far apart the changes are.
+//!
+//! `apply_text_minimal` builds on the line diff to turn "replace everything with
+//! this new text" (an external formatter, a reload) into a handful of small
+//! edits, so anchors and cursors outside the changed regions stay put.
+
+use anyhow::Result;
+
+use crate::buffer::{Edit, TextBuffer};
+use crate::text::Fnv64;
+
+/// One entry of a line diff, in order.
+///
+/// Line indices are 0-based: `Removed` refers to the old (`self`) buffer and
+/// `Added` to the new (`other`) buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineDiff {
+    /// Line `n` of the new buffer has no counterpart in the old one.
+    Added(usize),
+    /// Line `n` of the old buffer is gone from the new one.
+    Removed(usize),
+    /// Old line `old` and new line `new` are identical.
+    Unchanged { old: usize, new: usize },
+}
+
+impl TextBuffer {
+    /// Diff this buffer (old) against `other` (new), line by line.
+    ///
+    /// Line content is compared without the trailing newline. Modified lines show
+    /// up as a `Removed` followed by an `Added`.
+    pub fn diff_lines(&self, other: &TextBuffer) -> Vec<LineDiff> {
+        diff_line_hashes(&line_hashes(self), &line_hashes(other))
+    }
+}
+
+impl TextBuffer {
+    /// Make the buffer's content `new_text` by editing only what differs.
+    ///
+    /// Runs of changed lines are found with [`TextBuffer::diff_lines`], then
+    /// narrowed to the chars that actually differ (common leading/trailing chars
+    /// of each run are kept). The edits are applied in one [`TextBuffer::apply_edits`]
+    /// call and returned, with ranges in terms of the content *before* the call.
+    ///
+    /// Fails (changing nothing) only if the buffer is read-only.
+    pub fn apply_text_minimal(&mut self, new_text: &str) -> Result<Vec<Edit>> {
+        let new = TextBuffer::from_str(new_text);
+        let diff = self.diff_lines(&new);
+
+        // Changed line runs as (old lines, new lines), both half-open.
+        let mut hunks = Vec::new();
+        let mut start: Option<(usize, usize)> = None;
+        let (mut old_line, mut new_line) = (0, 0);
+        for entry in diff {
+            match entry {
+                LineDiff::Unchanged { .. } => {
+                    if let Some((o, n)) = start.take() {
+                        hunks.push((o..old_line, n..new_line));
+                    }
+                    old_line += 1;
+                    new_line += 1;
+                }
+                LineDiff::Removed(_) => {
+                    start.get_or_insert((old_line, new_line));
+                    old_line += 1;
+                }
+                LineDiff::Added(_) => {
+                    start.get_or_insert((old_line, new_line));
+                    new_line += 1;
+                }
+            }
+        }
+        if let Some((o, n)) = start {
+            hunks.push((o..old_line, n..new_line));
+        }
+
+        let edits: Vec<Edit> = hunks
+            .into_iter()
+            .filter_map(|(old_lines, new_lines)| {
+                // A run at the very end starts at the newline before it instead, since
+                // the last line of either text may or may not end in one.
+                let at_end = old_lines.end == self.len_lines() && new_lines.end == new.len_lines();
+                let bounds = |buffer: &TextBuffer, lines: std::ops::Range<usize>| {
+                    let start = if at_end && lines.start > 0 {
+                        buffer.line_char_range(lines.start - 1).end
+                    } else {
+                        buffer.line_to_char(lines.start)
+                    };
+                    let end = if lines.end < buffer.len_lines() {
+                        buffer.line_to_char(lines.end)
+                    } else {
+                        buffer.len_chars()
+                    };
+                    start..end
+                };
+                minimal_edit(self, bounds(self, old_lines), &new, bounds(&new, new_lines))
+            })
+            .collect();
+
+        self.apply_edits(edits.clone())?;
+        Ok(edits)
+    }
+}
+
+/// The edit turning `old[old_range]` into `new[new_range]`, minus their common
+/// leading and trailing chars; `None` if they're identical.
+fn minimal_edit(
+    old: &TextBuffer,
+    old_range: std::ops::Range<usize>,
+    new: &TextBuffer,
+    new_range: std::ops::Range<usize>,
+) -> Option<Edit> {
+    let old_slice = old.rope().slice(old_range.clone());
+    let new_slice = new.rope().slice(new_range.clone());
+
+    let prefix = old_slice
+        .chars()
+        .zip(new_slice.chars())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let max_suffix = old_range.len().min(new_range.len()) - prefix;
+    let suffix = old_slice
+        .chars_at(old_slice.len_chars())
+        .reversed()
+        .zip(new_slice.chars_at(new_slice.len_chars()).reversed())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let range = old_range.start + prefix..old_range.end - suffix;
+    let insert = new.slice_chars(new_range.start + prefix, new_range.end - suffix);
+    if range.is_empty() && insert.is_empty() {
+        return None;
+    }
+    Some(Edit::replace(range, insert))
+}
+
+/// Hash every line's content (excluding the trailing `'\n'`).
+pub(crate) fn line_hashes(buffer: &TextBuffer) -> Vec<u64> {
+    (0..buffer.len_lines())
+        .map(|line| {
+            let mut h = Fnv64::new();
+            for chunk in buffer.rope().slice(buffer.line_char_range(line)).chunks() {
+                h.write(chunk.as_bytes());
+            }
+            h.finish()
+        })
+        .collect()
+}
+
+/// Shortest edit script between two sequences of line hashes (Myers).
+///
+/// Within each run of changes, removals come before additions.
+pub(crate) fn diff_line_hashes(old: &[u64], new: &[u64]) -> Vec<LineDiff> {
+    let mut out = Vec::with_capacity(old.len().max(new.len()));
+    diff_range(old, new, (0, 0), &mut out);
+
+    // The recursion can split one run of changes into an addition followed by a
+    // removal; put removals first so a modified line reads as delete then add.
+    for run in out.chunk_by_mut(|a, b| {
+        !matches!(a, LineDiff::Unchanged { .. }) && !matches!(b, LineDiff::Unchanged { .. })
+    }) {
+        run.sort_by_key(|entry| matches!(entry, LineDiff::Added(_)));
+    }
+    out
+}
+
+/// Diff `old` against `new`, whose first lines are `at.0` and `at.1` in the full
+/// sequences, appending to `out`.
+fn diff_range(old: &[u64], new: &[u64], at: (usize, usize), out: &mut Vec<LineDiff>) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let unchanged = |out: &mut Vec<LineDiff>, old_from: usize, new_from: usize, len: usize| {
+        out.extend((0..len).map(|k| LineDiff::Unchanged {
+            old: at.0 + old_from + k,
+            new: at.1 + new_from + k,
+        }));
+    };
+
+    unchanged(out, 0, 0, prefix);
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+    let mid_at = (at.0 + prefix, at.1 + prefix);
+
+    if old_mid.is_empty() {
+        out.extend((0..new_mid.len()).map(|j| LineDiff::Added(mid_at.1 + j)));
+    } else if new_mid.is_empty() {
+        out.extend((0..old_mid.len()).map(|i| LineDiff::Removed(mid_at.0 + i)));
+    } else {
+        // Both ends differ, so there are at least two changes and each half of
+        // the split below is strictly smaller.
+        let (start, end) = middle_snake(old_mid, new_mid);
+        diff_range(&old_mid[..start.0], &new_mid[..start.1], mid_at, out);
+        unchanged(out, prefix + start.0, prefix + start.1, end.0 - start.0);
+        diff_range(
+            &old_mid[end.0..],
+            &new_mid[end.1..],
+            (mid_at.0 + end.0, mid_at.1 + end.1),
+            out,
+        );
+    }
+    unchanged(out, old.len() - suffix, new.len() - suffix, suffix);
+}
+
+/// The middle snake of a shortest edit script from `old` to `new`: a run of
+/// matching lines, as `(old, new)` start and end points, that some shortest
+/// script passes through about halfway.
+///
+/// Searches forward from the start and backward from the end at once, keeping
+/// only the furthest point reached on each diagonal (O(n + m) memory).
+fn middle_snake(old: &[u64], new: &[u64]) -> ((usize, usize), (usize, usize)) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let delta = n - m;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+    // Furthest x on diagonal k (x - y = k) going forward, and on diagonal c going
+    // backward (measured from the end of both sequences).
+    let mut forward = vec![0isize; 2 * offset as usize + 1];
+    let mut backward = vec![0isize; 2 * offset as usize + 1];
+    let at = |k: isize| (k + offset) as usize;
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let start = (x, y);
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[at(k)] = x;
+
+            let c = delta - k;
+            if delta % 2 != 0 && c.abs() < d && x + backward[at(c)] >= n {
+                return (to_point(start), to_point((x, y)));
+            }
+        }
+
+        for c in (-d..=d).step_by(2) {
+            let mut x = if c == -d || (c != d && backward[at(c - 1)] < backward[at(c + 1)]) {
+                backward[at(c + 1)]
+            } else {
+                backward[at(c - 1)] + 1
+            };
+            let mut y = x - c;
+            let end = (n - x, m - y);
+            while x < n && y < m && old[(n - x - 1) as usize] == new[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[at(c)] = x;
+
+            let k = delta - c;
+            if delta % 2 == 0 && k.abs() <= d && x + forward[at(k)] >= n {
+                return (to_point((n - x, m - y)), to_point(end));
+            }
+        }
+    }
+    unreachable!("the forward and backward searches always meet by D / 2")
+}
+
+fn to_point((x, y): (isize, isize)) -> (usize, usize) {
+    (x as usize, y as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LineDiff::*;
+    use super::*;
+    use crate::Pos;
+    use crate::buffer::Bias;
+
+    fn diff(a: &str, b: &str) -> Vec<LineDiff> {
+        TextBuffer::from_str(a).diff_lines(&TextBuffer::from_str(b))
+    }
+
+    #[test]
+    fn pure_insertion() {
+        assert_eq!(
+            diff("a\nc", "a\nb\nc"),
+            vec![
+                Unchanged { old: 0, new: 0 },
+                Added(1),
+                Unchanged { old: 1, new: 2 }
+            ]
+        );
+    }
+
+    #[test]
+    fn pure_deletion() {
+        assert_eq!(
+            diff("a\nb\nc\nd", "a\nd"),
+            vec![
+                Unchanged { old: 0, new: 0 },
+                Removed(1),
+                Removed(2),
+                Unchanged { old: 3, new: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn modified_line_is_delete_then_add() {
+        assert_eq!(
+            diff("a\nb\nc\n", "a\nB\nc\n"),
+            vec![
+                Unchanged { old: 0, new: 0 },
+                Removed(1),
+                Added(1),
+                Unchanged { old: 2, new: 2 },
+                Unchanged { old: 3, new: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_buffers_are_all_unchanged() {
+        let d = diff("x\ny", "x\ny");
+        assert!(d.iter().all(|e| matches!(e, Unchanged { .. })));
+        assert_eq!(d.len(), 2);
+    }
+
+    /// Longest common subsequence length, the slow way.
+    fn lcs_len(a: &[u64], b: &[u64]) -> usize {
+        let mut row = vec![0usize; b.len() + 1];
+        for x in a {
+            let mut diag = 0;
+            for (j, y) in b.iter().enumerate() {
+                let up = row[j + 1];
+                row[j + 1] = if x == y { diag + 1 } else { up.max(row[j]) };
+                diag = up;
+            }
+        }
+        row[b.len()]
+    }
+
+    #[test]
+    fn diff_is_a_shortest_edit_script() {
+        let cases: [(&[u64], &[u64]); 7] = [
+            (&[1, 2, 3, 4, 5], &[1, 3, 4, 6, 5]),
+            (&[1, 2, 3], &[4, 5, 6]),
+            (&[1, 2, 1, 2, 1], &[2, 1, 2, 1, 2]),
+            (&[1, 1, 1, 2], &[2, 1, 1, 1]),
+            (&[7, 1, 2, 3, 8], &[9, 1, 2, 3]),
+            (&[], &[1, 2]),
+            (&[5, 6, 7, 8, 9, 1], &[1, 5, 7, 9]),
+        ];
+        for (old, new) in cases {
+            let d = diff_line_hashes(old, new);
+            let kept: Vec<(usize, usize)> = d
+                .iter()
+                .filter_map(|e| match *e {
+                    Unchanged { old, new } => Some((old, new)),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(kept.len(), lcs_len(old, new), "{old:?} -> {new:?}");
+            assert!(kept.iter().all(|&(o, n)| old[o] == new[n]));
+            assert!(kept.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1));
+
+            let removed = d.iter().filter(|e| matches!(e, Removed(_))).count();
+            let added = d.iter().filter(|e| matches!(e, Added(_))).count();
+            assert_eq!(removed + kept.len(), old.len());
+            assert_eq!(added + kept.len(), new.len());
+        }
+    }
+
+    #[test]
+    fn far_apart_changes_in_a_big_file_stay_small() {
+        // Changing the first and last lines used to fill a table covering every
+        // line in between.
+        let old: Vec<u64> = (0..200_000).collect();
+        let mut new = old.clone();
+        new[0] = u64::MAX;
+        *new.last_mut().unwrap() = u64::MAX - 1;
+
+        let d = diff_line_hashes(&old, &new);
+        let changes: Vec<_> = d
+            .iter()
+            .filter(|e| !matches!(e, Unchanged { .. }))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![&Removed(0), &Added(0), &Removed(199_999), &Added(199_999)]
+        );
+    }
+
+    fn apply_minimal(old: &str, new: &str) -> Vec<Edit> {
+        let mut buffer = TextBuffer::from_str(old);
+        let edits = buffer.apply_text_minimal(new).unwrap();
+        assert_eq!(buffer.to_string(), new);
+        edits
+    }
+
+    #[test]
+    fn minimal_edit_touches_only_the_changed_line() {
+        let old = "fn main() {\n    let x=1;\n    println!(x);\n}\n";
+        let new = "fn main() {\n    let x = 1;\n    println!(x);\n}\n";
+        assert_eq!(apply_minimal(old, new), vec![Edit::replace(21..22, " = ")]);
+    }
+
+    #[test]
+    fn minimal_edit_keeps_anchors_elsewhere() {
+        let mut buffer = TextBuffer::from_str("a\nb\nc\nd");
+        let anchor = buffer.create_anchor(Pos::new(3, 1), Bias::Before);
+        buffer.apply_text_minimal("a\nB\nc\nd").unwrap();
+        assert_eq!(buffer.resolve(anchor), Some(Pos::new(3, 1)));
+    }
+
+    #[test]
+    fn minimal_edit_handles_trailing_newline_changes() {
+        assert_eq!(
+            apply_minimal("a\nb", "a\nc"),
+            vec![Edit::replace(2..3, "c")]
+        );
+        assert_eq!(apply_minimal("a\n", "a"), vec![Edit::delete(1..2)]);
+        assert_eq!(apply_minimal("a", "a\nb\n"), vec![Edit::insert(1, "\nb\n")]);
+        assert_eq!(apply_minimal("x\ny", "x\ny"), vec![]);
+        assert_eq!(apply_minimal("", "new"), vec![Edit::insert(0, "new")]);
+    }
+}
@@ -0,0 +1,254 @@
+//! Folded line regions.
+//!
+//! `Folds` only tracks which line ranges are collapsed; it doesn't own or look
+//! at the text, so it can live in view state next to the buffer it describes.
+//! Ranges are inclusive `(start_line, end_line)` pairs kept sorted and disjoint:
+//! folding over an existing fold absorbs it rather than nesting.
+//!
+//! Ropey has no stable line identities, so whoever edits the buffer reports the
+//! line-level shape of each edit (`lines_inserted` / `lines_deleted`) and the
+//! ranges are shifted to match.
+//!
+//! `Folds::compute_indent_folds` derives foldable regions from indentation, for
+//! zero-config folding (Vim's `foldmethod=indent`).
+
+use crate::buffer::TextBuffer;
+
+/// A set of folded (collapsed) line ranges.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Folds {
+    ranges: Vec<(usize, usize)>,
+}
+
+impl Folds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The folded ranges, in order.
+    pub fn ranges(&self) -> &[(usize, usize)] {
+        &self.ranges
+    }
+
+    /// Fold lines `start..=end` (either order), merging with any fold it touches.
+    pub fn fold(&mut self, start: usize, end: usize) {
+        let (mut start, mut end) = if start <= end {
+            (start, end)
+        } else {
+            (end, start)
+        };
+        self.ranges.retain(|&(s, e)| {
+            let overlaps = s <= end && start <= e;
+            if overlaps {
+                start = start.min(s);
+                end = end.max(e);
+            }
+            !overlaps
+        });
+        let at = self.ranges.partition_point(|&(s, _)| s < start);
+        self.ranges.insert(at, (start, end));
+    }
+
+    /// Open the fold containing `line`. Returns whether there was one.
+    pub fn unfold(&mut self, line: usize) -> bool {
+        match self.fold_at(line) {
+            Some(i) => {
+                self.ranges.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether `line` is inside a fold (including its first line).
+    pub fn is_folded(&self, line: usize) -> bool {
+        self.fold_at(line).is_some()
+    }
+
+    /// The lines to draw for a buffer of `len_lines` lines, in order.
+    ///
+    /// Each fold shows up as its first line only.
+    pub fn visible_lines(&self, len_lines: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut folds = self.ranges.iter().peekable();
+        let mut line = 0;
+        std::iter::from_fn(move || {
+            if line >= len_lines {
+                return None;
+            }
+            let current = line;
+            while folds.next_if(|&&(_, e)| e < current).is_some() {}
+            line = match folds.peek() {
+                Some(&&(s, e)) if s == current => e + 1,
+                _ => current + 1,
+            };
+            Some(current)
+        })
+    }
+
+    /// Shift folds for `count` new lines inserted before line `at`.
+    ///
+    /// Lines inserted inside a fold (after its first line) grow it.
+    pub fn lines_inserted(&mut self, at: usize, count: usize) {
+        for (s, e) in &mut self.ranges {
+            if at <= *s {
+                *s += count;
+                *e += count;
+            } else if at <= *e {
+                *e += count;
+            }
+        }
+    }
+
+    /// Shift folds for lines `start..start + count` being deleted.
+    ///
+    /// A fold loses whatever part of it was deleted, and disappears entirely if
+    /// nothing is left.
+    pub fn lines_deleted(&mut self, start: usize, count: usize) {
+        let end = start + count;
+        self.ranges.retain_mut(|(s, e)| {
+            let overlap = (*e + 1).min(end).saturating_sub((*s).max(start));
+            let remaining = *e + 1 - *s - overlap;
+            if remaining == 0 {
+                return false;
+            }
+            *s = if *s < start {
+                *s
+            } else if *s >= end {
+                *s - count
+            } else {
+                start
+            };
+            *e = *s + remaining - 1;
+            true
+        });
+    }
+
+    /// Foldable regions implied by indentation, sorted by first line.
+    ///
+    /// A region is a header line plus the lines after it that are indented
+    /// deeper, so nested blocks give nested regions. Blank lines don't end a
+    /// region, but one never ends on a blank line either. Tabs advance to the
+    /// next multiple of `tab_width` (0 is treated as 1).
+    pub fn compute_indent_folds(buffer: &TextBuffer, tab_width: usize) -> Vec<(usize, usize)> {
+        let mut regions = Vec::new();
+        // Open headers as (line, indent), indents strictly increasing.
+        let mut open: Vec<(usize, usize)> = Vec::new();
+        let mut last_nonblank = 0;
+
+        for line in 0..buffer.len_lines() {
+            if buffer.is_blank_line(line) {
+                continue;
+            }
+            let indent = buffer.indent_width(line, tab_width);
+            while let Some(&(header, header_indent)) = open.last() {
+                if header_indent < indent {
+                    break;
+                }
+                open.pop();
+                if last_nonblank > header {
+                    regions.push((header, last_nonblank));
+                }
+            }
+            open.push((line, indent));
+            last_nonblank = line;
+        }
+        for (header, _) in open {
+            if last_nonblank > header {
+                regions.push((header, last_nonblank));
+            }
+        }
+
+        regions.sort_unstable();
+        regions
+    }
+
+    fn fold_at(&self, line: usize) -> Option<usize> {
+        let i = self.ranges.partition_point(|&(_, e)| e < line);
+        self.ranges.get(i).filter(|&&(s, _)| s <= line).map(|_| i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_unfold_and_visible_lines() {
+        let mut folds = Folds::new();
+        folds.fold(5, 3);
+        folds.fold(8, 9);
+        assert!(folds.is_folded(3) && folds.is_folded(5) && folds.is_folded(9));
+        assert!(!folds.is_folded(2) && !folds.is_folded(6));
+        assert_eq!(
+            folds.visible_lines(11).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 6, 7, 8, 10]
+        );
+
+        // Folding across both merges them.
+        folds.fold(4, 8);
+        assert_eq!(folds.ranges(), &[(3, 9)]);
+
+        assert!(folds.unfold(5));
+        assert!(!folds.unfold(5));
+        assert_eq!(folds.visible_lines(3).count(), 3);
+    }
+
+    #[test]
+    fn inserting_lines_shifts_and_grows_folds() {
+        let mut folds = Folds::new();
+        folds.fold(2, 4);
+        folds.fold(8, 9);
+
+        // Inside the first fold: it grows, the later one shifts.
+        folds.lines_inserted(3, 2);
+        assert_eq!(folds.ranges(), &[(2, 6), (10, 11)]);
+
+        // Right before a fold's first line: it just shifts.
+        folds.lines_inserted(2, 1);
+        assert_eq!(folds.ranges(), &[(3, 7), (11, 12)]);
+    }
+
+    #[test]
+    fn deleting_lines_shrinks_and_removes_folds() {
+        let mut folds = Folds::new();
+        folds.fold(2, 6);
+        folds.fold(10, 11);
+
+        // Lines 5..8 straddle the end of the first fold.
+        folds.lines_deleted(5, 3);
+        assert_eq!(folds.ranges(), &[(2, 4), (7, 8)]);
+
+        // Lines 1..4 take the start of the first fold with them.
+        folds.lines_deleted(1, 3);
+        assert_eq!(folds.ranges(), &[(1, 1), (4, 5)]);
+
+        folds.lines_deleted(0, 5);
+        assert_eq!(folds.ranges(), &[(0, 0)]);
+        folds.lines_deleted(0, 1);
+        assert!(folds.ranges().is_empty());
+    }
+
+    #[test]
+    fn indent_folds_nest_and_span_blank_lines() {
+        let text = "\
+fn a() {
+    if x {
+        one();
+
+        two();
+    }
+    three();
+}
+
+fn b() {}
+class C:
+\tdef f():
+\t\tpass
+";
+        let buffer = TextBuffer::from_str(text);
+        assert_eq!(
+            Folds::compute_indent_folds(&buffer, 4),
+            vec![(0, 6), (1, 4), (10, 12), (11, 12)]
+        );
+    }
+}
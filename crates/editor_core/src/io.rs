@@ -3,38 +3,485 @@
 //! This module is intentionally small and UI-agnostic. It just provides helpers
 //! that read and write UTF-8 text to/from the rope-backed `TextBuffer`.
 
-use std::path::Path;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Cursor, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context as _, Result};
+use anyhow::{Context as _, Result, bail};
+use ropey::Rope;
 
-use crate::buffer::TextBuffer;
+use crate::buffer::{Selection, TextBuffer};
 
 /// Read a UTF-8 file into a `TextBuffer`.
 ///
-/// This is a simple, whole-file read (simple for the early development stage):
-/// - loads entire file into memory
-/// - requires valid UTF-8
+/// The file is streamed into the rope through a `BufReader` (`Rope::from_reader`),
+/// which validates UTF-8 as it goes, so the text is never held as one big
+/// `Vec`/`String` alongside the rope.
 ///
-/// Might add higher-level functions for encoding detection and streaming IO later
+/// The buffer remembers `path`, so `save_buffer` can write it back.
+///
+/// Might add higher-level functions for encoding detection later.
 pub fn load_buffer(path: impl AsRef<Path>) -> Result<TextBuffer> {
     let path = path.as_ref();
 
-    let bytes = std::fs::read(path)
+    let file = File::open(path)
+        .with_context(|| format!("failed to read file: {}", path.to_string_lossy()))?;
+
+    let rope = Rope::from_reader(BufReader::new(file)).map_err(|err| read_error(path, err))?;
+
+    let mut buffer = TextBuffer::from_rope(rope);
+    buffer.set_path(Some(path.to_path_buf()));
+    Ok(buffer)
+}
+
+/// How many leading bytes `load_buffer_detect` looks at for NUL bytes.
+pub const BINARY_SNIFF_BYTES: usize = 8000;
+
+/// A loaded buffer plus what was noticed about the file while loading it.
+#[derive(Debug)]
+pub struct LoadResult {
+    pub buffer: TextBuffer,
+    /// The file looks binary: its first `BINARY_SNIFF_BYTES` contain a NUL.
+    pub is_binary: bool,
+}
+
+/// Like `load_buffer`, but checks whether the file looks binary first.
+///
+/// Only the first `BINARY_SNIFF_BYTES` are scanned for a NUL (the same heuristic
+/// Git and most editors use). A binary file isn't an error: it's decoded lossily
+/// (invalid UTF-8 becomes U+FFFD) and flagged, so the caller can warn or refuse.
+/// Text files are streamed and validated exactly like `load_buffer`.
+pub fn load_buffer_detect(path: impl AsRef<Path>) -> Result<LoadResult> {
+    let path = path.as_ref();
+
+    let mut file = BufReader::new(
+        File::open(path)
+            .with_context(|| format!("failed to read file: {}", path.to_string_lossy()))?,
+    );
+    let mut head = Vec::with_capacity(BINARY_SNIFF_BYTES);
+    file.by_ref()
+        .take(BINARY_SNIFF_BYTES as u64)
+        .read_to_end(&mut head)
         .with_context(|| format!("failed to read file: {}", path.to_string_lossy()))?;
 
-    let text = String::from_utf8(bytes)
-        .with_context(|| format!("file is not valid UTF-8: {}", path.to_string_lossy()))?;
+    if head.contains(&0) {
+        let mut bytes = head;
+        file.read_to_end(&mut bytes)
+            .with_context(|| format!("failed to read file: {}", path.to_string_lossy()))?;
+        let mut buffer = TextBuffer::from_str(&String::from_utf8_lossy(&bytes));
+        buffer.set_path(Some(path.to_path_buf()));
+        return Ok(LoadResult {
+            buffer,
+            is_binary: true,
+        });
+    }
 
-    Ok(TextBuffer::from_str(&text))
+    let rope =
+        Rope::from_reader(Cursor::new(head).chain(file)).map_err(|err| read_error(path, err))?;
+    let mut buffer = TextBuffer::from_rope(rope);
+    buffer.set_path(Some(path.to_path_buf()));
+    Ok(LoadResult {
+        buffer,
+        is_binary: false,
+    })
+}
+
+/// Wrap an error from reading `path` into the rope, calling out bad UTF-8.
+fn read_error(path: &Path, err: std::io::Error) -> anyhow::Error {
+    let what = match err.kind() {
+        ErrorKind::InvalidData => "file is not valid UTF-8",
+        _ => "failed to read file",
+    };
+    anyhow::Error::new(err).context(format!("{what}: {}", path.to_string_lossy()))
+}
+
+/// Like `load_buffer`, but refuses files larger than `max_bytes`.
+///
+/// The size comes from the file's metadata, so an oversized file is rejected
+/// before any of it is read (rather than running out of memory halfway through).
+pub fn load_buffer_with_limit(path: impl AsRef<Path>, max_bytes: u64) -> Result<TextBuffer> {
+    let path = path.as_ref();
+
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("failed to read file: {}", path.to_string_lossy()))?
+        .len();
+    if size > max_bytes {
+        bail!(
+            "file is too large to open ({size} bytes, limit is {max_bytes}): {}",
+            path.to_string_lossy()
+        );
+    }
+
+    load_buffer(path)
+}
+
+/// Write a `TextBuffer` back to its own file (`TextBuffer::path`).
+///
+/// Errors if the buffer has no path; use `save_buffer_as` for those.
+pub fn save_buffer(buffer: &TextBuffer) -> Result<()> {
+    let Some(path) = buffer.path() else {
+        bail!("no file name");
+    };
+    save_buffer_as(path, buffer)
 }
 
-/// Write a `TextBuffer` to a UTF-8 file.
+/// Write a `TextBuffer` to a UTF-8 file at `path`.
 ///
-/// This writes the entire buffer to disk in one go.
+/// This writes the entire buffer to disk in one go, honouring the buffer's
+/// `final_newline` option like `save_buffer_to_writer`. The buffer's own path
+/// isn't changed.
 /// Will add variants later for stuff like incremental or atomic writes.
-pub fn save_buffer(path: impl AsRef<Path>, buffer: &TextBuffer) -> Result<()> {
+pub fn save_buffer_as(path: impl AsRef<Path>, buffer: &TextBuffer) -> Result<()> {
     let path = path.as_ref();
-    std::fs::write(path, buffer.to_string())
+    let file = File::create(path)
         .with_context(|| format!("failed to write file: {}", path.to_string_lossy()))?;
+    save_buffer_to_writer(BufWriter::new(file), buffer)
+        .with_context(|| format!("failed to write file: {}", path.to_string_lossy()))?;
+
+    // The file now has everything the swap file was protecting.
+    if buffer.path() == Some(path) {
+        remove_swap(path)?;
+    }
     Ok(())
 }
+
+/// Write just the text selected by `sel` to `path`, replacing the file
+/// (`:'<,'>w file`).
+///
+/// The selection is written as is: no `final_newline` handling.
+pub fn save_selection(path: impl AsRef<Path>, buffer: &TextBuffer, sel: Selection) -> Result<()> {
+    let path = path.as_ref();
+    let file = File::create(path)
+        .with_context(|| format!("failed to write file: {}", path.to_string_lossy()))?;
+    write_selection(file, buffer, sel)
+        .with_context(|| format!("failed to write file: {}", path.to_string_lossy()))
+}
+
+/// Append the text selected by `sel` to `path`, creating the file if it doesn't
+/// exist (`:'<,'>w >> file`).
+pub fn append_selection(path: impl AsRef<Path>, buffer: &TextBuffer, sel: Selection) -> Result<()> {
+    let path = path.as_ref();
+    let file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .with_context(|| format!("failed to append to file: {}", path.to_string_lossy()))?;
+    write_selection(file, buffer, sel)
+        .with_context(|| format!("failed to append to file: {}", path.to_string_lossy()))
+}
+
+fn write_selection(mut writer: impl Write, buffer: &TextBuffer, sel: Selection) -> Result<()> {
+    writer
+        .write_all(buffer.slice_selection(sel).as_bytes())
+        .context("failed to write text")?;
+    writer.flush().context("failed to flush writer")?;
+    Ok(())
+}
+
+/// First line of every swap file.
+const SWAP_MAGIC: &str = "redox swap 1";
+
+/// Where the swap file for `path` lives: `.<name>.swp` in the same directory.
+pub fn swap_path(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}.swp"))
+}
+
+/// Write `buffer` to the swap file for `path`.
+///
+/// The format is a `SWAP_MAGIC` line, a line with `path`, then the text as is.
+/// A path that isn't valid UTF-8 (or spans lines) couldn't be read back from
+/// that header, so it's refused up front rather than recorded lossily.
+pub(crate) fn write_swap_file(path: &Path, buffer: &TextBuffer) -> Result<()> {
+    let Some(name) = path.to_str().filter(|name| !name.contains('\n')) else {
+        bail!(
+            "can't write a swap file for this file name: {}",
+            path.to_string_lossy()
+        );
+    };
+    let swap = swap_path(path);
+    let file = File::create(&swap)
+        .with_context(|| format!("failed to write swap file: {}", swap.to_string_lossy()))?;
+    let mut writer = BufWriter::new(file);
+    let header = format!("{SWAP_MAGIC}\n{name}\n");
+    writer
+        .write_all(header.as_bytes())
+        .and_then(|()| buffer.rope().write_to(&mut writer))
+        .and_then(|()| writer.flush())
+        .with_context(|| format!("failed to write swap file: {}", swap.to_string_lossy()))
+}
+
+/// Load the swap file left behind for `path`, if there is one.
+///
+/// The recovered buffer has the swap file's text and the path recorded in it;
+/// the swap file itself is left alone until the buffer is saved (or
+/// `remove_swap` is called). A file that isn't a swap file is an error.
+pub fn recover_from_swap(path: impl AsRef<Path>) -> Result<Option<TextBuffer>> {
+    let swap = swap_path(path);
+    let contents = match std::fs::read_to_string(&swap) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(read_error(&swap, err)),
+    };
+
+    let parsed = contents
+        .strip_prefix(SWAP_MAGIC)
+        .and_then(|rest| rest.strip_prefix('\n'))
+        .and_then(|rest| rest.split_once('\n'));
+    let Some((original, text)) = parsed else {
+        bail!("not a swap file: {}", swap.to_string_lossy());
+    };
+
+    let mut buffer = TextBuffer::from_str(text);
+    buffer.set_path(Some(PathBuf::from(original)));
+    Ok(Some(buffer))
+}
+
+/// Delete the swap file for `path`. A missing swap file isn't an error.
+pub fn remove_swap(path: impl AsRef<Path>) -> Result<()> {
+    let swap = swap_path(path);
+    match std::fs::remove_file(&swap) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(anyhow::Error::new(err).context(
+            format!("failed to remove swap file: {}", swap.to_string_lossy()),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Read UTF-8 text from any reader (stdin, a socket, ...) into a `TextBuffer`.
+///
+/// The text is streamed into the rope in chunks (`Rope::from_reader`) rather than
+/// buffered whole first. Invalid UTF-8 is an error, same as `load_buffer`.
+pub fn load_buffer_from_reader(reader: impl Read) -> Result<TextBuffer> {
+    let rope = Rope::from_reader(reader).context("failed to read UTF-8 text")?;
+    Ok(TextBuffer::from_rope(rope))
+}
+
+/// Write a `TextBuffer` to any writer, chunk by chunk.
+///
+/// With the buffer's `final_newline` option set, a non-empty buffer that doesn't
+/// end in a line break gets its `line_ending` appended (on disk only).
+pub fn save_buffer_to_writer(mut writer: impl Write, buffer: &TextBuffer) -> Result<()> {
+    let rope = buffer.rope();
+    rope.write_to(&mut writer).context("failed to write text")?;
+
+    let options = buffer.options();
+    let last = rope.len_chars().checked_sub(1).map(|c| rope.char(c));
+    if options.final_newline && last.is_some_and(|ch| ch != '\n' && ch != '\r') {
+        writer
+            .write_all(options.line_ending.as_str().as_bytes())
+            .context("failed to write text")?;
+    }
+    writer.flush().context("failed to flush writer")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pos;
+    use crate::buffer::LineEnding;
+    use crate::test_util::TempFile;
+
+    #[test]
+    fn reader_round_trip() {
+        let text = "héllo\nwörld\n".repeat(1000);
+        let buffer = load_buffer_from_reader(Cursor::new(text.as_bytes())).unwrap();
+        assert_eq!(buffer.to_string(), text);
+        assert_eq!(buffer.generation(), 0);
+
+        let mut out = Vec::new();
+        save_buffer_to_writer(&mut out, &buffer).unwrap();
+        assert_eq!(out, text.as_bytes());
+    }
+
+    #[test]
+    fn final_newline_option_is_applied_on_save() {
+        let mut buffer = TextBuffer::from_str("a\nb");
+        let saved = |buffer: &TextBuffer| {
+            let mut out = Vec::new();
+            save_buffer_to_writer(&mut out, buffer).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(saved(&buffer), "a\nb");
+
+        buffer.options_mut().final_newline = true;
+        assert_eq!(saved(&buffer), "a\nb\n");
+        buffer.options_mut().line_ending = LineEnding::Crlf;
+        assert_eq!(saved(&buffer), "a\nb\r\n");
+        assert_eq!(buffer.to_string(), "a\nb");
+
+        buffer.insert(Pos::new(1, 1), "\n");
+        assert_eq!(saved(&buffer), "a\nb\n");
+        assert_eq!(saved(&TextBuffer::new()), "");
+    }
+
+    #[test]
+    fn large_file_loads_intact() {
+        let file = TempFile::new("load.txt");
+        let path = file.path();
+        let text = "line with some text, ünïcode and\ttabs\n".repeat(20_000);
+        std::fs::write(path, &text).unwrap();
+
+        let buffer = load_buffer(path).unwrap();
+        assert!(buffer.rope().chunks().count() > 1);
+        assert_eq!(buffer.path(), Some(path));
+        assert_eq!(buffer.to_string(), text);
+
+        std::fs::write(path, b"bad \xff utf-8").unwrap();
+        let err = load_buffer(path).unwrap_err();
+        assert!(err.to_string().starts_with("file is not valid UTF-8"));
+    }
+
+    #[test]
+    fn save_writes_back_to_the_loaded_path() {
+        let file = TempFile::new("save.txt");
+        let path = file.path();
+        std::fs::write(path, "old").unwrap();
+
+        let mut buffer = load_buffer(path).unwrap();
+        assert_eq!(buffer.name(), path.file_name().unwrap().to_str().unwrap());
+        buffer.insert(Pos::zero(), "new ");
+        save_buffer(&buffer).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "new old");
+
+        let unnamed = TextBuffer::from_str("text");
+        let err = save_buffer(&unnamed).unwrap_err();
+        assert_eq!(err.to_string(), "no file name");
+    }
+
+    #[test]
+    fn selection_saves_and_appends() {
+        let file = TempFile::new("selection.txt");
+        let path = file.path();
+        let _ = std::fs::remove_file(path);
+        let buffer = TextBuffer::from_str("one\ntwo\nthree\n");
+
+        // Lines 1..2 (linewise, so ending at the start of line 3).
+        let sel = Selection::new(Pos::new(1, 0), Pos::new(3, 0));
+        save_selection(path, &buffer, sel).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "two\nthree\n");
+
+        // Saving again replaces; appending adds to the end.
+        save_selection(
+            path,
+            &buffer,
+            Selection::new(Pos::new(0, 0), Pos::new(1, 0)),
+        )
+        .unwrap();
+        append_selection(
+            path,
+            &buffer,
+            Selection::new(Pos::new(2, 3), Pos::new(2, 0)),
+        )
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "one\nthr");
+
+        std::fs::remove_file(path).unwrap();
+        append_selection(path, &buffer, sel).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "two\nthree\n");
+    }
+
+    #[test]
+    fn swap_file_round_trip_and_cleanup() {
+        let file = TempFile::new("swap.txt");
+        let path = file.path();
+        std::fs::write(path, "saved\n").unwrap();
+        assert_eq!(
+            swap_path(path).file_name().unwrap().to_str().unwrap(),
+            format!(".redox_swap_{}.txt.swp", std::process::id())
+        );
+        assert!(recover_from_swap(path).unwrap().is_none());
+
+        let mut buffer = load_buffer(path).unwrap();
+        buffer.insert(Pos::new(1, 0), "unsaved\nlines");
+        buffer.write_swap().unwrap();
+
+        let recovered = recover_from_swap(path).unwrap().unwrap();
+        assert_eq!(recovered.to_string(), "saved\nunsaved\nlines");
+        assert_eq!(recovered.path(), Some(path));
+
+        // Saving to the buffer's own file deletes the swap file.
+        save_buffer(&buffer).unwrap();
+        assert!(!swap_path(path).exists());
+        assert!(recover_from_swap(path).unwrap().is_none());
+
+        std::fs::write(swap_path(path), "junk").unwrap();
+        assert!(recover_from_swap(path).is_err());
+        remove_swap(path).unwrap();
+        remove_swap(path).unwrap();
+    }
+
+    #[test]
+    fn swap_file_refuses_unrecordable_paths() {
+        let dir = std::env::temp_dir();
+        let mut buffer = TextBuffer::from_str("text");
+
+        buffer.set_path(Some(dir.join("two\nlines.txt")));
+        assert!(buffer.write_swap().is_err());
+        assert!(!swap_path(dir.join("two\nlines.txt")).exists());
+
+        #[cfg(unix)]
+        {
+            use std::ffi::OsStr;
+            use std::os::unix::ffi::OsStrExt;
+
+            let path = dir.join(OsStr::from_bytes(b"redox_\xff.txt"));
+            buffer.set_path(Some(path.clone()));
+            assert!(buffer.write_swap().is_err());
+            assert!(!swap_path(path).exists());
+        }
+    }
+
+    #[test]
+    fn size_limit_is_checked_before_reading() {
+        let file = TempFile::new("limit.txt");
+        let path = file.path();
+        std::fs::write(path, "0123456789").unwrap();
+
+        // Just under and right at the limit both load.
+        assert_eq!(
+            load_buffer_with_limit(path, 11).unwrap().to_string(),
+            "0123456789"
+        );
+        assert_eq!(
+            load_buffer_with_limit(path, 10).unwrap().to_string(),
+            "0123456789"
+        );
+        let err = load_buffer_with_limit(path, 9).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("file is too large to open (10 bytes, limit is 9)")
+        );
+    }
+
+    #[test]
+    fn reader_rejects_invalid_utf8() {
+        let bytes: &[u8] = &[b'o', b'k', 0xff, 0xfe];
+        assert!(load_buffer_from_reader(Cursor::new(bytes)).is_err());
+    }
+
+    #[test]
+    fn nul_byte_flags_file_as_binary() {
+        let file = TempFile::new("binary.bin");
+        let path = file.path();
+        std::fs::write(path, b"\x7fELF\x00\x01\xff\xfe").unwrap();
+
+        let loaded = load_buffer_detect(path).unwrap();
+        assert!(loaded.is_binary);
+        assert!(loaded.buffer.to_string().starts_with("\x7fELF\0\x01"));
+    }
+
+    #[test]
+    fn text_file_is_not_binary() {
+        let file = TempFile::new("text.txt");
+        let path = file.path();
+        let text = "plain text\n".repeat(BINARY_SNIFF_BYTES / 4);
+        std::fs::write(path, &text).unwrap();
+
+        let loaded = load_buffer_detect(path).unwrap();
+        assert!(!loaded.is_binary);
+        assert_eq!(loaded.buffer.to_string(), text);
+    }
+}
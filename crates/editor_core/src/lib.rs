@@ -12,10 +12,16 @@
 //!   but those are not used as the primary index type in this crate.
 
 pub mod buffer;
+pub mod diff;
+pub mod folds;
 pub mod io;
 pub mod logic;
+pub mod marks;
 pub mod text;
 
+#[cfg(test)]
+mod test_util;
+
 // Prefer using the rope-backed buffer implementation from `buffer`.
 // Re-export the common types here for ergonomic access by downstream crates.
 pub use buffer::{Edit, Pos, Selection, TextBuffer};
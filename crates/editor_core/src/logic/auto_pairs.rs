@@ -0,0 +1,98 @@
+//! Auto-pairing of brackets and quotes while typing.
+//!
+//! Typing an opening char inserts its closing partner too, with the cursor left
+//! between them. Typing a closing char that's already under the cursor steps
+//! over it instead of inserting a duplicate. Next to a word, nothing is paired,
+//! so `(` before `foo` or `'` in `don't` insert just the one char.
+
+use crate::buffer::{Pos, Selection, TextBuffer};
+
+/// The usual bracket and quote pairs, as `(open, close)`.
+pub const DEFAULT_PAIRS: &[(char, char)] =
+    &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')];
+
+/// Type `ch` at `pos`, auto-pairing it according to `pairs`.
+///
+/// Returns the new cursor (as an empty selection) and whether any text was
+/// inserted; stepping over an existing closing char inserts nothing.
+pub fn auto_pair_insert(
+    buffer: &mut TextBuffer,
+    pos: Pos,
+    ch: char,
+    pairs: &[(char, char)],
+) -> (Selection, bool) {
+    let pos = buffer.clamp_pos(pos);
+    let next = buffer.char_at(pos);
+
+    if next == Some(ch) && pairs.iter().any(|&(_, close)| close == ch) {
+        return (Selection::empty(Pos::new(pos.line, pos.col + 1)), false);
+    }
+
+    let close = pairs
+        .iter()
+        .find(|&&(open, _)| open == ch)
+        .map(|&(_, close)| close);
+    let is_word_char = |ch: char| buffer.options().is_word_char(ch);
+    let touches_word = next.is_some_and(is_word_char)
+        // A symmetric quote after a word is more likely an apostrophe.
+        || (close == Some(ch) && buffer.char_before(pos).is_some_and(is_word_char));
+
+    let cursor = match close {
+        Some(close) if !touches_word => {
+            let text: String = [ch, close].into_iter().collect();
+            buffer.insert(pos, &text);
+            Pos::new(pos.line, pos.col + 1)
+        }
+        _ => buffer.insert(pos, ch.encode_utf8(&mut [0; 4])),
+    };
+    (Selection::empty(cursor), true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_char(text: &str, col: usize, ch: char) -> (String, Pos, bool) {
+        let mut buffer = TextBuffer::from_str(text);
+        let (sel, inserted) = auto_pair_insert(&mut buffer, Pos::new(0, col), ch, DEFAULT_PAIRS);
+        (buffer.to_string(), sel.cursor, inserted)
+    }
+
+    #[test]
+    fn opening_char_inserts_the_pair() {
+        assert_eq!(type_char("f", 1, '('), ("f()".into(), Pos::new(0, 2), true));
+        assert_eq!(
+            type_char("x = ", 4, '"'),
+            ("x = \"\"".into(), Pos::new(0, 5), true)
+        );
+    }
+
+    #[test]
+    fn closing_char_steps_over_existing_one() {
+        assert_eq!(
+            type_char("f()", 2, ')'),
+            ("f()".into(), Pos::new(0, 3), false)
+        );
+        assert_eq!(
+            type_char("\"\"", 1, '"'),
+            ("\"\"".into(), Pos::new(0, 2), false)
+        );
+    }
+
+    #[test]
+    fn no_pair_next_to_a_word() {
+        assert_eq!(
+            type_char("foo", 0, '('),
+            ("(foo".into(), Pos::new(0, 1), true)
+        );
+        assert_eq!(
+            type_char("dont", 3, '\''),
+            ("don't".into(), Pos::new(0, 4), true)
+        );
+        // Unpaired chars are inserted as usual.
+        assert_eq!(
+            type_char("ab", 1, 'x'),
+            ("axb".into(), Pos::new(0, 2), true)
+        );
+    }
+}
@@ -0,0 +1,9 @@
+//! Editing behaviour layered on top of `TextBuffer`.
+//!
+//! The buffer itself only knows how to splice text; the helpers here decide
+//! *what* to splice for editor conveniences, so the TUI (or tests) can call them
+//! without any UI state.
+
+mod auto_pairs;
+
+pub use auto_pairs::{DEFAULT_PAIRS, auto_pair_insert};
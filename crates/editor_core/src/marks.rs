@@ -0,0 +1,121 @@
+//! Per-line markers (diagnostics, breakpoints, signs) that follow their lines.
+//!
+//! Like `folds`, this is bookkeeping beside the buffer: ropey has no stable line
+//! identities, so the editor reports the line-level shape of each edit
+//! (`lines_inserted` / `lines_deleted`) and stored line numbers are shifted by
+//! the edit's line delta. A mark whose line is deleted goes with it.
+
+/// Values of type `T` attached to buffer lines.
+///
+/// A line can carry any number of marks; they're kept in line order, and in
+/// insertion order within a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMarks<T> {
+    marks: Vec<(usize, T)>,
+}
+
+impl<T> Default for LineMarks<T> {
+    fn default() -> Self {
+        Self { marks: Vec::new() }
+    }
+}
+
+impl<T> LineMarks<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach `value` to `line`.
+    pub fn add(&mut self, line: usize, value: T) {
+        let at = self.marks.partition_point(|&(l, _)| l <= line);
+        self.marks.insert(at, (line, value));
+    }
+
+    /// The marks on `line`, in the order they were added.
+    pub fn get(&self, line: usize) -> impl Iterator<Item = &T> + '_ {
+        let start = self.marks.partition_point(|&(l, _)| l < line);
+        self.marks[start..]
+            .iter()
+            .take_while(move |&&(l, _)| l == line)
+            .map(|(_, value)| value)
+    }
+
+    /// Remove every mark on `line`.
+    pub fn clear_line(&mut self, line: usize) {
+        self.marks.retain(|&(l, _)| l != line);
+    }
+
+    /// All marks as `(line, value)`, in line order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> + '_ {
+        self.marks.iter().map(|(line, value)| (*line, value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.marks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.marks.is_empty()
+    }
+
+    /// Shift marks for `count` new lines inserted before line `at`.
+    pub fn lines_inserted(&mut self, at: usize, count: usize) {
+        for (line, _) in &mut self.marks {
+            if *line >= at {
+                *line += count;
+            }
+        }
+    }
+
+    /// Drop marks on lines `start..start + count` and shift the ones below up.
+    pub fn lines_deleted(&mut self, start: usize, count: usize) {
+        let end = start + count;
+        self.marks.retain(|&(line, _)| line < start || line >= end);
+        for (line, _) in &mut self.marks {
+            if *line >= end {
+                *line -= count;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_move_with_inserted_lines() {
+        let mut marks = LineMarks::new();
+        marks.add(4, "error");
+        marks.add(1, "breakpoint");
+        marks.add(4, "warning");
+        assert_eq!(marks.get(4).collect::<Vec<_>>(), vec![&"error", &"warning"]);
+
+        // Two lines inserted above line 4, none above line 1.
+        marks.lines_inserted(2, 2);
+        assert_eq!(marks.get(4).count(), 0);
+        assert_eq!(marks.get(6).collect::<Vec<_>>(), vec![&"error", &"warning"]);
+        assert_eq!(marks.get(1).collect::<Vec<_>>(), vec![&"breakpoint"]);
+
+        // Inserting at the mark's own line pushes it down too.
+        marks.lines_inserted(1, 1);
+        assert_eq!(
+            marks.iter().collect::<Vec<_>>(),
+            vec![(2, &"breakpoint"), (7, &"error"), (7, &"warning")]
+        );
+    }
+
+    #[test]
+    fn marks_on_deleted_lines_are_dropped() {
+        let mut marks = LineMarks::new();
+        marks.add(1, 'a');
+        marks.add(3, 'b');
+        marks.add(6, 'c');
+
+        marks.lines_deleted(2, 3);
+        assert_eq!(marks.iter().collect::<Vec<_>>(), vec![(1, &'a'), (3, &'c')]);
+
+        marks.clear_line(1);
+        assert_eq!(marks.len(), 1);
+    }
+}
@@ -0,0 +1,32 @@
+//! Helpers shared by tests that touch the filesystem.
+
+use std::path::{Path, PathBuf};
+
+/// A file path in the system temp dir, unique to this process, whose file (and
+/// swap file) are removed when it's dropped, even if the test panicked.
+///
+/// `TempFile::new("load.txt")` is `redox_load_<pid>.txt`. The file itself isn't
+/// created.
+pub(crate) struct TempFile(PathBuf);
+
+impl TempFile {
+    pub(crate) fn new(name: &str) -> Self {
+        let pid = std::process::id();
+        let name = match name.rsplit_once('.') {
+            Some((stem, ext)) => format!("redox_{stem}_{pid}.{ext}"),
+            None => format!("redox_{name}_{pid}"),
+        };
+        Self(std::env::temp_dir().join(name))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+        let _ = std::fs::remove_file(crate::io::swap_path(&self.0));
+    }
+}
@@ -13,6 +13,8 @@
 use core::cmp::{max, min};
 use core::fmt;
 
+use unicode_width::UnicodeWidthChar;
+
 /// A 0-based character index (Unicode scalar value index).
 ///
 /// In ropey, most cursor-safe indexing is done in **char indices** (not bytes).
@@ -226,7 +228,7 @@ pub fn char_to_line_col(
 
     while lo < hi {
         // bias upwards to avoid infinite loop
-        let mid = (lo + hi + 1) / 2;
+        let mid = (lo + hi).div_ceil(2);
         let mid_start = line_to_char(mid);
         if mid_start <= target {
             lo = mid;
@@ -377,3 +379,86 @@ pub fn clamp_cursor_to_line_editable(
 ) -> CharIdx {
     CharIdx(max(line_start.0, min(cursor.0, editable_end.0)))
 }
+
+/// Incremental 64-bit FNV-1a hasher.
+///
+/// Not cryptographic but good enough for change detection. Because FNV-1a is a
+/// plain byte-at-a-time fold, feeding the same bytes in different chunkings
+/// (eg. rope chunks vs. one `String`) produces the same hash.
+#[derive(Clone, Copy, Debug)]
+pub struct Fnv64(u64);
+
+impl Fnv64 {
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    #[inline]
+    pub const fn new() -> Self {
+        Self(Self::OFFSET)
+    }
+
+    #[inline]
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    #[inline]
+    pub const fn finish(self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for Fnv64 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Simple 64-bit FNV-1a hash for strings.
+#[inline]
+pub fn hash64(s: &str) -> u64 {
+    let mut h = Fnv64::new();
+    h.write(s.as_bytes());
+    h.finish()
+}
+
+/// Terminal cell width of `s` laid out from column 0.
+///
+/// Tabs advance to the next multiple of `tab_width` (0 is treated as 1), wide
+/// (eg. CJK) chars take two cells and combining marks and other zero-width chars
+/// take none. Newlines count as zero, so pass a single line for alignment.
+///
+/// Chars other than tabs get the widths the TUI gets from MinUI's `cell_width`.
+/// Tabs differ: the TUI draws every tab as a fixed 4 cells
+/// (`TabPolicy::Fixed(4)`), wherever it starts.
+pub fn str_display_width(s: &str, tab_width: usize) -> usize {
+    let tab_width = tab_width.max(1);
+    s.chars().fold(0, |col, ch| match ch {
+        '\t' => col + tab_width - col % tab_width,
+        ch => col + ch.width().unwrap_or(0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_width_of_wide_and_combining_chars() {
+        assert_eq!(str_display_width("", 4), 0);
+        assert_eq!(str_display_width("abc", 4), 3);
+        assert_eq!(str_display_width("日本語", 4), 6);
+        assert_eq!(str_display_width("e\u{301}", 4), 1);
+    }
+
+    #[test]
+    fn display_width_expands_tabs_to_stops() {
+        assert_eq!(str_display_width("\t", 4), 4);
+        assert_eq!(str_display_width("ab\tc", 4), 5);
+        assert_eq!(str_display_width("日\t", 4), 4);
+        assert_eq!(str_display_width("a\t", 0), 2);
+    }
+}
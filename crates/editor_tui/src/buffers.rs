@@ -0,0 +1,246 @@
+//! The list of open buffers (`:e`, `:bn`, `:bp`, `:b N`, `:bd`).
+//!
+//! Each [`OpenBuffer`] carries its saved content id, so every buffer knows
+//! whether it's dirty; the file it belongs to is the `TextBuffer`'s own path.
+//!
+//! The editor state owns the live cursor and scroll offsets of the buffer on
+//! screen; switching hands them to the buffer being left and gets back the
+//! [`ViewState`] the other buffer was left with.
+
+use std::path::Path;
+
+use editor_core::{Pos, TextBuffer};
+
+/// Where a buffer was being viewed when it was switched away from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ViewState {
+    pub cursor: Pos,
+    pub scroll_x: usize,
+    pub scroll_y: usize,
+}
+
+impl Default for ViewState {
+    fn default() -> Self {
+        Self {
+            cursor: Pos::zero(),
+            scroll_x: 0,
+            scroll_y: 0,
+        }
+    }
+}
+
+/// A buffer and its editing state.
+#[derive(Debug)]
+pub struct OpenBuffer {
+    /// The text, and (via `TextBuffer::path`) the file `:w` saves to.
+    pub buffer: TextBuffer,
+    /// `TextBuffer::content_id` as of the last load/save; anything else is unsaved.
+    pub saved_content_id: u64,
+    /// The view to restore when switching back (stale while the buffer is active).
+    pub view: ViewState,
+}
+
+impl OpenBuffer {
+    pub fn new(buffer: TextBuffer) -> Self {
+        Self {
+            saved_content_id: buffer.content_id(),
+            buffer,
+            view: ViewState::default(),
+        }
+    }
+
+    /// True if the buffer changed since it was loaded or last saved.
+    pub fn is_dirty(&self) -> bool {
+        self.buffer.content_id() != self.saved_content_id
+    }
+}
+
+/// Every open buffer, in the order they were opened, and which one is active.
+///
+/// Never empty: the last buffer can't be removed.
+#[derive(Debug)]
+pub struct BufferList {
+    buffers: Vec<OpenBuffer>,
+    active: usize,
+}
+
+impl BufferList {
+    pub fn new(first: OpenBuffer) -> Self {
+        Self {
+            buffers: vec![first],
+            active: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> &OpenBuffer {
+        &self.buffers[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut OpenBuffer {
+        &mut self.buffers[self.active]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &OpenBuffer> {
+        self.buffers.iter()
+    }
+
+    /// Index of the buffer open on `path`, if any.
+    pub fn find(&self, path: &Path) -> Option<usize> {
+        self.buffers
+            .iter()
+            .position(|b| b.buffer.path() == Some(path))
+    }
+
+    /// Append `buffer` (without switching to it) and return its index.
+    pub fn add(&mut self, buffer: OpenBuffer) -> usize {
+        self.buffers.push(buffer);
+        self.buffers.len() - 1
+    }
+
+    /// Remove the buffer at `index`. The active buffer stays active if it's
+    /// another one; if it's the one removed, the buffer before it (or the new
+    /// first one) becomes active.
+    ///
+    /// Returns `None` for an out-of-range index or the only buffer.
+    pub fn remove(&mut self, index: usize) -> Option<OpenBuffer> {
+        if index >= self.buffers.len() || self.buffers.len() == 1 {
+            return None;
+        }
+        let removed = self.buffers.remove(index);
+        if index < self.active || (index == self.active && index > 0) {
+            self.active -= 1;
+        }
+        Some(removed)
+    }
+
+    /// Make buffer `index` active, saving `view` as the view of the buffer being
+    /// left. Returns the view to restore, or `None` (and stays put) if `index`
+    /// is out of range.
+    pub fn switch_to(&mut self, index: usize, view: ViewState) -> Option<ViewState> {
+        if index >= self.buffers.len() {
+            return None;
+        }
+        self.buffers[self.active].view = view;
+        self.active = index;
+        Some(self.buffers[index].view)
+    }
+
+    /// Switch to the next buffer, wrapping after the last (`:bn`).
+    pub fn next(&mut self, view: ViewState) -> ViewState {
+        let index = (self.active + 1) % self.buffers.len();
+        self.switch_to(index, view).unwrap_or(view)
+    }
+
+    /// Switch to the previous buffer, wrapping before the first (`:bp`).
+    pub fn prev(&mut self, view: ViewState) -> ViewState {
+        let index = self.active.checked_sub(1).unwrap_or(self.buffers.len() - 1);
+        self.switch_to(index, view).unwrap_or(view)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(text: &str, path: &str) -> OpenBuffer {
+        let mut buffer = TextBuffer::from_str(text);
+        buffer.set_path(Some(path.into()));
+        OpenBuffer::new(buffer)
+    }
+
+    fn path(buffer: &OpenBuffer) -> &Path {
+        buffer.buffer.path().unwrap()
+    }
+
+    fn three_buffers() -> BufferList {
+        let mut list = BufferList::new(open("a", "a.txt"));
+        list.add(open("b", "b.txt"));
+        list.add(open("c", "c.txt"));
+        list
+    }
+
+    fn view(line: usize, scroll_y: usize) -> ViewState {
+        ViewState {
+            cursor: Pos::new(line, 0),
+            scroll_x: 0,
+            scroll_y,
+        }
+    }
+
+    #[test]
+    fn switching_preserves_each_buffers_view() {
+        let mut list = three_buffers();
+
+        // Leave a.txt at line 5, visit b.txt and move to line 9 there.
+        assert_eq!(list.switch_to(1, view(5, 2)), Some(ViewState::default()));
+        assert_eq!(path(list.active()), Path::new("b.txt"));
+        // Back to a.txt: its view comes back; b.txt keeps line 9.
+        assert_eq!(list.switch_to(0, view(9, 4)), Some(view(5, 2)));
+        assert_eq!(list.switch_to(1, view(5, 2)), Some(view(9, 4)));
+
+        assert_eq!(list.switch_to(7, view(0, 0)), None);
+        assert_eq!(list.active_index(), 1);
+    }
+
+    #[test]
+    fn next_and_prev_wrap_around() {
+        let mut list = three_buffers();
+        list.prev(ViewState::default());
+        assert_eq!(list.active_index(), 2);
+        list.next(ViewState::default());
+        assert_eq!(list.active_index(), 0);
+        list.next(ViewState::default());
+        assert_eq!(list.active_index(), 1);
+    }
+
+    #[test]
+    fn each_buffer_tracks_its_own_dirty_flag() {
+        let mut list = three_buffers();
+        list.active_mut().buffer.insert(Pos::zero(), "x");
+        list.switch_to(2, ViewState::default());
+        assert!(!list.active().is_dirty());
+        assert_eq!(list.iter().filter(|b| b.is_dirty()).count(), 1);
+        assert_eq!(list.find(Path::new("c.txt")), Some(2));
+    }
+
+    #[test]
+    fn restoring_a_snapshot_tracks_the_saved_content() {
+        let mut open = OpenBuffer::new(TextBuffer::from_str("a"));
+        let snap = open.buffer.snapshot();
+        open.buffer.insert(Pos::new(0, 1), "b");
+        open.saved_content_id = open.buffer.content_id();
+
+        // Back before the save, then a different edit: still unsaved.
+        open.buffer.restore(snap.clone());
+        assert!(open.is_dirty());
+        open.buffer.insert(Pos::new(0, 1), "c");
+        assert!(open.is_dirty());
+
+        // Restoring what was loaded, with the load as the last save, is clean.
+        open.saved_content_id = snap.content_id();
+        open.buffer.restore(snap);
+        assert!(!open.is_dirty());
+    }
+
+    #[test]
+    fn remove_keeps_the_active_buffer() {
+        let mut list = three_buffers();
+        list.switch_to(2, ViewState::default());
+        assert_eq!(path(&list.remove(0).unwrap()), Path::new("a.txt"));
+        assert_eq!(path(list.active()), Path::new("c.txt"));
+
+        // Removing the active buffer falls back to the one before it.
+        list.remove(1);
+        assert_eq!(path(list.active()), Path::new("b.txt"));
+        assert!(list.remove(0).is_none());
+        assert_eq!(list.len(), 1);
+    }
+}
@@ -0,0 +1,257 @@
+//! The `:` command line.
+//!
+//! [`CommandLine`] holds what's been typed after `:`; [`parse_ex_command`] turns it
+//! into an [`ExCommand`]. Running the command is up to the editor state, since it
+//! touches files, the buffer and the cursor.
+
+use std::fmt;
+use std::path::PathBuf;
+
+/// A parsed ex command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExCommand {
+    /// `:w [path]` / `:w!`: save, optionally to another path.
+    Write(Option<PathBuf>),
+    /// `:q` / `:q!`: quit; `force` discards unsaved changes.
+    Quit { force: bool },
+    /// `:wq` / `:x`: save and quit.
+    WriteQuit,
+    /// `:e path`: open another file (or switch to it if it's already open).
+    Edit(PathBuf),
+    /// `:bn`: switch to the next buffer.
+    BufferNext,
+    /// `:bp` / `:bN`: switch to the previous buffer.
+    BufferPrev,
+    /// `:b 2`: switch to a 1-based buffer number.
+    Buffer(usize),
+    /// `:bd` / `:bd!`: close the active buffer; `force` discards unsaved changes.
+    BufferDelete { force: bool },
+    /// `:sp`: split the focused pane into two stacked panes.
+    Split,
+    /// `:vs`: split the focused pane into two side-by-side panes.
+    VerticalSplit,
+    /// `:wincmd w`: move focus to the next pane.
+    WindowNext,
+    /// `:42`: jump to a 1-based line.
+    Goto(usize),
+}
+
+/// Why a command line couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExError {
+    /// Nothing but whitespace.
+    Empty,
+    /// Not a command we know.
+    Unknown(String),
+    /// The command needs an argument (e.g. `:e` without a path).
+    MissingArgument(&'static str),
+    /// The command doesn't take an argument but got one.
+    TrailingArgument(String),
+    /// The argument isn't what the command expects (e.g. `:b x`).
+    InvalidArgument(String),
+}
+
+impl fmt::Display for ExError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExError::Empty => write!(f, "empty command"),
+            ExError::Unknown(name) => write!(f, "not an editor command: {name}"),
+            ExError::MissingArgument(what) => write!(f, "argument required: {what}"),
+            ExError::TrailingArgument(arg) => write!(f, "trailing characters: {arg}"),
+            ExError::InvalidArgument(arg) => write!(f, "invalid argument: {arg}"),
+        }
+    }
+}
+
+impl std::error::Error for ExError {}
+
+/// Parse a command line such as `:w`, ` :q! `, `:e notes.txt` or `:42`.
+///
+/// The leading `:` is optional, and whitespace around the command and its
+/// argument is ignored.
+pub fn parse_ex_command(input: &str) -> Result<ExCommand, ExError> {
+    let input = input.trim();
+    let input = input.strip_prefix(':').unwrap_or(input).trim();
+    if input.is_empty() {
+        return Err(ExError::Empty);
+    }
+
+    let (name, arg) = match input.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, Some(arg.trim()).filter(|a| !a.is_empty())),
+        None => (input, None),
+    };
+
+    if name.bytes().all(|b| b.is_ascii_digit()) {
+        let line = name.parse().map_err(|_| ExError::Unknown(name.into()))?;
+        return no_argument(arg, ExCommand::Goto(line));
+    }
+
+    match name {
+        "w" | "w!" | "write" | "write!" => Ok(ExCommand::Write(arg.map(PathBuf::from))),
+        "q" | "quit" => no_argument(arg, ExCommand::Quit { force: false }),
+        "q!" | "quit!" => no_argument(arg, ExCommand::Quit { force: true }),
+        "wq" | "wq!" | "x" | "x!" => no_argument(arg, ExCommand::WriteQuit),
+        "e" | "edit" => arg
+            .map(|path| ExCommand::Edit(PathBuf::from(path)))
+            .ok_or(ExError::MissingArgument("file name")),
+        "bn" | "bnext" => no_argument(arg, ExCommand::BufferNext),
+        "bp" | "bN" | "bprevious" | "bNext" => no_argument(arg, ExCommand::BufferPrev),
+        "bd" | "bdelete" => no_argument(arg, ExCommand::BufferDelete { force: false }),
+        "bd!" | "bdelete!" => no_argument(arg, ExCommand::BufferDelete { force: true }),
+        "sp" | "split" => no_argument(arg, ExCommand::Split),
+        "vs" | "vsplit" => no_argument(arg, ExCommand::VerticalSplit),
+        "winc" | "wincmd" => match arg {
+            Some("w") => Ok(ExCommand::WindowNext),
+            Some(arg) => Err(ExError::InvalidArgument(arg.into())),
+            None => Err(ExError::MissingArgument("window command")),
+        },
+        "b" | "buffer" => {
+            let arg = arg.ok_or(ExError::MissingArgument("buffer number"))?;
+            arg.parse()
+                .map(ExCommand::Buffer)
+                .map_err(|_| ExError::InvalidArgument(arg.into()))
+        }
+        _ => Err(ExError::Unknown(name.into())),
+    }
+}
+
+fn no_argument(arg: Option<&str>, cmd: ExCommand) -> Result<ExCommand, ExError> {
+    match arg {
+        Some(arg) => Err(ExError::TrailingArgument(arg.into())),
+        None => Ok(cmd),
+    }
+}
+
+/// Text typed on the command line (without the leading `:`).
+#[derive(Debug, Clone, Default)]
+pub struct CommandLine {
+    input: String,
+}
+
+impl CommandLine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.input
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.input.push(c);
+    }
+
+    /// Delete the last char. Returns `false` if the line was already empty
+    /// (Vim leaves the command line in that case).
+    pub fn backspace(&mut self) -> bool {
+        self.input.pop().is_some()
+    }
+
+    /// Take the typed text, leaving the line empty.
+    pub fn take(&mut self) -> String {
+        std::mem::take(&mut self.input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_variants() {
+        assert_eq!(parse_ex_command(":w"), Ok(ExCommand::Write(None)));
+        assert_eq!(parse_ex_command(":w!"), Ok(ExCommand::Write(None)));
+        assert_eq!(
+            parse_ex_command("  :w   out.txt  "),
+            Ok(ExCommand::Write(Some(PathBuf::from("out.txt"))))
+        );
+    }
+
+    #[test]
+    fn quit_variants() {
+        assert_eq!(parse_ex_command(":q"), Ok(ExCommand::Quit { force: false }));
+        assert_eq!(parse_ex_command(":q!"), Ok(ExCommand::Quit { force: true }));
+        assert_eq!(parse_ex_command(" :wq "), Ok(ExCommand::WriteQuit));
+        assert_eq!(parse_ex_command(":x"), Ok(ExCommand::WriteQuit));
+    }
+
+    #[test]
+    fn edit_and_goto() {
+        assert_eq!(
+            parse_ex_command(":e src/main.rs"),
+            Ok(ExCommand::Edit(PathBuf::from("src/main.rs")))
+        );
+        assert_eq!(parse_ex_command(":42"), Ok(ExCommand::Goto(42)));
+        assert_eq!(parse_ex_command("7"), Ok(ExCommand::Goto(7)));
+    }
+
+    #[test]
+    fn buffer_commands() {
+        assert_eq!(parse_ex_command(":bn"), Ok(ExCommand::BufferNext));
+        assert_eq!(parse_ex_command(":bprevious"), Ok(ExCommand::BufferPrev));
+        assert_eq!(parse_ex_command(":b 3"), Ok(ExCommand::Buffer(3)));
+        assert_eq!(
+            parse_ex_command(":bd"),
+            Ok(ExCommand::BufferDelete { force: false })
+        );
+        assert_eq!(
+            parse_ex_command(":bdelete!"),
+            Ok(ExCommand::BufferDelete { force: true })
+        );
+        assert_eq!(
+            parse_ex_command(":b x"),
+            Err(ExError::InvalidArgument("x".into()))
+        );
+        assert_eq!(
+            parse_ex_command(":buffer"),
+            Err(ExError::MissingArgument("buffer number"))
+        );
+    }
+
+    #[test]
+    fn window_commands() {
+        assert_eq!(parse_ex_command(":sp"), Ok(ExCommand::Split));
+        assert_eq!(parse_ex_command(":vsplit"), Ok(ExCommand::VerticalSplit));
+        assert_eq!(parse_ex_command(":wincmd w"), Ok(ExCommand::WindowNext));
+        assert_eq!(
+            parse_ex_command(":winc j"),
+            Err(ExError::InvalidArgument("j".into()))
+        );
+        assert_eq!(
+            parse_ex_command(":wincmd"),
+            Err(ExError::MissingArgument("window command"))
+        );
+    }
+
+    #[test]
+    fn malformed_input_is_an_error() {
+        assert_eq!(parse_ex_command(" : "), Err(ExError::Empty));
+        assert_eq!(
+            parse_ex_command(":frob"),
+            Err(ExError::Unknown("frob".into()))
+        );
+        assert_eq!(
+            parse_ex_command(":e"),
+            Err(ExError::MissingArgument("file name"))
+        );
+        assert_eq!(
+            parse_ex_command(":q now"),
+            Err(ExError::TrailingArgument("now".into()))
+        );
+        assert_eq!(
+            parse_ex_command(":99999999999999999999999"),
+            Err(ExError::Unknown("99999999999999999999999".into()))
+        );
+    }
+
+    #[test]
+    fn command_line_editing() {
+        let mut line = CommandLine::new();
+        line.push('w');
+        line.push('q');
+        assert!(line.backspace());
+        assert_eq!(line.text(), "w");
+        assert_eq!(line.take(), "w");
+        assert!(!line.backspace());
+    }
+}
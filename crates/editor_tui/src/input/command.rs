@@ -0,0 +1,350 @@
+//! Vim-style command parsing: `[count][operator][count]motion`.
+//!
+//! Keys are fed one at a time. The parser accumulates a count and a pending
+//! operator until a motion resolves the command (e.g. `2dw`, `d3w`, `dd`, `5j`).
+//! It knows nothing about the buffer; turning a [`Command`] into an edit is the
+//! caller's job.
+
+use minui::prelude::KeyKind;
+
+/// An operator waiting for a motion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// `d`
+    Delete,
+    /// `c`
+    Change,
+    /// `y`
+    Yank,
+}
+
+impl Operator {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'd' => Some(Operator::Delete),
+            'c' => Some(Operator::Change),
+            'y' => Some(Operator::Yank),
+            _ => None,
+        }
+    }
+}
+
+/// Where a command moves to (or, with an operator, what it covers).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    /// `h`
+    Left,
+    /// `l`
+    Right,
+    /// `k`
+    Up,
+    /// `j`
+    Down,
+    /// `w`: start of the next word.
+    WordForward,
+    /// `b`: start of the previous word.
+    WordBackward,
+    /// `e`: end of the current/next word.
+    WordEnd,
+    /// `0`
+    LineStart,
+    /// `$`
+    LineEnd,
+    /// A doubled operator (`dd`, `cc`, `yy`): `count` whole lines from the cursor.
+    CurrentLine,
+    /// `f`/`F`/`t`/`T` followed by the char to look for.
+    FindChar(CharSearch),
+    /// `;` repeats the last `FindChar`; `,` repeats it in the other direction.
+    RepeatFindChar { reverse: bool },
+}
+
+impl Motion {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'h' => Some(Motion::Left),
+            'l' => Some(Motion::Right),
+            'k' => Some(Motion::Up),
+            'j' => Some(Motion::Down),
+            'w' => Some(Motion::WordForward),
+            'b' => Some(Motion::WordBackward),
+            'e' => Some(Motion::WordEnd),
+            '0' => Some(Motion::LineStart),
+            '$' => Some(Motion::LineEnd),
+            ';' => Some(Motion::RepeatFindChar { reverse: false }),
+            ',' => Some(Motion::RepeatFindChar { reverse: true }),
+            _ => None,
+        }
+    }
+}
+
+/// A find-char-on-line search (`fx`, `Fx`, `tx`, `Tx`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CharSearch {
+    pub ch: char,
+    /// `f`/`t` search right, `F`/`T` search left.
+    pub forward: bool,
+    /// `t`/`T` stop next to the match instead of on it.
+    pub till: bool,
+}
+
+impl CharSearch {
+    /// `(forward, till)` for a search key, waiting on the char to find.
+    fn prefix(c: char) -> Option<(bool, bool)> {
+        match c {
+            'f' => Some((true, false)),
+            'F' => Some((false, false)),
+            't' => Some((true, true)),
+            'T' => Some((false, true)),
+            _ => None,
+        }
+    }
+
+    /// The same search in the other direction (`,`).
+    pub fn reversed(self) -> Self {
+        Self {
+            forward: !self.forward,
+            ..self
+        }
+    }
+}
+
+/// A fully parsed command.
+///
+/// `count` is always at least 1; counts given before and after the operator
+/// multiply (`2d3w` deletes 6 words), as in Vim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Command {
+    pub count: usize,
+    pub operator: Option<Operator>,
+    pub motion: Motion,
+}
+
+/// Result of feeding one key to a [`CommandParser`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseStep {
+    /// More keys are needed.
+    Pending,
+    /// A command was completed; the parser has been reset.
+    Complete(Command),
+    /// The key doesn't continue a command (or was Escape); the parser has been reset.
+    Cancelled,
+}
+
+/// Accumulates `[count][operator][count]motion` across key presses.
+#[derive(Debug, Clone, Default)]
+pub struct CommandParser {
+    /// Count typed before the operator.
+    count: Option<usize>,
+    operator: Option<Operator>,
+    /// Count typed after the operator.
+    motion_count: Option<usize>,
+    /// `(forward, till)` of a find-char key still waiting for its char.
+    char_search: Option<(bool, bool)>,
+}
+
+impl CommandParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// True if a count or operator has been typed but not yet resolved.
+    pub fn is_pending(&self) -> bool {
+        self.count.is_some() || self.operator.is_some() || self.char_search.is_some()
+    }
+
+    /// Drop any partially typed command.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Feed one key.
+    pub fn feed(&mut self, key: KeyKind) -> ParseStep {
+        let KeyKind::Char(c) = key else {
+            self.reset();
+            return ParseStep::Cancelled;
+        };
+
+        // Whatever follows `f`/`F`/`t`/`T` is the char to find, digits included.
+        if let Some((forward, till)) = self.char_search {
+            return self.complete(Motion::FindChar(CharSearch {
+                ch: c,
+                forward,
+                till,
+            }));
+        }
+        if let Some(prefix) = CharSearch::prefix(c) {
+            self.char_search = Some(prefix);
+            return ParseStep::Pending;
+        }
+
+        // Digits extend the active count; a leading `0` is the line-start motion.
+        if let Some(digit) = c.to_digit(10) {
+            let slot = match self.operator {
+                None => &mut self.count,
+                Some(_) => &mut self.motion_count,
+            };
+            if digit != 0 || slot.is_some() {
+                let n = slot.unwrap_or(0);
+                *slot = Some(n.saturating_mul(10).saturating_add(digit as usize));
+                return ParseStep::Pending;
+            }
+        }
+
+        if let Some(op) = Operator::from_char(c) {
+            return match self.operator {
+                None => {
+                    self.operator = Some(op);
+                    ParseStep::Pending
+                }
+                Some(pending) if pending == op => self.complete(Motion::CurrentLine),
+                Some(_) => {
+                    self.reset();
+                    ParseStep::Cancelled
+                }
+            };
+        }
+
+        match Motion::from_char(c) {
+            Some(motion) => self.complete(motion),
+            None => {
+                self.reset();
+                ParseStep::Cancelled
+            }
+        }
+    }
+
+    fn complete(&mut self, motion: Motion) -> ParseStep {
+        let count = self
+            .count
+            .unwrap_or(1)
+            .saturating_mul(self.motion_count.unwrap_or(1))
+            .max(1);
+        let command = Command {
+            count,
+            operator: self.operator,
+            motion,
+        };
+        self.reset();
+        ParseStep::Complete(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed every char of `keys`, returning the last step.
+    fn feed_all(parser: &mut CommandParser, keys: &str) -> ParseStep {
+        let mut step = ParseStep::Pending;
+        for c in keys.chars() {
+            step = parser.feed(KeyKind::Char(c));
+        }
+        step
+    }
+
+    fn parse(keys: &str) -> ParseStep {
+        feed_all(&mut CommandParser::new(), keys)
+    }
+
+    fn cmd(count: usize, operator: Option<Operator>, motion: Motion) -> ParseStep {
+        ParseStep::Complete(Command {
+            count,
+            operator,
+            motion,
+        })
+    }
+
+    #[test]
+    fn count_operator_motion() {
+        assert_eq!(
+            parse("2dw"),
+            cmd(2, Some(Operator::Delete), Motion::WordForward)
+        );
+        assert_eq!(
+            parse("c3e"),
+            cmd(3, Some(Operator::Change), Motion::WordEnd)
+        );
+        assert_eq!(
+            parse("2y3b"),
+            cmd(6, Some(Operator::Yank), Motion::WordBackward)
+        );
+    }
+
+    #[test]
+    fn doubled_operator_is_linewise() {
+        assert_eq!(
+            parse("dd"),
+            cmd(1, Some(Operator::Delete), Motion::CurrentLine)
+        );
+        assert_eq!(
+            parse("3yy"),
+            cmd(3, Some(Operator::Yank), Motion::CurrentLine)
+        );
+    }
+
+    #[test]
+    fn stays_pending_until_a_motion() {
+        let mut parser = CommandParser::new();
+        assert_eq!(feed_all(&mut parser, "1"), ParseStep::Pending);
+        assert_eq!(feed_all(&mut parser, "2"), ParseStep::Pending);
+        assert_eq!(feed_all(&mut parser, "d"), ParseStep::Pending);
+        assert!(parser.is_pending());
+        assert_eq!(
+            feed_all(&mut parser, "$"),
+            cmd(12, Some(Operator::Delete), Motion::LineEnd)
+        );
+        assert!(!parser.is_pending());
+    }
+
+    #[test]
+    fn zero_is_a_motion_unless_counting() {
+        assert_eq!(parse("0"), cmd(1, None, Motion::LineStart));
+        assert_eq!(
+            parse("d0"),
+            cmd(1, Some(Operator::Delete), Motion::LineStart)
+        );
+        assert_eq!(parse("10j"), cmd(10, None, Motion::Down));
+    }
+
+    #[test]
+    fn mismatched_operator_or_escape_cancels() {
+        assert_eq!(parse("dc"), ParseStep::Cancelled);
+        assert_eq!(parse("dz"), ParseStep::Cancelled);
+
+        let mut parser = CommandParser::new();
+        feed_all(&mut parser, "2d");
+        assert_eq!(parser.feed(KeyKind::Escape), ParseStep::Cancelled);
+        assert!(!parser.is_pending());
+    }
+
+    #[test]
+    fn find_char_takes_the_next_key() {
+        let find = |ch, forward, till| Motion::FindChar(CharSearch { ch, forward, till });
+        assert_eq!(parse("fx"), cmd(1, None, find('x', true, false)));
+        assert_eq!(parse("2T,"), cmd(2, None, find(',', false, true)));
+        assert_eq!(
+            parse("dt3"),
+            cmd(1, Some(Operator::Delete), find('3', true, true))
+        );
+
+        let mut parser = CommandParser::new();
+        assert_eq!(feed_all(&mut parser, "F"), ParseStep::Pending);
+        assert!(parser.is_pending());
+        assert_eq!(parser.feed(KeyKind::Escape), ParseStep::Cancelled);
+    }
+
+    #[test]
+    fn semicolon_and_comma_repeat_find_char() {
+        let repeat = |reverse| Motion::RepeatFindChar { reverse };
+        assert_eq!(parse(";"), cmd(1, None, repeat(false)));
+        assert_eq!(parse("3,"), cmd(3, None, repeat(true)));
+        assert_eq!(parse("d;"), cmd(1, Some(Operator::Delete), repeat(false)));
+        // After a find key they're just the char to look for.
+        let find = Motion::FindChar(CharSearch {
+            ch: ';',
+            forward: true,
+            till: false,
+        });
+        assert_eq!(parse("f;"), cmd(1, None, find));
+    }
+}
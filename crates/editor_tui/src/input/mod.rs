@@ -4,11 +4,55 @@
 //! system, rather than being pure editor logic. Any more complex logic required
 //! for future motions will be implemented in the core crate.
 //!
+//! Keys are mapped through one table per [`EditorMode`], so the same key can mean
+//! different things depending on the mode (`i` enters Insert in Normal mode, but
+//! inserts an `i` in Insert mode).
+//!
+//! Counts, operators and the motions they take are forwarded as
+//! [`InputAction::CommandKey`] to a [`CommandParser`]; while it has a command
+//! pending, [`map_pending_event`] sends it every key instead.
+//!
+//! Mouse clicks need the layout of the last frame, so they go through
+//! [`map_mouse_event`] with the snapshot that was drawn.
+//!
 //! Over time, this module will grow to a comprehensive list implementing
 //! all basic vim motions.
 
+use editor_core::Pos;
 use minui::prelude::*;
 
+use crate::ui::RenderSnapshot;
+
+mod command;
+
+pub use command::{CharSearch, Command, CommandParser, Motion, Operator, ParseStep};
+
+/// The modal editing state, Vim style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EditorMode {
+    /// Keys are commands and motions.
+    #[default]
+    Normal,
+    /// Keys insert text.
+    Insert,
+    /// Motions extend a selection anchored where Visual mode was entered.
+    Visual,
+    /// Typing an ex command after `:`.
+    CommandLine,
+}
+
+impl EditorMode {
+    /// Name shown in the status line.
+    pub fn label(self) -> &'static str {
+        match self {
+            EditorMode::Normal => "NORMAL",
+            EditorMode::Insert => "INSERT",
+            EditorMode::Visual => "VISUAL",
+            EditorMode::CommandLine => "COMMAND",
+        }
+    }
+}
+
 /// High-level input intents the TUI understands.
 ///
 /// This enum will stay small and stable, and variants will be added as needed
@@ -20,47 +64,387 @@ pub enum InputAction {
     Quit,
 
     /// Scroll the viewport by a delta in cells (x) and visual rows (y).
-    ScrollBy { dx: i32, dy: i32 },
+    ScrollBy {
+        dx: i32,
+        dy: i32,
+    },
+
+    /// Switch to Normal mode (dropping any selection).
+    EnterNormal,
+    /// Switch to Insert mode.
+    EnterInsert,
+    /// Switch to Visual mode, anchoring a selection at the cursor.
+    EnterVisual,
+    /// Open the `:` command line.
+    EnterCommandLine,
+
+    /// Type a char on the command line.
+    CommandLineChar(char),
+    /// Delete the last command-line char (leaving the command line if it's empty).
+    CommandLineBackspace,
+    /// Run the typed command line.
+    SubmitCommandLine,
+
+    /// Move the cursor one char left/right or one line up/down.
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+
+    /// Insert a char at the cursor.
+    InsertChar(char),
+    /// Split the line at the cursor.
+    InsertNewline,
+    /// Delete the char before the cursor (Backspace).
+    DeleteCharBackward,
+    /// Delete the char under the cursor (`x` / Delete).
+    DeleteCharForward,
+
+    /// Put the cursor at a document position (e.g. a mouse click).
+    SetCursor(Pos),
+
+    /// Repeat the last change (`.`).
+    RepeatChange,
+    /// Put the register's text after the cursor (`p`) or before it (`P`).
+    Paste {
+        before: bool,
+    },
+
+    /// A key that starts or continues a `[count][operator]motion` command.
+    CommandKey(KeyKind),
+
+    /// The terminal was resized; the next frame re-lays out the view.
+    Resize,
 
     /// No action.
     None,
 }
 
-/// Map a MinUI [`Event`] to a TUI [`InputAction`].
+/// Map a MinUI [`Event`] to a TUI [`InputAction`] for the current `mode`.
 ///
 /// Notes:
-/// - For now this only handles `Event::KeyWithModifiers` plus legacy `Event::Character('q')`.
-/// - Vim-like motions are intentionally minimal (hjkl + arrow keys).
-/// - Ignoring modifiers for now; later this can grow into a real keymap.
-pub fn map_event(event: &Event) -> InputAction {
-    match event {
+/// - For now this only handles `Event::KeyWithModifiers` plus legacy `Event::Character`,
+///   and `Event::Resize` (in any mode).
+/// - Modifiers are only looked at for Ctrl scrolling in Normal mode; later this can
+///   grow into a real keymap.
+pub fn map_event(event: &Event, mode: EditorMode) -> InputAction {
+    let key = match event {
         // Prefer modifier-aware key model
-        Event::KeyWithModifiers(k) => map_key(k.key),
+        Event::KeyWithModifiers(k) => *k,
 
         // Legacy convenience variant
-        Event::Character('q') => InputAction::Quit,
+        Event::Character(c) => KeyWithModifiers {
+            key: KeyKind::Char(*c),
+            mods: KeyModifiers::default(),
+        },
+
+        Event::Resize { .. } => return InputAction::Resize,
+        _ => return InputAction::None,
+    };
+
+    match mode {
+        EditorMode::Normal => map_normal(key),
+        EditorMode::Insert => map_insert(key),
+        EditorMode::Visual => map_visual(key),
+        EditorMode::CommandLine => map_command_line(key),
+    }
+}
 
+/// Map a left click to a [`InputAction::SetCursor`] via the snapshot on screen.
+///
+/// - `origin` is the screen `(row, column)` where the snapshot's text starts: its
+///   first row, and the column past the gutter. Clicks on the gutter land on the
+///   first column.
+/// - Clicks past the end of a row land at the end of that row's text.
+/// - Clicks below the last drawn row land on the last row (the end of the buffer,
+///   if it's in view).
+pub fn map_mouse_event(
+    event: &Event,
+    snapshot: &RenderSnapshot,
+    origin: (usize, usize),
+) -> InputAction {
+    let Event::MouseClick {
+        x,
+        y,
+        button: MouseButton::Left,
+    } = *event
+    else {
+        return InputAction::None;
+    };
+    let Some(last_row) = snapshot.rows.len().checked_sub(1) else {
+        return InputAction::None;
+    };
+
+    let (top, text_x) = origin;
+    let row = (y as usize).saturating_sub(top).min(last_row);
+    let col = (x as usize).saturating_sub(text_x);
+    snapshot
+        .screen_to_pos(row, col)
+        .map_or(InputAction::None, InputAction::SetCursor)
+}
+
+/// Map an event while a command is pending: every key goes to the parser.
+pub fn map_pending_event(event: &Event) -> InputAction {
+    match event {
+        Event::KeyWithModifiers(k) => InputAction::CommandKey(k.key),
+        Event::Character(c) => InputAction::CommandKey(KeyKind::Char(*c)),
+        Event::Resize { .. } => InputAction::Resize,
         _ => InputAction::None,
     }
 }
 
-fn map_key(key: KeyKind) -> InputAction {
+/// Chars that start a command in Normal mode: counts, operators and the motions
+/// that don't have a direct [`InputAction`].
+fn starts_command(c: char, operators: bool) -> bool {
+    matches!(
+        c,
+        '0'..='9' | 'w' | 'b' | 'e' | '$' | 'f' | 'F' | 't' | 'T' | ';' | ','
+    ) || (operators && matches!(c, 'd' | 'c' | 'y'))
+}
+
+/// Cursor motions shared by Normal and Visual mode.
+fn map_motion(key: KeyKind) -> InputAction {
     match key {
-        // Quit
+        KeyKind::Left | KeyKind::Char('h') => InputAction::MoveLeft,
+        KeyKind::Right | KeyKind::Char('l') => InputAction::MoveRight,
+        KeyKind::Up | KeyKind::Char('k') => InputAction::MoveUp,
+        KeyKind::Down | KeyKind::Char('j') => InputAction::MoveDown,
+        _ => InputAction::None,
+    }
+}
+
+fn map_normal(k: KeyWithModifiers) -> InputAction {
+    if k.mods.ctrl {
+        return match k.key {
+            // Scroll the view by one row, leaving the cursor where it is.
+            KeyKind::Char('e') => InputAction::ScrollBy { dx: 0, dy: 1 },
+            KeyKind::Char('y') => InputAction::ScrollBy { dx: 0, dy: -1 },
+            _ => InputAction::None,
+        };
+    }
+
+    match k.key {
         KeyKind::Char('q') => InputAction::Quit,
+        KeyKind::Char('i') => InputAction::EnterInsert,
+        KeyKind::Char('v') => InputAction::EnterVisual,
+        KeyKind::Char(':') => InputAction::EnterCommandLine,
+        KeyKind::Char('x') | KeyKind::Delete => InputAction::DeleteCharForward,
+        KeyKind::Char('.') => InputAction::RepeatChange,
+        KeyKind::Char('p') => InputAction::Paste { before: false },
+        KeyKind::Char('P') => InputAction::Paste { before: true },
+        KeyKind::Char(c) if starts_command(c, true) => InputAction::CommandKey(k.key),
+        key => map_motion(key),
+    }
+}
+
+fn map_insert(k: KeyWithModifiers) -> InputAction {
+    if k.mods.ctrl || k.mods.alt {
+        return InputAction::None;
+    }
 
-        // Arrow keys => scroll by one
-        KeyKind::Up => InputAction::ScrollBy { dx: 0, dy: -1 },
-        KeyKind::Down => InputAction::ScrollBy { dx: 0, dy: 1 },
-        KeyKind::Left => InputAction::ScrollBy { dx: -1, dy: 0 },
-        KeyKind::Right => InputAction::ScrollBy { dx: 1, dy: 0 },
+    match k.key {
+        KeyKind::Escape => InputAction::EnterNormal,
+        KeyKind::Char(c) => InputAction::InsertChar(c),
+        KeyKind::Tab => InputAction::InsertChar('\t'),
+        KeyKind::Enter => InputAction::InsertNewline,
+        KeyKind::Backspace => InputAction::DeleteCharBackward,
+        KeyKind::Delete => InputAction::DeleteCharForward,
+        KeyKind::Left => InputAction::MoveLeft,
+        KeyKind::Right => InputAction::MoveRight,
+        KeyKind::Up => InputAction::MoveUp,
+        KeyKind::Down => InputAction::MoveDown,
+        _ => InputAction::None,
+    }
+}
 
-        // Vim-ish => scroll by one
-        KeyKind::Char('k') => InputAction::ScrollBy { dx: 0, dy: -1 },
-        KeyKind::Char('j') => InputAction::ScrollBy { dx: 0, dy: 1 },
-        KeyKind::Char('h') => InputAction::ScrollBy { dx: -1, dy: 0 },
-        KeyKind::Char('l') => InputAction::ScrollBy { dx: 1, dy: 0 },
+fn map_visual(k: KeyWithModifiers) -> InputAction {
+    match k.key {
+        KeyKind::Escape | KeyKind::Char('v') => InputAction::EnterNormal,
+        // No operators on selections yet, just counts and motions.
+        KeyKind::Char(c) if starts_command(c, false) => InputAction::CommandKey(k.key),
+        key => map_motion(key),
+    }
+}
 
+fn map_command_line(k: KeyWithModifiers) -> InputAction {
+    match k.key {
+        KeyKind::Escape => InputAction::EnterNormal,
+        KeyKind::Enter => InputAction::SubmitCommandLine,
+        KeyKind::Backspace => InputAction::CommandLineBackspace,
+        KeyKind::Char(c) => InputAction::CommandLineChar(c),
         _ => InputAction::None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: KeyKind) -> Event {
+        Event::KeyWithModifiers(KeyWithModifiers {
+            key,
+            mods: KeyModifiers::default(),
+        })
+    }
+
+    #[test]
+    fn same_key_differs_across_modes() {
+        let i = key(KeyKind::Char('i'));
+        assert_eq!(map_event(&i, EditorMode::Normal), InputAction::EnterInsert);
+        assert_eq!(
+            map_event(&i, EditorMode::Insert),
+            InputAction::InsertChar('i')
+        );
+        assert_eq!(map_event(&i, EditorMode::Visual), InputAction::None);
+
+        let h = key(KeyKind::Char('h'));
+        assert_eq!(map_event(&h, EditorMode::Normal), InputAction::MoveLeft);
+        assert_eq!(
+            map_event(&h, EditorMode::Insert),
+            InputAction::InsertChar('h')
+        );
+        assert_eq!(map_event(&h, EditorMode::Visual), InputAction::MoveLeft);
+
+        let q = key(KeyKind::Char('q'));
+        assert_eq!(map_event(&q, EditorMode::Normal), InputAction::Quit);
+        assert_eq!(
+            map_event(&q, EditorMode::Insert),
+            InputAction::InsertChar('q')
+        );
+    }
+
+    #[test]
+    fn escape_returns_to_normal() {
+        let esc = key(KeyKind::Escape);
+        assert_eq!(
+            map_event(&esc, EditorMode::Insert),
+            InputAction::EnterNormal
+        );
+        assert_eq!(
+            map_event(&esc, EditorMode::Visual),
+            InputAction::EnterNormal
+        );
+        assert_eq!(map_event(&esc, EditorMode::Normal), InputAction::None);
+    }
+
+    #[test]
+    fn legacy_character_events_use_the_mode_table() {
+        let x = Event::Character('x');
+        assert_eq!(
+            map_event(&x, EditorMode::Normal),
+            InputAction::DeleteCharForward
+        );
+        assert_eq!(
+            map_event(&x, EditorMode::Insert),
+            InputAction::InsertChar('x')
+        );
+    }
+
+    #[test]
+    fn command_keys_go_to_the_parser() {
+        let d = key(KeyKind::Char('d'));
+        assert_eq!(
+            map_event(&d, EditorMode::Normal),
+            InputAction::CommandKey(KeyKind::Char('d'))
+        );
+        assert_eq!(map_event(&d, EditorMode::Visual), InputAction::None);
+        assert_eq!(
+            map_event(&d, EditorMode::Insert),
+            InputAction::InsertChar('d')
+        );
+
+        // Once pending, even mode keys belong to the command.
+        assert_eq!(
+            map_pending_event(&key(KeyKind::Char('i'))),
+            InputAction::CommandKey(KeyKind::Char('i'))
+        );
+    }
+
+    fn click(x: u16, y: u16) -> Event {
+        Event::MouseClick {
+            x,
+            y,
+            button: MouseButton::Left,
+        }
+    }
+
+    fn two_line_snapshot() -> RenderSnapshot {
+        RenderSnapshot::new(0, vec!["hello".into(), "hi".into()])
+    }
+
+    #[test]
+    fn click_on_a_row_sets_the_cursor() {
+        let snap = two_line_snapshot();
+        // Two gutter cells before the text.
+        assert_eq!(
+            map_mouse_event(&click(4, 0), &snap, (0, 2)),
+            InputAction::SetCursor(Pos::new(0, 2))
+        );
+        assert_eq!(
+            map_mouse_event(&click(0, 1), &snap, (0, 2)),
+            InputAction::SetCursor(Pos::new(1, 0))
+        );
+        // A pane drawn three rows down, with text starting at column 12.
+        assert_eq!(
+            map_mouse_event(&click(14, 4), &snap, (3, 12)),
+            InputAction::SetCursor(Pos::new(1, 2))
+        );
+    }
+
+    #[test]
+    fn click_past_line_end_clamps_to_it() {
+        assert_eq!(
+            map_mouse_event(&click(30, 1), &two_line_snapshot(), (0, 0)),
+            InputAction::SetCursor(Pos::new(1, 2))
+        );
+    }
+
+    #[test]
+    fn click_below_the_buffer_goes_to_the_last_line() {
+        let snap = two_line_snapshot();
+        assert_eq!(
+            map_mouse_event(&click(1, 9), &snap, (0, 0)),
+            InputAction::SetCursor(Pos::new(1, 1))
+        );
+        let right = Event::MouseClick {
+            x: 1,
+            y: 0,
+            button: MouseButton::Right,
+        };
+        assert_eq!(map_mouse_event(&right, &snap, (0, 0)), InputAction::None);
+        assert_eq!(
+            map_mouse_event(&click(0, 0), &RenderSnapshot::new(0, Vec::new()), (0, 0)),
+            InputAction::None
+        );
+    }
+
+    #[test]
+    fn ctrl_e_scrolls_in_normal_mode() {
+        let ctrl_e = Event::KeyWithModifiers(KeyWithModifiers {
+            key: KeyKind::Char('e'),
+            mods: KeyModifiers {
+                ctrl: true,
+                ..KeyModifiers::default()
+            },
+        });
+        assert_eq!(
+            map_event(&ctrl_e, EditorMode::Normal),
+            InputAction::ScrollBy { dx: 0, dy: 1 }
+        );
+        assert_eq!(map_event(&ctrl_e, EditorMode::Insert), InputAction::None);
+    }
+
+    #[test]
+    fn resize_maps_in_every_mode() {
+        let resize = Event::Resize {
+            width: 80,
+            height: 24,
+        };
+        assert_eq!(map_event(&resize, EditorMode::Insert), InputAction::Resize);
+        assert_eq!(
+            map_event(&resize, EditorMode::CommandLine),
+            InputAction::Resize
+        );
+        assert_eq!(map_pending_event(&resize), InputAction::Resize);
+    }
+}
@@ -1,44 +1,458 @@
 use std::env;
 use std::path::PathBuf;
 
-use editor_core::TextBuffer;
-use editor_core::io::load_buffer;
+use anyhow::bail;
+use editor_core::io::{load_buffer_with_limit, save_buffer, save_buffer_as};
+use editor_core::{Pos, Selection, TextBuffer};
 
 use minui::{Window, prelude::*};
 
+mod buffers;
+mod command_line;
 mod input;
+mod operators;
+mod redraw;
+mod repeat;
 mod ui;
 
-use input::{InputAction, map_event};
-use ui::{GraphemeCache, TextViewport, draw_snapshot, snapshot_lines_wrapped_cached};
+use buffers::{BufferList, OpenBuffer, ViewState};
+use command_line::{CommandLine, ExCommand, parse_ex_command};
+use input::{
+    CharSearch, CommandParser, EditorMode, InputAction, Motion, ParseStep, map_event,
+    map_mouse_event, map_pending_event,
+};
+use operators::{Register, apply_command, paste_register, resolve_find_repeat};
+use redraw::Redraw;
+use repeat::{ChangeKind, LastChange, repeat_last_change};
+use ui::{
+    GraphemeCache, Layout, LineNumberMode, Pane, RenderSnapshot, StatusLine, TextViewport,
+    WrapMode, clamp_scroll, draw_cursor, draw_search_matches, draw_selection,
+    draw_snapshot_with_gutter, draw_status_line, follow_cursor, snapshot_lines_wrapped_cached,
+};
+
+/// Rows of context kept above/below the cursor when scrolling to follow it.
+const SCROLLOFF: usize = 3;
+
+/// Files bigger than this are refused instead of read into memory.
+const MAX_FILE_BYTES: u64 = 512 * 1024 * 1024;
 
 #[derive(Debug)]
 struct EditorState {
-    buffer: TextBuffer,
+    /// Open buffers; the active one is on screen.
+    buffers: BufferList,
+    mode: EditorMode,
+    cursor: Pos,
+    /// Selection anchor while selecting; the cursor is the other end.
+    anchor: Option<Pos>,
+    /// Partially typed `[count][operator]motion` command.
+    commands: CommandParser,
+    /// Text from the last delete/change/yank, put back by `p`/`P`.
+    register: Option<Register>,
+    /// The last completed change, replayed by `.`.
+    last_change: Option<LastChange>,
+    /// The change being typed in Insert mode; becomes `last_change` on Escape.
+    pending_change: Option<LastChange>,
+    /// The last `f`/`F`/`t`/`T` search, repeated by `;` and `,`.
+    last_char_search: Option<CharSearch>,
+    /// The active buffer's view; saved into the buffer list when switching away.
     scroll_x: usize,
     scroll_y: usize,
+    /// Set when the cursor moved; the next frame scrolls to keep it visible.
+    follow_cursor: bool,
+    /// Set when the terminal was resized; the next frame clamps the scroll offsets.
+    resized: bool,
+    /// What the next frame has to redraw; frames with nothing to redraw are skipped.
+    needs_redraw: Redraw,
     grapheme_cache: GraphemeCache,
+    /// Panes sharing the text area. The focused one shows the active buffer
+    /// through the fields above; the others keep their own view.
+    layout: Layout,
+    /// The last frame's layout and where its text started on screen, for mapping
+    /// mouse clicks.
+    last_snapshot: RenderSnapshot,
+    last_text_origin: (usize, usize),
+    line_numbers: LineNumberMode,
+    /// Marker drawn left of soft-wrap continuation rows (`None` hides the column).
+    wrap_marker: Option<char>,
+    /// Soft-wrap column when narrower than the window (`None` wraps at the edge).
+    wrap_width: Option<u16>,
+    wrap_mode: WrapMode,
+    /// Term whose matches are highlighted (`hlsearch`); `None` when not searching.
+    search_term: Option<String>,
+    command_line: CommandLine,
+    /// Result of the last command-line command, shown on the bottom row.
+    message: Option<String>,
+    /// Set by `:q`/`:wq`; the event loop exits after the current event.
+    quit: bool,
 }
 
 impl EditorState {
     fn new(buffer: TextBuffer) -> Self {
         Self {
-            buffer,
+            buffers: BufferList::new(OpenBuffer::new(buffer)),
+            mode: EditorMode::default(),
+            cursor: Pos::zero(),
+            anchor: None,
+            commands: CommandParser::new(),
+            register: None,
+            last_change: None,
+            pending_change: None,
+            last_char_search: None,
             scroll_x: 0,
             scroll_y: 0,
+            follow_cursor: false,
+            resized: false,
+            needs_redraw: Redraw::Full,
             // Cache a few screens worth of lines. Will tune this later.
             grapheme_cache: GraphemeCache::new(512),
+            layout: Layout::new(Pane::new(0)),
+            last_snapshot: RenderSnapshot::new(0, Vec::new()),
+            last_text_origin: (0, 0),
+            line_numbers: LineNumberMode::default(),
+            wrap_marker: Some('↪'),
+            wrap_width: None,
+            wrap_mode: WrapMode::default(),
+            search_term: None,
+            command_line: CommandLine::new(),
+            message: None,
+            quit: false,
         }
     }
 
+    /// The active buffer.
+    fn buffer(&self) -> &TextBuffer {
+        &self.buffers.active().buffer
+    }
+
+    /// Apply the display options shared by every pane to `viewport`, which shows
+    /// `buffer`.
+    fn configure_viewport(&self, viewport: &mut TextViewport, buffer: &TextBuffer) {
+        viewport.gutter_width = self.line_numbers.gutter_width(buffer.len_lines());
+        viewport.wrap_marker = self.wrap_marker;
+        viewport.wrap_width = self.wrap_width;
+        viewport.wrap_mode = self.wrap_mode;
+    }
+
+    fn status_line(&self) -> StatusLine {
+        let active = self.buffers.active();
+        StatusLine {
+            mode: self.mode,
+            file_name: active.buffer.path().map_or_else(
+                || active.buffer.name().to_string(),
+                |p| p.display().to_string(),
+            ),
+            cursor: self.cursor,
+            len_lines: active.buffer.effective_len_lines(),
+            dirty: active.is_dirty(),
+            buffer: (self.buffers.active_index() + 1, self.buffers.len()),
+        }
+    }
+
+    /// The active buffer's current view.
+    fn view(&self) -> ViewState {
+        ViewState {
+            cursor: self.cursor,
+            scroll_x: self.scroll_x,
+            scroll_y: self.scroll_y,
+        }
+    }
+
+    /// Make buffer `index` active, restoring the view it was left with.
+    fn switch_buffer(&mut self, index: usize) -> anyhow::Result<()> {
+        let Some(view) = self.buffers.switch_to(index, self.view()) else {
+            bail!("buffer {} does not exist", index + 1);
+        };
+        self.restore_view(view);
+        Ok(())
+    }
+
+    /// Show the newly active buffer at `view`, dropping state tied to the old one.
+    fn restore_view(&mut self, view: ViewState) {
+        self.cursor = self.buffer().clamp_pos(view.cursor);
+        (self.scroll_x, self.scroll_y) = (view.scroll_x, view.scroll_y);
+        self.anchor = None;
+        self.commands = CommandParser::new();
+        self.pending_change = None;
+        // Cached lines are keyed by line index and generation, which another
+        // buffer can share.
+        self.grapheme_cache.clear();
+        self.last_snapshot = RenderSnapshot::new(0, Vec::new());
+        self.follow_cursor = true;
+    }
+
+    /// Move the cursor to the start of 1-based `line` (clamped into the buffer).
+    fn goto_line(&mut self, line: usize) {
+        let last = self.buffer().effective_len_lines() - 1;
+        self.cursor = Pos::new(line.saturating_sub(1).min(last), 0);
+        self.follow_cursor = true;
+    }
+
+    fn submit_command_line(&mut self) {
+        let text = self.command_line.take();
+        self.mode = EditorMode::Normal;
+        let result = parse_ex_command(&text)
+            .map_err(anyhow::Error::from)
+            .and_then(|cmd| self.execute_ex(cmd));
+        self.message = result.err().map(|err| format!("{err:#}"));
+    }
+
+    fn execute_ex(&mut self, cmd: ExCommand) -> anyhow::Result<()> {
+        match cmd {
+            ExCommand::Write(path) => self.write(path)?,
+            ExCommand::Quit { force } => {
+                if !force && let Some(dirty) = self.buffers.iter().find(|b| b.is_dirty()) {
+                    bail!(
+                        "no write since last change for {} (add ! to override)",
+                        dirty.buffer.name()
+                    );
+                }
+                self.quit = true;
+            }
+            ExCommand::WriteQuit => {
+                self.write(None)?;
+                self.quit = true;
+            }
+            ExCommand::Edit(path) => {
+                let index = match self.buffers.find(&path) {
+                    Some(index) => index,
+                    None => {
+                        let buffer = load_buffer_with_limit(&path, MAX_FILE_BYTES)?;
+                        self.buffers.add(OpenBuffer::new(buffer))
+                    }
+                };
+                self.switch_buffer(index)?;
+            }
+            ExCommand::BufferNext => {
+                let view = self.buffers.next(self.view());
+                self.restore_view(view);
+            }
+            ExCommand::BufferPrev => {
+                let view = self.buffers.prev(self.view());
+                self.restore_view(view);
+            }
+            ExCommand::Buffer(number) => {
+                if number == 0 || number > self.buffers.len() {
+                    bail!("buffer {number} does not exist");
+                }
+                self.switch_buffer(number - 1)?;
+            }
+            ExCommand::BufferDelete { force } => {
+                let active = self.buffers.active();
+                if !force && active.is_dirty() {
+                    bail!(
+                        "no write since last change for {} (add ! to override)",
+                        active.buffer.name()
+                    );
+                }
+                if self.buffers.remove(self.buffers.active_index()).is_none() {
+                    bail!("cannot delete the last buffer");
+                }
+                let view = self.buffers.active().view;
+                self.restore_view(view);
+            }
+            ExCommand::Split => self.layout.split_horizontal(),
+            ExCommand::VerticalSplit => self.layout.split_vertical(),
+            ExCommand::WindowNext => self.focus_next_pane()?,
+            ExCommand::Goto(line) => self.goto_line(line),
+        }
+        Ok(())
+    }
+
+    /// Move focus to the next pane, making its buffer active at its scroll offsets.
+    fn focus_next_pane(&mut self) -> anyhow::Result<()> {
+        let focused = &mut self.layout.focused_mut().viewport;
+        (focused.scroll_x, focused.scroll_y) = (self.scroll_x, self.scroll_y);
+        self.layout.focus_next();
+
+        let pane = *self.layout.focused();
+        if pane.buffer != self.buffers.active_index() {
+            self.switch_buffer(pane.buffer)?;
+        }
+        (self.scroll_x, self.scroll_y) = (pane.viewport.scroll_x, pane.viewport.scroll_y);
+        self.follow_cursor = true;
+        Ok(())
+    }
+
+    /// Save to `path`, or to the buffer's own file.
+    fn write(&mut self, path: Option<PathBuf>) -> anyhow::Result<()> {
+        let active = self.buffers.active_mut();
+        match path {
+            Some(path) if active.buffer.path() != Some(path.as_path()) => {
+                save_buffer_as(&path, &active.buffer)?;
+            }
+            _ => {
+                save_buffer(&active.buffer)?;
+                active.saved_content_id = active.buffer.content_id();
+            }
+        }
+        Ok(())
+    }
+
+    /// The active selection, if any.
+    fn selection(&self) -> Option<Selection> {
+        self.anchor
+            .map(|anchor| Selection::new(anchor, self.cursor))
+    }
+
+    /// Apply `action` and mark what the next frame has to redraw.
     fn apply_input(&mut self, action: InputAction) {
+        let generation = self.buffer().generation();
+        let lines = self.buffer().len_lines();
+        let mut redraw = Redraw::for_action(&action);
+        self.apply_action(action);
+        if self.buffer().generation() != generation {
+            redraw = Redraw::Full;
+            // Drop what the edit made stale; lines the cache still holds are
+            // re-hashed on their next lookup anyway, so this is only a shortcut.
+            if let Some(pos) = self.buffer().last_edit_pos() {
+                if self.buffer().len_lines() == lines {
+                    self.grapheme_cache.invalidate_line(pos.line);
+                } else {
+                    self.grapheme_cache.invalidate_from(pos.line);
+                }
+            }
+        }
+        self.needs_redraw.mark(redraw);
+    }
+
+    fn apply_action(&mut self, action: InputAction) {
         match action {
             InputAction::ScrollBy { dx, dy } => {
                 self.scroll_x = apply_scroll_delta(self.scroll_x, dx);
                 self.scroll_y = apply_scroll_delta(self.scroll_y, dy);
+                return;
+            }
+            InputAction::EnterNormal => {
+                self.mode = EditorMode::Normal;
+                self.anchor = None;
+                self.command_line.take();
+                if let Some(change) = self.pending_change.take() {
+                    self.last_change = Some(change);
+                }
+            }
+            InputAction::EnterInsert => {
+                self.mode = EditorMode::Insert;
+                self.anchor = None;
+                self.pending_change = Some(LastChange::new(ChangeKind::Insert));
+            }
+            InputAction::EnterVisual => {
+                self.mode = EditorMode::Visual;
+                self.anchor = Some(self.cursor);
+            }
+            InputAction::EnterCommandLine => {
+                self.mode = EditorMode::CommandLine;
+                self.message = None;
+                return;
+            }
+            InputAction::CommandLineChar(c) => {
+                self.command_line.push(c);
+                return;
+            }
+            InputAction::CommandLineBackspace => {
+                if !self.command_line.backspace() {
+                    self.mode = EditorMode::Normal;
+                }
+                return;
+            }
+            InputAction::SubmitCommandLine => {
+                self.submit_command_line();
+                return;
+            }
+            InputAction::SetCursor(pos) => self.cursor = self.buffer().clamp_pos(pos),
+            InputAction::MoveLeft => self.cursor = self.buffer().move_left(self.cursor),
+            InputAction::MoveRight => self.cursor = self.buffer().move_right(self.cursor),
+            InputAction::MoveUp => self.cursor = self.buffer().move_up(self.cursor),
+            InputAction::MoveDown => self.cursor = self.buffer().move_down(self.cursor),
+            InputAction::InsertChar(c) => {
+                self.cursor = self
+                    .buffers
+                    .active_mut()
+                    .buffer
+                    .insert(self.cursor, c.encode_utf8(&mut [0; 4]));
+                if let Some(change) = &mut self.pending_change {
+                    change.push_char(c);
+                }
+            }
+            InputAction::InsertNewline => {
+                if let Some(change) = &mut self.pending_change {
+                    change.push_char('\n');
+                }
+                self.cursor = self
+                    .buffers
+                    .active_mut()
+                    .buffer
+                    .insert_newline(Selection::empty(self.cursor))
+                    .cursor;
+            }
+            InputAction::DeleteCharBackward => {
+                self.cursor = self
+                    .buffers
+                    .active_mut()
+                    .buffer
+                    .backspace(Selection::empty(self.cursor))
+                    .cursor;
+                if let Some(change) = &mut self.pending_change {
+                    change.pop_char();
+                }
+            }
+            InputAction::DeleteCharForward => {
+                self.cursor = self
+                    .buffers
+                    .active_mut()
+                    .buffer
+                    .delete(Selection::empty(self.cursor))
+                    .cursor;
+                if self.mode == EditorMode::Normal {
+                    self.last_change = Some(LastChange::new(ChangeKind::DeleteChar));
+                }
+            }
+            InputAction::RepeatChange => {
+                let Some(change) = &self.last_change else {
+                    return;
+                };
+                self.cursor =
+                    repeat_last_change(&mut self.buffers.active_mut().buffer, self.cursor, change);
+            }
+            InputAction::Paste { before } => {
+                let Some(register) = &self.register else {
+                    return;
+                };
+                let buffer = &mut self.buffers.active_mut().buffer;
+                self.cursor = paste_register(buffer, self.cursor, register, before);
+            }
+            InputAction::CommandKey(key) => {
+                let ParseStep::Complete(cmd) = self.commands.feed(key) else {
+                    return;
+                };
+                if let Motion::FindChar(search) = cmd.motion {
+                    self.last_char_search = Some(search);
+                }
+                let sel = self.selection().unwrap_or(Selection::empty(self.cursor));
+                let buffer = &mut self.buffers.active_mut().buffer;
+                let Some(cmd) = resolve_find_repeat(buffer, sel.cursor, cmd, self.last_char_search)
+                else {
+                    return;
+                };
+                let outcome = apply_command(buffer, sel, cmd);
+                self.cursor = outcome.selection.cursor;
+                if outcome.register.is_some() {
+                    self.register = outcome.register;
+                }
+                if outcome.enter_insert {
+                    self.mode = EditorMode::Insert;
+                    self.pending_change = LastChange::from_command(cmd);
+                } else if let Some(change) = LastChange::from_command(cmd) {
+                    self.last_change = Some(change);
+                }
+            }
+            InputAction::Resize => {
+                // The old layout no longer matches the screen; don't map clicks with it.
+                self.last_snapshot = RenderSnapshot::new(0, Vec::new());
+                self.resized = true;
             }
-            InputAction::Quit | InputAction::None => {}
+            InputAction::Quit | InputAction::None => return,
         }
+        self.follow_cursor = true;
     }
 }
 
@@ -50,11 +464,122 @@ fn apply_scroll_delta(current: usize, delta: i32) -> usize {
     }
 }
 
-fn draw_buffer_view(state: &mut EditorState, window: &mut dyn Window) -> minui::Result<()> {
-    let viewport = TextViewport::from_window(window, state.scroll_x, state.scroll_y);
-    let snapshot =
-        snapshot_lines_wrapped_cached(&state.buffer, &viewport, &mut state.grapheme_cache);
-    draw_snapshot(&snapshot, window)
+/// Draw the whole view. With `Redraw::Cursor` the last frame's snapshot is
+/// reused unless following the cursor scrolled the view.
+fn draw_buffer_view(
+    state: &mut EditorState,
+    redraw: Redraw,
+    window: &mut dyn Window,
+) -> minui::Result<()> {
+    let (width, height) = window.get_size();
+    // The bottom two rows are kept for the status line and the command line.
+    let text_height = height.saturating_sub(2);
+
+    state.layout.focused_mut().buffer = state.buffers.active_index();
+    let panes = state.layout.viewports(width, text_height);
+    let focus = state.layout.focus();
+    for (index, &(_, pane)) in panes.iter().enumerate() {
+        if index != focus {
+            draw_pane(state, pane, window)?;
+        }
+    }
+
+    let (_, pane) = panes[focus];
+    let buffer = &state.buffers.active().buffer;
+    let mut viewport = pane.viewport;
+    (viewport.scroll_x, viewport.scroll_y) = (state.scroll_x, state.scroll_y);
+    state.configure_viewport(&mut viewport, buffer);
+    if std::mem::take(&mut state.resized) {
+        (state.scroll_x, state.scroll_y) = clamp_scroll(buffer, &viewport);
+        (viewport.scroll_x, viewport.scroll_y) = (state.scroll_x, state.scroll_y);
+    }
+    let mut scrolled = false;
+    if std::mem::take(&mut state.follow_cursor) {
+        let scroll = follow_cursor(buffer, &viewport, state.cursor, SCROLLOFF);
+        scrolled = scroll != (state.scroll_x, state.scroll_y);
+        (state.scroll_x, state.scroll_y) = scroll;
+        (viewport.scroll_x, viewport.scroll_y) = scroll;
+    }
+    state.layout.focused_mut().viewport = viewport;
+    let snapshot = if redraw == Redraw::Cursor && !scrolled {
+        std::mem::replace(&mut state.last_snapshot, RenderSnapshot::new(0, Vec::new()))
+    } else {
+        snapshot_lines_wrapped_cached(buffer, &viewport, &mut state.grapheme_cache)
+    };
+
+    draw_snapshot_with_gutter(
+        &snapshot,
+        &viewport,
+        state.line_numbers,
+        state.cursor.line,
+        window,
+    )?;
+    draw_search_matches(
+        buffer,
+        &viewport,
+        &snapshot,
+        state.search_term.as_deref(),
+        window,
+    )?;
+    if let Some(sel) = state.selection() {
+        draw_selection(buffer, &viewport, &snapshot, sel, window)?;
+    }
+    draw_cursor(buffer, &viewport, &snapshot, state.cursor, window)?;
+
+    draw_status_line(&state.status_line(), text_height, width, window)?;
+
+    let bottom = text_height + 1;
+    if state.mode == EditorMode::CommandLine {
+        window.write_str(bottom, 0, &format!(":{}", state.command_line.text()))?;
+    } else if let Some(message) = &state.message {
+        window.write_str(bottom, 0, message)?;
+    }
+
+    state.last_snapshot = snapshot;
+    state.last_text_origin = (
+        viewport.top as usize,
+        viewport.left as usize + viewport.text_x(),
+    );
+    Ok(())
+}
+
+/// Draw a pane that doesn't have focus: its text and search matches, but no
+/// cursor or selection.
+fn draw_pane(state: &mut EditorState, pane: Pane, window: &mut dyn Window) -> minui::Result<()> {
+    // Buffers closed since the pane was split leave it blank.
+    let Some(open) = state.buffers.iter().nth(pane.buffer) else {
+        return Ok(());
+    };
+    let buffer = &open.buffer;
+    let mut viewport = pane.viewport;
+    state.configure_viewport(&mut viewport, buffer);
+
+    // The grapheme cache is keyed by line index, so only the active buffer's
+    // panes can share it.
+    let active = pane.buffer == state.buffers.active_index();
+    let mut own_cache;
+    let (cache, cursor) = if active {
+        (&mut state.grapheme_cache, state.cursor)
+    } else {
+        own_cache = GraphemeCache::new(viewport.height as usize);
+        (&mut own_cache, open.view.cursor)
+    };
+    let snapshot = snapshot_lines_wrapped_cached(buffer, &viewport, cache);
+
+    draw_snapshot_with_gutter(
+        &snapshot,
+        &viewport,
+        state.line_numbers,
+        cursor.line,
+        window,
+    )?;
+    draw_search_matches(
+        buffer,
+        &viewport,
+        &snapshot,
+        state.search_term.as_deref(),
+        window,
+    )
 }
 
 fn parse_path_arg() -> anyhow::Result<PathBuf> {
@@ -67,7 +592,7 @@ fn parse_path_arg() -> anyhow::Result<PathBuf> {
 
 fn main() -> minui::Result<()> {
     let path = parse_path_arg().expect("file path required (e.g. editor_tui ./file.txt)");
-    let buffer = load_buffer(&path).expect("failed to load file");
+    let buffer = load_buffer_with_limit(&path, MAX_FILE_BYTES).expect("failed to load file");
 
     let mut app = App::new(EditorState::new(buffer))?;
 
@@ -75,17 +600,31 @@ fn main() -> minui::Result<()> {
     app.run(
         |state, event| {
             // Closure for handling input and updates.
-            match map_event(&event) {
+            let action = if matches!(event, Event::MouseClick { .. }) {
+                match state.mode {
+                    EditorMode::CommandLine => InputAction::None,
+                    _ => map_mouse_event(&event, &state.last_snapshot, state.last_text_origin),
+                }
+            } else if state.commands.is_pending() {
+                map_pending_event(&event)
+            } else {
+                map_event(&event, state.mode)
+            };
+            match action {
                 InputAction::Quit => false,
                 action => {
                     state.apply_input(action);
-                    true
+                    !state.quit
                 }
             }
         },
         |state, window| {
             // Closure for rendering the application state.
-            draw_buffer_view(state, window)?;
+            let redraw = state.needs_redraw.take();
+            if redraw == Redraw::None {
+                return Ok(());
+            }
+            draw_buffer_view(state, redraw, window)?;
 
             window.flush()?;
 
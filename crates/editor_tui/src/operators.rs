@@ -0,0 +1,550 @@
+//! Applying parsed Vim commands to the buffer.
+//!
+//! This is glue between the input layer (which produces a [`Command`]) and the
+//! core buffer (which only knows positions and edits), so it lives in the TUI.
+//!
+//! Motions come in three flavours, as in Vim:
+//! - exclusive (`w`, `b`, `h`, `l`, `0`, `F`, `T`): the range stops before the target;
+//! - inclusive (`e`, `$`, `f`, `t`): the char at the target is part of the range;
+//! - linewise (`j`, `k`, `dd`): whole lines, newlines included.
+
+use editor_core::{Edit, Pos, Selection, TextBuffer};
+
+use crate::input::{CharSearch, Command, Motion, Operator};
+
+/// Text captured by a delete, change or yank, and put back by `p`/`P`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+    pub text: String,
+    /// Whole lines (ending in a newline) rather than a charwise span.
+    pub linewise: bool,
+}
+
+/// What applying a command did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandOutcome {
+    /// The new selection. Motions without an operator move the cursor and keep
+    /// the anchor; operators collapse it.
+    pub selection: Selection,
+    /// Text removed or copied by an operator.
+    pub register: Option<Register>,
+    /// The command was a change (`c`), so the caller should enter Insert mode.
+    pub enter_insert: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MotionKind {
+    Exclusive,
+    Inclusive,
+    Linewise,
+}
+
+fn motion_kind(motion: Motion) -> MotionKind {
+    match motion {
+        Motion::Up | Motion::Down | Motion::CurrentLine => MotionKind::Linewise,
+        Motion::WordEnd | Motion::LineEnd => MotionKind::Inclusive,
+        Motion::FindChar(search) if search.forward => MotionKind::Inclusive,
+        _ => MotionKind::Exclusive,
+    }
+}
+
+/// Where `motion` (repeated `count` times) takes the cursor from `pos`.
+fn motion_target(buffer: &TextBuffer, pos: Pos, motion: Motion, count: usize) -> Pos {
+    let pos = buffer.clamp_pos(pos);
+    let last_line = buffer.effective_len_lines().saturating_sub(1);
+
+    match motion {
+        Motion::Left => Pos::new(pos.line, pos.col.saturating_sub(count)),
+        Motion::Right => Pos::new(
+            pos.line,
+            (pos.col + count).min(buffer.line_len_chars(pos.line)),
+        ),
+        Motion::Up => (0..count).fold(pos, |p, _| buffer.move_up(p)),
+        Motion::Down => (0..count).fold(pos, |p, _| buffer.move_down(p)),
+        Motion::WordForward => (0..count).fold(pos, |p, _| buffer.word_start_after(p)),
+        Motion::WordBackward => (0..count).fold(pos, |p, _| buffer.word_start_before(p)),
+        Motion::WordEnd => (0..count).fold(pos, |p, _| {
+            // Land on the last char of the word, starting past the current one.
+            let end = buffer.word_end_after(buffer.move_right(p));
+            buffer.move_left(end).max(p)
+        }),
+        Motion::LineStart => Pos::new(pos.line, 0),
+        Motion::LineEnd => {
+            let line = (pos.line + count - 1).min(last_line);
+            Pos::new(line, buffer.line_len_chars(line).saturating_sub(1))
+        }
+        Motion::CurrentLine => Pos::new((pos.line + count - 1).min(last_line), pos.col),
+        Motion::FindChar(search) => find_char_target(buffer, pos, search, count).unwrap_or(pos),
+        // Left to `resolve_find_repeat`; unresolved, it goes nowhere.
+        Motion::RepeatFindChar { .. } => pos,
+    }
+}
+
+/// Where `search` lands after skipping `count - 1` earlier matches, or `None` if
+/// the line runs out first.
+fn find_char_target(
+    buffer: &TextBuffer,
+    pos: Pos,
+    search: CharSearch,
+    count: usize,
+) -> Option<Pos> {
+    let find = |p: Pos, till: bool| match search.forward {
+        true => buffer.find_char_forward(p, search.ch, till),
+        false => buffer.find_char_backward(p, search.ch, till),
+    };
+    // Step over earlier matches with `f`/`F` so a till search doesn't get stuck
+    // next to the first one.
+    let before_last = (1..count).try_fold(pos, |p, _| find(p, false))?;
+    find(before_last, search.till)
+}
+
+/// Turn a `;`/`,` in `cmd` into the find-char it repeats, given the `last`
+/// `f`/`F`/`t`/`T` search. Other commands come back unchanged; a repeat with
+/// nothing to repeat is `None`.
+///
+/// A repeated till search that wouldn't move (the cursor is already next to a
+/// match) skips to the match after, so `;` after `t` keeps going, as in Vim.
+pub fn resolve_find_repeat(
+    buffer: &TextBuffer,
+    cursor: Pos,
+    cmd: Command,
+    last: Option<CharSearch>,
+) -> Option<Command> {
+    let Motion::RepeatFindChar { reverse } = cmd.motion else {
+        return Some(cmd);
+    };
+    let search = if reverse { last?.reversed() } else { last? };
+    let cursor = buffer.clamp_pos(cursor);
+    let mut count = cmd.count.max(1);
+    if search.till && find_char_target(buffer, cursor, search, 1) == Some(cursor) {
+        count += 1;
+    }
+    Some(Command {
+        count,
+        motion: Motion::FindChar(search),
+        ..cmd
+    })
+}
+
+/// Apply `cmd` to `buffer`, starting from `sel.cursor`.
+pub fn apply_command(buffer: &mut TextBuffer, sel: Selection, cmd: Command) -> CommandOutcome {
+    let cursor = buffer.clamp_pos(sel.cursor);
+    let count = cmd.count.max(1);
+
+    let Some(op) = cmd.operator else {
+        let mut target = motion_target(buffer, cursor, cmd.motion, count);
+        if cmd.motion == Motion::CurrentLine {
+            target = cursor;
+        }
+        return CommandOutcome {
+            selection: Selection::new(sel.anchor, target),
+            register: None,
+            enter_insert: false,
+        };
+    };
+
+    // `cw` on a word behaves like `ce` (it doesn't eat the trailing whitespace).
+    let mut motion = cmd.motion;
+    if op == Operator::Change
+        && motion == Motion::WordForward
+        && buffer.char_at(cursor).is_some_and(|c| !c.is_whitespace())
+    {
+        motion = Motion::WordEnd;
+    }
+
+    // A find-char that doesn't match cancels the operator, as in Vim, and so
+    // does a `;`/`,` that wasn't resolved to one.
+    let no_target = match motion {
+        Motion::FindChar(search) => find_char_target(buffer, cursor, search, count).is_none(),
+        Motion::RepeatFindChar { .. } => true,
+        _ => false,
+    };
+    if no_target {
+        return CommandOutcome {
+            selection: Selection::empty(cursor),
+            register: None,
+            enter_insert: false,
+        };
+    }
+
+    let target = motion_target(buffer, cursor, motion, count);
+    match motion_kind(motion) {
+        MotionKind::Linewise => {
+            let first = cursor.line.min(target.line);
+            let last = cursor.line.max(target.line);
+            apply_linewise(buffer, op, first, last)
+        }
+        kind => {
+            let (start, mut end) = if target < cursor {
+                (target, cursor)
+            } else {
+                (cursor, target)
+            };
+            if kind == MotionKind::Inclusive {
+                end.col = (end.col + 1).min(buffer.line_len_chars(end.line));
+            }
+            // `dw` on the last word of a line stops at the end of that line
+            // instead of joining it with the next.
+            if motion == Motion::WordForward && end.line > start.line {
+                let line = end.line - 1;
+                end = Pos::new(line, buffer.line_len_chars(line)).max(start);
+            }
+            apply_charwise(buffer, op, start, end)
+        }
+    }
+}
+
+fn apply_charwise(buffer: &mut TextBuffer, op: Operator, start: Pos, end: Pos) -> CommandOutcome {
+    let register = Register {
+        text: buffer.slice_pos_range(start, end),
+        linewise: false,
+    };
+    let cursor = match op {
+        Operator::Yank => start,
+        Operator::Delete | Operator::Change => buffer.delete_range(start, end),
+    };
+    CommandOutcome {
+        selection: Selection::empty(cursor),
+        register: Some(register),
+        enter_insert: op == Operator::Change,
+    }
+}
+
+fn apply_linewise(
+    buffer: &mut TextBuffer,
+    op: Operator,
+    first: usize,
+    last: usize,
+) -> CommandOutcome {
+    let start = buffer.line_to_char(first);
+    let is_last_line = last + 1 >= buffer.len_lines();
+    let end = if is_last_line {
+        buffer.len_chars()
+    } else {
+        buffer.line_to_char(last + 1)
+    };
+
+    let register = Some(Register {
+        text: buffer.yank_lines(first, last),
+        linewise: true,
+    });
+
+    let cursor = match op {
+        Operator::Yank => Pos::new(first, 0),
+        Operator::Change => {
+            // Keep one (now empty) line to type into.
+            let content_end = buffer.line_char_range(last).end;
+            buffer.apply_edit(Edit::delete(start..content_end));
+            Pos::new(first, 0)
+        }
+        Operator::Delete => {
+            // Deleting through the last line also takes the newline before it.
+            let start = if is_last_line && first > 0 {
+                start - 1
+            } else {
+                start
+            };
+            buffer.apply_edit(Edit::delete(start..end));
+            Pos::new(first.min(buffer.effective_len_lines() - 1), 0)
+        }
+    };
+
+    CommandOutcome {
+        selection: Selection::empty(cursor),
+        register,
+        enter_insert: op == Operator::Change,
+    }
+}
+
+/// Put `register` into `buffer` after the cursor (`p`) or, with `before`,
+/// before it (`P`). Returns the new cursor.
+///
+/// Linewise text goes in as whole lines below (or above) the cursor's line,
+/// with the cursor at the start of the first one. Charwise text goes after (or
+/// at) the cursor, which ends up on its last char.
+pub fn paste_register(
+    buffer: &mut TextBuffer,
+    cursor: Pos,
+    register: &Register,
+    before: bool,
+) -> Pos {
+    let cursor = buffer.clamp_pos(cursor);
+    if register.linewise {
+        return if before {
+            buffer.insert(Pos::new(cursor.line, 0), &register.text);
+            Pos::new(cursor.line, 0)
+        } else {
+            buffer.paste_lines(cursor.line, &register.text)
+        };
+    }
+
+    let at = if before {
+        cursor
+    } else {
+        Pos::new(
+            cursor.line,
+            (cursor.col + 1).min(buffer.line_len_chars(cursor.line)),
+        )
+    };
+    let end = buffer.insert(at, &register.text);
+    if end == at {
+        return at;
+    }
+    buffer.char_to_pos(buffer.pos_to_char(end) - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(text: &str, cursor: Pos, cmd: Command) -> (TextBuffer, CommandOutcome) {
+        let mut buffer = TextBuffer::from_str(text);
+        let outcome = apply_command(&mut buffer, Selection::empty(cursor), cmd);
+        (buffer, outcome)
+    }
+
+    fn cmd(count: usize, operator: Option<Operator>, motion: Motion) -> Command {
+        Command {
+            count,
+            operator,
+            motion,
+        }
+    }
+
+    #[test]
+    fn dw_deletes_word_and_trailing_space() {
+        let (b, out) = run(
+            "one two three",
+            Pos::new(0, 4),
+            cmd(1, Some(Operator::Delete), Motion::WordForward),
+        );
+        assert_eq!(b.to_string(), "one three");
+        assert_eq!(out.selection.cursor, Pos::new(0, 4));
+        assert_eq!(out.register.unwrap().text, "two ");
+    }
+
+    #[test]
+    fn dw_on_last_word_keeps_the_newline() {
+        let (b, _) = run(
+            "one two\nthree",
+            Pos::new(0, 4),
+            cmd(1, Some(Operator::Delete), Motion::WordForward),
+        );
+        assert_eq!(b.to_string(), "one \nthree");
+    }
+
+    #[test]
+    fn de_is_inclusive() {
+        let (b, _) = run(
+            "one two three",
+            Pos::new(0, 4),
+            cmd(2, Some(Operator::Delete), Motion::WordEnd),
+        );
+        assert_eq!(b.to_string(), "one ");
+    }
+
+    #[test]
+    fn dd_removes_whole_lines() {
+        let (b, out) = run(
+            "a\nb\nc",
+            Pos::new(1, 0),
+            cmd(1, Some(Operator::Delete), Motion::CurrentLine),
+        );
+        assert_eq!(b.to_string(), "a\nc");
+        assert_eq!(out.selection.cursor, Pos::new(1, 0));
+        assert_eq!(
+            out.register,
+            Some(Register {
+                text: "b\n".into(),
+                linewise: true
+            })
+        );
+
+        // Last line: the preceding newline goes too.
+        let (b, out) = run(
+            "a\nb\nc",
+            Pos::new(1, 0),
+            cmd(2, Some(Operator::Delete), Motion::CurrentLine),
+        );
+        assert_eq!(b.to_string(), "a");
+        assert_eq!(out.selection.cursor, Pos::new(0, 0));
+
+        // Last line before a trailing newline: the cursor doesn't land on the
+        // phantom line after it.
+        let (b, out) = run(
+            "a\nb\n",
+            Pos::new(1, 0),
+            cmd(1, Some(Operator::Delete), Motion::CurrentLine),
+        );
+        assert_eq!(b.to_string(), "a\n");
+        assert_eq!(out.selection.cursor, Pos::new(0, 0));
+    }
+
+    #[test]
+    fn cw_behaves_like_ce_and_enters_insert() {
+        let (b, out) = run(
+            "one two three",
+            Pos::new(0, 4),
+            cmd(1, Some(Operator::Change), Motion::WordForward),
+        );
+        assert_eq!(b.to_string(), "one  three");
+        assert!(out.enter_insert);
+    }
+
+    #[test]
+    fn yank_leaves_the_buffer_alone() {
+        let (b, out) = run(
+            "hello world",
+            Pos::new(0, 6),
+            cmd(1, Some(Operator::Yank), Motion::LineEnd),
+        );
+        assert_eq!(b.to_string(), "hello world");
+        assert_eq!(out.register.unwrap().text, "world");
+    }
+
+    #[test]
+    fn bare_motion_moves_cursor_and_keeps_anchor() {
+        let mut buffer = TextBuffer::from_str("a\nb\nc\nd");
+        let sel = Selection::empty(Pos::new(0, 0));
+        let out = apply_command(&mut buffer, sel, cmd(2, None, Motion::Down));
+        assert_eq!(
+            out.selection,
+            Selection::new(Pos::new(0, 0), Pos::new(2, 0))
+        );
+        assert!(out.register.is_none());
+    }
+
+    fn find(ch: char, forward: bool, till: bool) -> Motion {
+        Motion::FindChar(CharSearch { ch, forward, till })
+    }
+
+    #[test]
+    fn find_char_forward_is_inclusive() {
+        let (b, _) = run(
+            "foo(bar, baz)",
+            Pos::new(0, 0),
+            cmd(1, Some(Operator::Delete), find('(', true, false)),
+        );
+        assert_eq!(b.to_string(), "bar, baz)");
+
+        let (b, _) = run(
+            "foo(bar, baz)",
+            Pos::new(0, 4),
+            cmd(1, Some(Operator::Delete), find(')', true, true)),
+        );
+        assert_eq!(b.to_string(), "foo()");
+    }
+
+    #[test]
+    fn find_char_backward_is_exclusive() {
+        let (b, out) = run(
+            "foo(bar)",
+            Pos::new(0, 7),
+            cmd(1, Some(Operator::Delete), find('(', false, false)),
+        );
+        assert_eq!(b.to_string(), "foo)");
+        assert_eq!(out.selection.cursor, Pos::new(0, 3));
+    }
+
+    #[test]
+    fn counted_till_skips_earlier_matches() {
+        let mut buffer = TextBuffer::from_str("a,b,c,d");
+        let sel = Selection::empty(Pos::new(0, 0));
+        let out = apply_command(&mut buffer, sel, cmd(2, None, find(',', true, true)));
+        assert_eq!(out.selection.cursor, Pos::new(0, 2));
+    }
+
+    #[test]
+    fn repeat_find_follows_the_last_search() {
+        let buffer = TextBuffer::from_str("a,b,c,d");
+        let at = |col| Pos::new(0, col);
+        let repeat = |count, reverse| cmd(count, None, Motion::RepeatFindChar { reverse });
+        let resolve = |pos, c, last| resolve_find_repeat(&buffer, pos, c, last);
+        let till = CharSearch {
+            ch: ',',
+            forward: true,
+            till: true,
+        };
+
+        assert_eq!(resolve(at(0), repeat(1, false), None), None);
+        // `;` after `t,` (cursor next to the comma) moves on to the next one.
+        assert_eq!(
+            resolve(at(0), repeat(1, false), Some(till)),
+            Some(cmd(2, None, find(',', true, true)))
+        );
+        assert_eq!(
+            resolve(at(5), repeat(1, true), Some(till)),
+            Some(cmd(1, None, find(',', false, true)))
+        );
+
+        let mut buffer = buffer.clone();
+        let resolved = resolve_find_repeat(&buffer, at(0), repeat(1, false), Some(till)).unwrap();
+        let out = apply_command(&mut buffer, Selection::empty(at(0)), resolved);
+        assert_eq!(out.selection.cursor, at(2));
+
+        let other = cmd(1, None, Motion::WordForward);
+        assert_eq!(
+            resolve_find_repeat(&buffer, at(0), other, None),
+            Some(other)
+        );
+    }
+
+    #[test]
+    fn failed_find_char_is_a_no_op() {
+        let (b, out) = run(
+            "hello\nworld",
+            Pos::new(0, 1),
+            cmd(1, Some(Operator::Delete), find('w', true, false)),
+        );
+        assert_eq!(b.to_string(), "hello\nworld");
+        assert_eq!(out.selection.cursor, Pos::new(0, 1));
+        assert!(out.register.is_none());
+    }
+
+    #[test]
+    fn paste_charwise_and_linewise() {
+        let chars = Register {
+            text: "XY".to_string(),
+            linewise: false,
+        };
+        let mut buffer = TextBuffer::from_str("abc\ndef");
+        assert_eq!(
+            paste_register(&mut buffer, Pos::new(0, 0), &chars, false),
+            Pos::new(0, 2)
+        );
+        assert_eq!(buffer.to_string(), "aXYbc\ndef");
+        assert_eq!(
+            paste_register(&mut buffer, Pos::new(1, 0), &chars, true),
+            Pos::new(1, 1)
+        );
+        assert_eq!(buffer.to_string(), "aXYbc\nXYdef");
+
+        let lines = Register {
+            text: "new\n".to_string(),
+            linewise: true,
+        };
+        let mut buffer = TextBuffer::from_str("one\ntwo");
+        assert_eq!(
+            paste_register(&mut buffer, Pos::new(1, 2), &lines, false),
+            Pos::new(2, 0)
+        );
+        assert_eq!(buffer.to_string(), "one\ntwo\nnew");
+        assert_eq!(
+            paste_register(&mut buffer, Pos::new(1, 2), &lines, true),
+            Pos::new(1, 0)
+        );
+        assert_eq!(buffer.to_string(), "one\nnew\ntwo\nnew");
+    }
+
+    #[test]
+    fn yy_then_p_duplicates_the_line() {
+        let (mut buffer, out) = run(
+            "first\nsecond\n",
+            Pos::new(0, 3),
+            cmd(1, Some(Operator::Yank), Motion::CurrentLine),
+        );
+        let register = out.register.unwrap();
+        paste_register(&mut buffer, out.selection.cursor, &register, false);
+        assert_eq!(buffer.to_string(), "first\nfirst\nsecond\n");
+    }
+}
@@ -0,0 +1,79 @@
+//! Deciding how much of the screen the next frame has to redraw.
+//!
+//! Input handling marks what it changed and the render closure takes the mark:
+//! nothing changed means the frame is skipped; a cursor-only change (a motion,
+//! a mode switch, command-line typing) can reuse the last frame's snapshot as
+//! long as the view didn't scroll; anything else rebuilds it.
+
+use crate::input::InputAction;
+
+/// How much the next frame needs to redraw. Ordered from least to most work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Redraw {
+    /// Nothing changed; skip the frame.
+    #[default]
+    None,
+    /// Only the cursor, selection, or bottom rows changed; the text layout didn't.
+    Cursor,
+    /// The text or its layout changed; rebuild the snapshot.
+    Full,
+}
+
+impl Redraw {
+    /// The least an `action` needs redrawn, before looking at whether it edited
+    /// the buffer (callers upgrade to `Full` when it did).
+    pub fn for_action(action: &InputAction) -> Self {
+        match action {
+            InputAction::Quit | InputAction::None => Redraw::None,
+            // Scrolling and resizing move the text; `:` commands can load a file.
+            InputAction::ScrollBy { .. } | InputAction::Resize | InputAction::SubmitCommandLine => {
+                Redraw::Full
+            }
+            _ => Redraw::Cursor,
+        }
+    }
+
+    /// Raise the pending redraw to at least `level`.
+    pub fn mark(&mut self, level: Redraw) {
+        *self = (*self).max(level);
+    }
+
+    /// Take the pending redraw, leaving `None` behind.
+    pub fn take(&mut self) -> Redraw {
+        std::mem::take(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_only_escalate_until_taken() {
+        let mut redraw = Redraw::None;
+        redraw.mark(Redraw::Cursor);
+        assert_eq!(redraw, Redraw::Cursor);
+        redraw.mark(Redraw::Full);
+        redraw.mark(Redraw::Cursor);
+        assert_eq!(redraw, Redraw::Full);
+
+        assert_eq!(redraw.take(), Redraw::Full);
+        assert_eq!(redraw, Redraw::None);
+        assert_eq!(redraw.take(), Redraw::None);
+    }
+
+    #[test]
+    fn actions_pick_the_cheapest_redraw() {
+        assert_eq!(Redraw::for_action(&InputAction::None), Redraw::None);
+        assert_eq!(Redraw::for_action(&InputAction::MoveDown), Redraw::Cursor);
+        assert_eq!(
+            Redraw::for_action(&InputAction::CommandLineChar('w')),
+            Redraw::Cursor
+        );
+        assert_eq!(
+            Redraw::for_action(&InputAction::ScrollBy { dx: 0, dy: 1 }),
+            Redraw::Full
+        );
+        assert_eq!(Redraw::for_action(&InputAction::Resize), Redraw::Full);
+    }
+}
@@ -0,0 +1,154 @@
+//! Dot-repeat (`.`): replaying the last change.
+//!
+//! A [`LastChange`] is a plain-data description of what the user did: the command
+//! (or simple edit) that started the change, plus whatever was typed in Insert
+//! mode before returning to Normal. Replaying re-runs the command at the current
+//! cursor and then re-types the text, so `cwfoo<Esc>` followed by `.` turns the
+//! next word into "foo" too.
+
+use editor_core::{Pos, Selection, TextBuffer};
+
+use crate::input::{Command, Operator};
+use crate::operators::apply_command;
+
+/// The edit that started a change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// An operator command (`dw`, `3dd`, `cw`, ...). Yanks aren't changes.
+    Command(Command),
+    /// `x`: delete the char under the cursor.
+    DeleteChar,
+    /// `i`: plain Insert mode.
+    Insert,
+}
+
+/// Everything needed to repeat a change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LastChange {
+    pub kind: ChangeKind,
+    /// Text typed in Insert mode as part of the change.
+    pub inserted: String,
+}
+
+impl LastChange {
+    pub fn new(kind: ChangeKind) -> Self {
+        Self {
+            kind,
+            inserted: String::new(),
+        }
+    }
+
+    /// The change for a completed command, or `None` if it doesn't modify text.
+    pub fn from_command(cmd: Command) -> Option<Self> {
+        match cmd.operator {
+            Some(Operator::Delete | Operator::Change) => Some(Self::new(ChangeKind::Command(cmd))),
+            Some(Operator::Yank) | None => None,
+        }
+    }
+
+    /// Record a char typed in Insert mode.
+    pub fn push_char(&mut self, c: char) {
+        self.inserted.push(c);
+    }
+
+    /// Record a Backspace typed in Insert mode.
+    ///
+    /// NOTE: only text typed during this change can be un-typed; backspacing past
+    /// where insertion started isn't replayed.
+    pub fn pop_char(&mut self) {
+        self.inserted.pop();
+    }
+}
+
+/// Re-apply `change` at `cursor`, returning the new cursor.
+pub fn repeat_last_change(buffer: &mut TextBuffer, cursor: Pos, change: &LastChange) -> Pos {
+    let cursor = match change.kind {
+        ChangeKind::Command(cmd) => {
+            apply_command(buffer, Selection::empty(cursor), cmd)
+                .selection
+                .cursor
+        }
+        ChangeKind::DeleteChar => buffer.delete(Selection::empty(cursor)).cursor,
+        ChangeKind::Insert => buffer.clamp_pos(cursor),
+    };
+
+    if change.inserted.is_empty() {
+        cursor
+    } else {
+        buffer.insert(cursor, &change.inserted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Motion;
+
+    fn op(operator: Operator, motion: Motion) -> Command {
+        Command {
+            count: 1,
+            operator: Some(operator),
+            motion,
+        }
+    }
+
+    #[test]
+    fn dw_then_dot_deletes_another_word() {
+        let mut buffer = TextBuffer::from_str("one two three four");
+        let dw = op(Operator::Delete, Motion::WordForward);
+
+        let cursor = apply_command(&mut buffer, Selection::empty(Pos::zero()), dw)
+            .selection
+            .cursor;
+        let change = LastChange::from_command(dw).unwrap();
+        assert_eq!(buffer.to_string(), "two three four");
+
+        let cursor = repeat_last_change(&mut buffer, cursor, &change);
+        assert_eq!(buffer.to_string(), "three four");
+        assert_eq!(cursor, Pos::zero());
+    }
+
+    #[test]
+    fn cw_foo_then_dot_replaces_another_word() {
+        let mut buffer = TextBuffer::from_str("one two three");
+        let cw = op(Operator::Change, Motion::WordForward);
+
+        // cw, then type "fox", Backspace, "o" and leave Insert mode.
+        let mut cursor = apply_command(&mut buffer, Selection::empty(Pos::zero()), cw)
+            .selection
+            .cursor;
+        let mut change = LastChange::from_command(cw).unwrap();
+        for c in "fox".chars() {
+            cursor = buffer.insert(cursor, &c.to_string());
+            change.push_char(c);
+        }
+        cursor = buffer.backspace(Selection::empty(cursor)).cursor;
+        change.pop_char();
+        buffer.insert(cursor, "o");
+        change.push_char('o');
+        assert_eq!(buffer.to_string(), "foo two three");
+        assert_eq!(change.inserted, "foo");
+
+        // Move to "three" and repeat.
+        repeat_last_change(&mut buffer, Pos::new(0, 8), &change);
+        assert_eq!(buffer.to_string(), "foo two foo");
+    }
+
+    #[test]
+    fn repeat_plain_insert_and_x() {
+        let mut buffer = TextBuffer::from_str("ab");
+        let mut insert = LastChange::new(ChangeKind::Insert);
+        insert.push_char('-');
+        let cursor = repeat_last_change(&mut buffer, Pos::new(0, 1), &insert);
+        assert_eq!(buffer.to_string(), "a-b");
+        assert_eq!(cursor, Pos::new(0, 2));
+
+        repeat_last_change(
+            &mut buffer,
+            Pos::zero(),
+            &LastChange::new(ChangeKind::DeleteChar),
+        );
+        assert_eq!(buffer.to_string(), "-b");
+        assert!(LastChange::from_command(op(Operator::Yank, Motion::WordEnd)).is_none());
+    }
+}
@@ -0,0 +1,134 @@
+//! Cursor placement: mapping a document `Pos` to a screen cell.
+
+use editor_core::{Pos, TextBuffer};
+use minui::Window;
+use minui::prelude::{Color, ColorPair};
+
+use super::layout::{LineLayout, line_screen_row};
+use super::{RenderSnapshot, TextViewport};
+
+/// Map a document position to a screen `(row, col)`, or `None` if it's off-screen.
+///
+/// - A cursor in a grapheme that starts left of `viewport.scroll_x` cells is
+///   off-screen.
+/// - Soft wrap can spread one line over several rows; a cursor sitting on the space
+///   a row was broken at is shown at the end of that row.
+/// - The returned column includes the gutter (and wrap-marker column) offset, and
+///   both include the viewport's `left`/`top` position on screen.
+pub fn cursor_screen_pos(
+    buffer: &TextBuffer,
+    viewport: &TextViewport,
+    snapshot: &RenderSnapshot,
+    cursor: Pos,
+) -> Option<(u16, u16)> {
+    let cursor = buffer.clamp_pos(cursor);
+    let max_cells = viewport.wrap_cells();
+    if max_cells == 0 || snapshot.lines.is_empty() {
+        return None;
+    }
+
+    let line_row = line_screen_row(snapshot, cursor.line)?;
+    // Rows of the line that are on screen.
+    let rows = (snapshot.lines.len() as isize - line_row).max(0) as usize;
+    let (row_in_line, col) =
+        LineLayout::new(buffer, viewport, cursor.line, cursor.col, rows).locate(cursor.col)?;
+
+    let row = line_row + row_in_line as isize;
+    if row < 0 || row as usize >= snapshot.lines.len() {
+        return None;
+    }
+
+    Some((
+        viewport.top + row as u16,
+        viewport.left + (viewport.text_x() + col) as u16,
+    ))
+}
+
+/// Draw the cursor as an inverted cell (if it's on screen).
+pub fn draw_cursor(
+    buffer: &TextBuffer,
+    viewport: &TextViewport,
+    snapshot: &RenderSnapshot,
+    cursor: Pos,
+    window: &mut dyn Window,
+) -> minui::Result<()> {
+    let Some((row, col)) = cursor_screen_pos(buffer, viewport, snapshot, cursor) else {
+        return Ok(());
+    };
+
+    // Tabs/newlines/EOF render as a blank block.
+    let under = buffer
+        .char_at(cursor)
+        .filter(|c| !c.is_control())
+        .unwrap_or(' ');
+    window.write_str_colored(
+        row,
+        col,
+        &under.to_string(),
+        ColorPair::new(Color::Black, Color::White),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::{GraphemeCache, WrapMode, snapshot_lines_wrapped_cached};
+
+    fn viewport(width: u16, height: u16, scroll_y: usize) -> TextViewport {
+        TextViewport {
+            scroll_x: 0,
+            scroll_y,
+            left: 0,
+            top: 0,
+            width,
+            height,
+            gutter_width: 2,
+            list_chars: None,
+            wrap_marker: None,
+            wrap_width: None,
+            wrap_mode: WrapMode::Word,
+        }
+    }
+
+    fn screen_pos(text: &str, vp: TextViewport, cursor: Pos) -> Option<(u16, u16)> {
+        let buffer = TextBuffer::from_str(text);
+        let snap = snapshot_lines_wrapped_cached(&buffer, &vp, &mut GraphemeCache::new(8));
+        cursor_screen_pos(&buffer, &vp, &snap, cursor)
+    }
+
+    #[test]
+    fn cursor_on_plain_line() {
+        let vp = viewport(12, 5, 0);
+        assert_eq!(screen_pos("abc\ndef", vp, Pos::new(1, 2)), Some((1, 4)));
+    }
+
+    #[test]
+    fn cursor_on_wrapped_continuation_row() {
+        // Text width 8: "one two" / "three" / "four"
+        let vp = viewport(10, 5, 0);
+        let text = "one two three four\nx";
+        assert_eq!(screen_pos(text, vp, Pos::new(0, 10)), Some((1, 4)));
+        assert_eq!(screen_pos(text, vp, Pos::new(0, 14)), Some((2, 2)));
+        assert_eq!(screen_pos(text, vp, Pos::new(1, 0)), Some((3, 2)));
+    }
+
+    #[test]
+    fn cursor_when_scroll_lands_mid_line() {
+        let vp = viewport(10, 5, 1);
+        let text = "one two three four\nx";
+        // Row 0 of line 0 is scrolled off; "three" is now the top row.
+        assert_eq!(screen_pos(text, vp, Pos::new(0, 9)), Some((0, 3)));
+        assert_eq!(screen_pos(text, vp, Pos::new(0, 1)), None);
+    }
+
+    #[test]
+    fn cursor_scrolled_out_of_view() {
+        let vp = viewport(10, 2, 0);
+        assert_eq!(screen_pos("a\nb\nc\nd", vp, Pos::new(3, 0)), None);
+
+        let mut vp = viewport(10, 2, 0);
+        vp.scroll_x = 3;
+        assert_eq!(screen_pos("abcdef", vp, Pos::new(0, 1)), None);
+        assert_eq!(screen_pos("abcdef", vp, Pos::new(0, 4)), Some((0, 3)));
+    }
+}
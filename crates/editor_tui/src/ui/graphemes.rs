@@ -0,0 +1,79 @@
+//! Streaming grapheme segmentation over rope slices.
+//!
+//! The cached render path segments an owned `String` per line, which is fine for
+//! normal source files but means a single 10MB line gets fully copied and
+//! segmented just to show its first screen. This iterator walks the rope's chunks
+//! directly, so callers can stop as soon as they have enough graphemes.
+//!
+//! Adapted from ropey's `graphemes_iter` example.
+
+use ropey::{RopeSlice, iter::Chunks};
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
+
+/// An iterator over the extended grapheme clusters of a `RopeSlice`.
+///
+/// Graphemes that straddle a chunk boundary are yielded as sub-slices of the rope;
+/// everything else borrows directly from the current chunk.
+pub struct RopeGraphemes<'a> {
+    text: RopeSlice<'a>,
+    chunks: Chunks<'a>,
+    cur_chunk: &'a str,
+    cur_chunk_start: usize,
+    cursor: GraphemeCursor,
+}
+
+impl<'a> RopeGraphemes<'a> {
+    pub fn new(slice: RopeSlice<'a>) -> Self {
+        let mut chunks = slice.chunks();
+        let first_chunk = chunks.next().unwrap_or("");
+        Self {
+            text: slice,
+            chunks,
+            cur_chunk: first_chunk,
+            cur_chunk_start: 0,
+            cursor: GraphemeCursor::new(0, slice.len_bytes(), true),
+        }
+    }
+}
+
+impl<'a> Iterator for RopeGraphemes<'a> {
+    type Item = RopeSlice<'a>;
+
+    fn next(&mut self) -> Option<RopeSlice<'a>> {
+        let a = self.cursor.cur_cursor();
+        let b;
+        loop {
+            match self
+                .cursor
+                .next_boundary(self.cur_chunk, self.cur_chunk_start)
+            {
+                Ok(None) => return None,
+                Ok(Some(n)) => {
+                    b = n;
+                    break;
+                }
+                Err(GraphemeIncomplete::NextChunk) => {
+                    self.cur_chunk_start += self.cur_chunk.len();
+                    self.cur_chunk = self.chunks.next().unwrap_or("");
+                }
+                Err(GraphemeIncomplete::PreContext(idx)) => {
+                    let (chunk, byte_idx, _, _) = self.text.chunk_at_byte(idx.saturating_sub(1));
+                    self.cursor.provide_context(chunk, byte_idx);
+                }
+                // `next_boundary` only asks for the next chunk or pre-context.
+                Err(_) => return None,
+            }
+        }
+
+        if a < self.cur_chunk_start {
+            // The grapheme started in an earlier chunk; slice it out of the rope.
+            let a_char = self.text.byte_to_char(a);
+            let b_char = self.text.byte_to_char(b);
+            Some(self.text.slice(a_char..b_char))
+        } else {
+            let a2 = a - self.cur_chunk_start;
+            let b2 = b - self.cur_chunk_start;
+            Some((&self.cur_chunk[a2..b2]).into())
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Line-number gutter rendering.
+//!
+//! The gutter sits to the left of the text area and is `TextViewport::gutter_width`
+//! cells wide. Numbers are 1-based and right-aligned, with a single separator
+//! space before the text. Soft-wrap continuation rows get a blank gutter so each
+//! number lines up with the first visual row of its document line.
+//!
+//! What the numbers mean is controlled by [`LineNumberMode`] (Vim's `number` /
+//! `relativenumber` combinations). This is view-only state.
+//!
+//! If the viewport has a `wrap_marker`, it's drawn in its own column between the
+//! gutter and the text, on continuation rows only.
+
+use minui::Window;
+
+use super::{RenderSnapshot, TextViewport};
+
+/// How (and whether) line numbers are shown in the gutter.
+///
+/// Not user-selectable yet (that needs `:set`), so only the default is constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineNumberMode {
+    /// No gutter at all.
+    None,
+    /// Absolute 1-based line numbers (`:set number`).
+    #[default]
+    Absolute,
+    /// Distance from the cursor line; the cursor line shows 0 (`:set relativenumber`).
+    Relative,
+    /// Absolute on the cursor line, relative elsewhere (`:set number relativenumber`).
+    Hybrid,
+}
+
+impl LineNumberMode {
+    /// Gutter width for this mode; zero when numbers are hidden.
+    pub fn gutter_width(self, len_lines: usize) -> usize {
+        match self {
+            LineNumberMode::None => 0,
+            _ => gutter_width_for(len_lines),
+        }
+    }
+
+    /// The number to show for document line `line` given the cursor's line.
+    fn label(self, line: usize, cursor_line: usize) -> usize {
+        let distance = line.abs_diff(cursor_line);
+        match self {
+            LineNumberMode::None | LineNumberMode::Absolute => line + 1,
+            LineNumberMode::Relative => distance,
+            LineNumberMode::Hybrid if distance == 0 => line + 1,
+            LineNumberMode::Hybrid => distance,
+        }
+    }
+}
+
+/// Gutter width (in cells) needed to show every line number of a buffer with
+/// `len_lines` lines, including the separator space.
+pub fn gutter_width_for(len_lines: usize) -> usize {
+    let mut digits = 1;
+    let mut n = len_lines.max(1);
+    while n >= 10 {
+        n /= 10;
+        digits += 1;
+    }
+    digits + 1
+}
+
+/// Build the gutter text for every row of `snapshot`.
+///
+/// Each label is exactly `gutter_width` cells wide. A zero-width gutter (or
+/// `LineNumberMode::None`) yields empty labels.
+pub fn gutter_labels(
+    snapshot: &RenderSnapshot,
+    gutter_width: usize,
+    mode: LineNumberMode,
+    cursor_line: usize,
+) -> Vec<String> {
+    let number_width = gutter_width.saturating_sub(1);
+    snapshot
+        .rows
+        .iter()
+        .map(|row| match row {
+            _ if gutter_width == 0 || mode == LineNumberMode::None => String::new(),
+            row if row.continuation => " ".repeat(gutter_width),
+            row => format!("{:>number_width$} ", mode.label(row.line, cursor_line)),
+        })
+        .collect()
+}
+
+/// Draw a snapshot with line numbers in the gutter and text offset past it.
+///
+/// `cursor_line` is the document line of the cursor, used by the relative modes.
+pub fn draw_snapshot_with_gutter(
+    snapshot: &RenderSnapshot,
+    viewport: &TextViewport,
+    mode: LineNumberMode,
+    cursor_line: usize,
+    window: &mut dyn Window,
+) -> minui::Result<()> {
+    let labels = gutter_labels(snapshot, viewport.gutter_width, mode, cursor_line);
+    let (left, top) = (viewport.left, viewport.top);
+    let text_x = left + viewport.text_x() as u16;
+
+    for (row, (label, line)) in labels.iter().zip(&snapshot.lines).enumerate() {
+        let y = top + row as u16;
+        window.write_str(y, left, label)?;
+        if let Some(marker) = viewport.wrap_marker {
+            let marker = if snapshot.rows[row].continuation {
+                marker
+            } else {
+                ' '
+            };
+            window.write_str(y, left + viewport.gutter_width as u16, &marker.to_string())?;
+        }
+        window.write_str(y, text_x, line)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::{GraphemeCache, WrapMode, snapshot_lines_wrapped_cached};
+    use editor_core::TextBuffer;
+
+    #[test]
+    fn width_grows_with_digit_count() {
+        assert_eq!(gutter_width_for(0), 2);
+        assert_eq!(gutter_width_for(9), 2);
+        assert_eq!(gutter_width_for(10), 3);
+        assert_eq!(gutter_width_for(1234), 5);
+    }
+
+    #[test]
+    fn labels_for_three_line_buffer() {
+        let buffer = TextBuffer::from_str("one\ntwo\nthree");
+        let viewport = TextViewport {
+            scroll_x: 0,
+            scroll_y: 0,
+            left: 0,
+            top: 0,
+            width: 20,
+            height: 10,
+            gutter_width: gutter_width_for(buffer.len_lines()),
+            list_chars: None,
+            wrap_marker: None,
+            wrap_width: None,
+            wrap_mode: WrapMode::Word,
+        };
+        let snap = snapshot_lines_wrapped_cached(&buffer, &viewport, &mut GraphemeCache::new(8));
+
+        assert_eq!(
+            gutter_labels(&snap, viewport.gutter_width, LineNumberMode::Absolute, 0),
+            vec!["1 ", "2 ", "3 "]
+        );
+    }
+
+    #[test]
+    fn continuation_rows_get_blank_gutter() {
+        let buffer = TextBuffer::from_str("aaaa bbbb\nc");
+        let viewport = TextViewport {
+            scroll_x: 0,
+            scroll_y: 0,
+            left: 0,
+            top: 0,
+            width: 8,
+            height: 10,
+            gutter_width: 3,
+            list_chars: None,
+            wrap_marker: None,
+            wrap_width: None,
+            wrap_mode: WrapMode::Word,
+        };
+        let snap = snapshot_lines_wrapped_cached(&buffer, &viewport, &mut GraphemeCache::new(8));
+
+        assert_eq!(snap.lines, vec!["aaaa", "bbbb", "c"]);
+        assert_eq!(
+            gutter_labels(&snap, 3, LineNumberMode::Absolute, 0),
+            vec![" 1 ", "   ", " 2 "]
+        );
+    }
+
+    fn five_line_snapshot() -> RenderSnapshot {
+        RenderSnapshot::new(0, vec![String::new(); 5])
+    }
+
+    #[test]
+    fn relative_mode_counts_from_cursor() {
+        let labels = gutter_labels(&five_line_snapshot(), 2, LineNumberMode::Relative, 2);
+        assert_eq!(labels, vec!["2 ", "1 ", "0 ", "1 ", "2 "]);
+    }
+
+    #[test]
+    fn hybrid_mode_shows_absolute_on_cursor_line() {
+        let labels = gutter_labels(&five_line_snapshot(), 2, LineNumberMode::Hybrid, 2);
+        assert_eq!(labels, vec!["2 ", "1 ", "3 ", "1 ", "2 "]);
+    }
+
+    #[test]
+    fn none_mode_hides_gutter() {
+        assert_eq!(LineNumberMode::None.gutter_width(100), 0);
+        let labels = gutter_labels(&five_line_snapshot(), 0, LineNumberMode::None, 2);
+        assert!(labels.iter().all(String::is_empty));
+    }
+}
@@ -0,0 +1,364 @@
+//! Per-line display layout, shared by cursor and selection rendering.
+//!
+//! The snapshot only records where each document line *starts* on screen, so the
+//! row/column of anything within a line is recovered by re-wrapping just that line
+//! with the same rules the snapshot used (see `wrap_grapheme_stream`). That keeps the
+//! mapping exact for soft-wrapped lines without storing per-grapheme layout for
+//! every visible row.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use editor_core::TextBuffer;
+
+use super::{
+    RenderSnapshot, RopeGraphemes, TextViewport, count_wrapped_rows, grapheme_cells,
+    trailing_spaces_from, visible_glyphs, wrap_grapheme_stream,
+};
+
+/// How one document line is laid out on screen (after horizontal scroll).
+///
+/// Graphemes scrolled off the left edge aren't part of the layout at all, and
+/// neither is anything past what the caller needs: graphemes are streamed off
+/// the rope (like the streaming snapshot path) and wrapping stops once char
+/// column `until_col` and the first `max_rows` rows are covered. So a cursor
+/// near the start of a 10MB line only lays out a row or two.
+pub(super) struct LineLayout {
+    /// Char column of the first grapheme not hidden by horizontal scroll (the
+    /// line's length if it's scrolled out of view entirely).
+    first_col: usize,
+    /// The graphemes of each laid-out visual row. Spaces a word wrap broke at
+    /// or skipped fall between two rows.
+    rows: Vec<Vec<Placed>>,
+    /// Whether `rows` run to the end of the line.
+    complete: bool,
+    max_cells: usize,
+}
+
+/// One laid-out grapheme: where it sits in the line and how wide it's drawn.
+struct Placed {
+    col: usize,
+    chars: usize,
+    cells: usize,
+}
+
+impl Placed {
+    fn end(&self) -> usize {
+        self.col + self.chars
+    }
+}
+
+impl LineLayout {
+    pub(super) fn new(
+        buffer: &TextBuffer,
+        viewport: &TextViewport,
+        line: usize,
+        until_col: usize,
+        max_rows: usize,
+    ) -> Self {
+        let slice = buffer.rope().slice(buffer.line_char_range(line));
+        let max_cells = viewport.wrap_cells();
+
+        // Past `until_col`, a row can't take more than `max_cells` cells, so one
+        // grapheme beyond that settles where the row holding `until_col` ends.
+        let mut past = 0usize;
+        let mut cut = false;
+        let mut first_col = slice.len_chars();
+        let glyphs = visible_glyphs(slice, viewport)
+            .inspect(|g| first_col = first_col.min(g.col))
+            .take_while(|g| {
+                if g.col >= until_col {
+                    if past > max_cells {
+                        cut = true;
+                        return false;
+                    }
+                    past += grapheme_cells(g.as_ref());
+                }
+                true
+            });
+        // One extra row tells whether the last wanted one ends the line.
+        let wanted = max_rows.saturating_add(1);
+        let rows: Vec<Vec<Placed>> =
+            wrap_grapheme_stream(glyphs, max_cells.max(1), wanted, viewport.wrap_mode)
+                .into_iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|g| Placed {
+                            col: g.col,
+                            chars: g.chars,
+                            cells: grapheme_cells(g.as_ref()),
+                        })
+                        .collect()
+                })
+                .collect();
+
+        Self {
+            first_col,
+            complete: !cut && rows.len() < wanted,
+            rows,
+            max_cells,
+        }
+    }
+
+    /// Number of visual rows laid out: all of the line's if it ends within the
+    /// rows and column asked for.
+    pub(super) fn rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether visual `row` is the line's last one (so its end is the newline).
+    pub(super) fn is_last_row(&self, row: usize) -> bool {
+        self.complete && row + 1 == self.rows.len()
+    }
+
+    /// `(row within the line, cell column)` for char column `col`, or `None` if
+    /// it's hidden by horizontal scroll.
+    ///
+    /// A column on the space a row was broken at maps to the end of that row. A
+    /// column past the laid-out rows lands on the last of them (one past
+    /// `max_rows`, so it reads as off screen).
+    pub(super) fn locate(&self, col: usize) -> Option<(usize, usize)> {
+        if col < self.first_col {
+            return None;
+        }
+        let row = self
+            .rows
+            .iter()
+            .rposition(|r| r.first().is_some_and(|g| g.col <= col))
+            .unwrap_or(0);
+        let cells: usize = self.rows[row]
+            .iter()
+            .take_while(|g| g.end() <= col)
+            .map(|g| g.cells)
+            .sum();
+        Some((row, cells.min(self.max_cells.saturating_sub(1))))
+    }
+
+    /// Cells of visual `row` covered by the char column range `cols`.
+    ///
+    /// Returns `None` if the range doesn't touch the row.
+    pub(super) fn cell_range(&self, row: usize, cols: Range<usize>) -> Option<Range<usize>> {
+        let row = self.rows.get(row)?;
+
+        // Graphemes whose char span intersects `cols`.
+        let start_g = row.partition_point(|g| g.end() <= cols.start);
+        let end_g = row.partition_point(|g| g.col < cols.end);
+        if start_g >= end_g {
+            return None;
+        }
+        let start = cells(&row[..start_g]);
+        Some(start..start + cells(&row[start_g..end_g]))
+    }
+
+    /// Cells used by visual `row`.
+    pub(super) fn row_cells(&self, row: usize) -> usize {
+        self.rows.get(row).map_or(0, |r| cells(r))
+    }
+}
+
+fn cells(graphemes: &[Placed]) -> usize {
+    graphemes.iter().map(|g| g.cells).sum()
+}
+
+/// Visual rows `line` takes in `viewport`, the same count [`LineLayout::rows`]
+/// gives. An empty line is one row.
+///
+/// Graphemes are streamed straight off the rope and only row boundaries are
+/// counted, so nothing is allocated per line (beyond listchars glyphs).
+pub(super) fn wrapped_row_count(
+    buffer: &TextBuffer,
+    viewport: &TextViewport,
+    line: usize,
+) -> usize {
+    let slice = buffer.rope().slice(buffer.line_char_range(line));
+    let list = viewport
+        .list_chars
+        .as_ref()
+        .map(|list| (list, trailing_spaces_from(slice)));
+    let mut col = 0usize;
+    let mut skipped = 0usize;
+    let glyphs = RopeGraphemes::new(slice)
+        .map(|g| {
+            let at = col;
+            col += g.len_chars();
+            match list {
+                Some((list, trailing_from)) => list.display(Cow::from(g), at >= trailing_from),
+                None => Cow::from(g),
+            }
+        })
+        .skip_while(|g| {
+            let skip = skipped < viewport.scroll_x;
+            skipped += grapheme_cells(g);
+            skip
+        });
+    count_wrapped_rows(glyphs, viewport.wrap_cells().max(1), viewport.wrap_mode)
+}
+
+/// The document line containing visual (wrapped) row `visual_row`, and which of
+/// its rows that is.
+///
+/// Rows past the end of the document clamp to the last row of the last line.
+/// This walks every line up to the answer (O(lines)); nothing is cached yet, so
+/// put a generation-keyed index in front of it if it shows up in profiles.
+pub(super) fn visual_row_to_line(
+    buffer: &TextBuffer,
+    viewport: &TextViewport,
+    visual_row: usize,
+) -> (usize, usize) {
+    let mut first_row = 0usize;
+    let last_line = buffer.len_lines() - 1;
+    for line in 0..=last_line {
+        let rows = wrapped_row_count(buffer, viewport, line);
+        if visual_row < first_row + rows || line == last_line {
+            return (line, (visual_row - first_row).min(rows - 1));
+        }
+        first_row += rows;
+    }
+    unreachable!("a buffer always has at least one line")
+}
+
+/// The visual row where document `line` starts: the inverse of
+/// [`visual_row_to_line`] (also O(lines)).
+pub(super) fn line_to_visual_row(
+    buffer: &TextBuffer,
+    viewport: &TextViewport,
+    line: usize,
+) -> usize {
+    (0..buffer.clamp_line(line))
+        .map(|l| wrapped_row_count(buffer, viewport, l))
+        .sum()
+}
+
+/// Screen row (possibly negative, ie. above the top) where `line` starts, or
+/// `None` if the line isn't part of the snapshot.
+pub(super) fn line_screen_row(snapshot: &RenderSnapshot, line: usize) -> Option<isize> {
+    match snapshot
+        .rows
+        .iter()
+        .position(|row| row.line == line && !row.continuation)
+    {
+        Some(row) => Some(row as isize),
+        None if line == snapshot.first_line && !snapshot.lines.is_empty() => {
+            Some(-(snapshot.first_row_offset as isize))
+        }
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::{ListChars, WrapMode};
+
+    fn viewport(width: u16, wrap_mode: WrapMode) -> TextViewport {
+        TextViewport {
+            scroll_x: 0,
+            scroll_y: 0,
+            left: 0,
+            top: 0,
+            width,
+            height: 10,
+            gutter_width: 0,
+            list_chars: None,
+            wrap_marker: None,
+            wrap_width: None,
+            wrap_mode,
+        }
+    }
+
+    #[test]
+    fn wrapped_row_counts() {
+        let buffer = TextBuffer::from_str("short\naaa bbb ccc\n");
+        let word = viewport(5, WrapMode::Word);
+        assert_eq!(wrapped_row_count(&buffer, &word, 0), 1);
+        // "aaa" / "bbb" / "ccc"
+        assert_eq!(wrapped_row_count(&buffer, &word, 1), 3);
+        // "aaa b" / "bb cc" / "c"
+        assert_eq!(
+            wrapped_row_count(&buffer, &viewport(5, WrapMode::Char), 1),
+            3
+        );
+        // The empty last line.
+        assert_eq!(wrapped_row_count(&buffer, &word, 2), 1);
+    }
+
+    #[test]
+    fn wrapped_row_count_matches_the_line_layout() {
+        let buffer = TextBuffer::from_str("\tab  cd ef   \n漢字漢字 x y z\n");
+        for width in 1..12 {
+            for mode in [WrapMode::Word, WrapMode::Char] {
+                let mut vp = viewport(width, mode);
+                for (scroll_x, list_chars) in
+                    [(0, None), (3, None), (0, Some(ListChars::default()))]
+                {
+                    vp.scroll_x = scroll_x;
+                    vp.list_chars = list_chars;
+                    for line in 0..buffer.len_lines() {
+                        assert_eq!(
+                            wrapped_row_count(&buffer, &vp, line),
+                            LineLayout::new(&buffer, &vp, line, usize::MAX, usize::MAX).rows(),
+                            "line {line} at {width} cells, {mode:?}, {vp:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bounded_layouts_agree_with_the_full_one() {
+        let buffer = TextBuffer::from_str("aaa bbb  ccc\tdd 漢字 eeee ff");
+        for mode in [WrapMode::Word, WrapMode::Char] {
+            let vp = viewport(5, mode);
+            let full = LineLayout::new(&buffer, &vp, 0, usize::MAX, usize::MAX);
+            for col in 0..=buffer.line_len_chars(0) {
+                let (row, _) = full.locate(col).unwrap();
+                let bounded = LineLayout::new(&buffer, &vp, 0, col, row + 1);
+                assert_eq!(bounded.locate(col), full.locate(col), "{mode:?} col {col}");
+                for r in 0..=row {
+                    assert_eq!(bounded.cell_range(r, 0..col), full.cell_range(r, 0..col));
+                    assert_eq!(bounded.row_cells(r), full.row_cells(r));
+                }
+                assert_eq!(bounded.is_last_row(row), full.is_last_row(row));
+            }
+        }
+    }
+
+    #[test]
+    fn layout_stops_at_the_rows_and_column_asked_for() {
+        let buffer = TextBuffer::from_str(&"word ".repeat(200_000));
+        let vp = viewport(10, WrapMode::Word);
+
+        let near_start = LineLayout::new(&buffer, &vp, 0, 12, usize::MAX);
+        assert_eq!(near_start.locate(12), Some((1, 2)));
+        assert!(near_start.rows() <= 3);
+
+        // A column past the visible rows reads as the row after them.
+        let top_rows = LineLayout::new(&buffer, &vp, 0, 500_000, 2);
+        assert_eq!(top_rows.rows(), 3);
+        assert_eq!(top_rows.locate(500_000).map(|(row, _)| row), Some(2));
+        assert!(!top_rows.is_last_row(2));
+    }
+
+    #[test]
+    fn visual_rows_map_to_lines_and_back() {
+        // Rows: 0 "one" | 1 "aaa", 2 "bbb", 3 "ccc" | 4 "two"
+        let buffer = TextBuffer::from_str("one\naaa bbb ccc\ntwo");
+        let vp = viewport(5, WrapMode::Word);
+        let map = |row| visual_row_to_line(&buffer, &vp, row);
+
+        assert_eq!(map(0), (0, 0));
+        assert_eq!(map(1), (1, 0));
+        assert_eq!(map(2), (1, 1)); // the middle of the wrapped line
+        assert_eq!(map(4), (2, 0));
+        assert_eq!(map(99), (2, 0));
+
+        assert_eq!(line_to_visual_row(&buffer, &vp, 0), 0);
+        assert_eq!(line_to_visual_row(&buffer, &vp, 1), 1);
+        assert_eq!(line_to_visual_row(&buffer, &vp, 2), 4);
+        assert_eq!(
+            line_to_visual_row(&buffer, &viewport(80, WrapMode::Word), 2),
+            2
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Visible whitespace (Vim's `listchars`).
+//!
+//! Substitution happens per grapheme before wrapping, and every replacement takes
+//! exactly as many cells as the whitespace it stands for, so wrapping, horizontal
+//! scroll and cursor placement all line up with the plain rendering.
+
+use std::borrow::Cow;
+
+use super::grapheme_cells;
+
+/// Glyphs used to show whitespace. View-only: the buffer is never touched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListChars {
+    /// Drawn in the first cell of a tab.
+    pub tab: char,
+    /// Fills the rest of a tab's cells.
+    pub tab_fill: char,
+    /// Replaces each space after the last non-space grapheme of a line.
+    pub trail: char,
+}
+
+impl Default for ListChars {
+    fn default() -> Self {
+        Self {
+            tab: '→',
+            tab_fill: ' ',
+            trail: '·',
+        }
+    }
+}
+
+impl ListChars {
+    /// Display form of one grapheme; `trailing` says it's part of the line's
+    /// trailing whitespace.
+    pub(super) fn display<'a>(&self, g: Cow<'a, str>, trailing: bool) -> Cow<'a, str> {
+        match g.as_ref() {
+            "\t" => {
+                let fill = grapheme_cells("\t").saturating_sub(1);
+                let mut out = String::with_capacity(fill + 4);
+                out.push(self.tab);
+                out.extend(std::iter::repeat_n(self.tab_fill, fill));
+                Cow::Owned(out)
+            }
+            " " if trailing => Cow::Owned(self.trail.to_string()),
+            _ => g,
+        }
+    }
+
+    /// Display form of a whole line's graphemes.
+    pub(super) fn apply<'a, G: AsRef<str>>(&self, graphemes: &'a [G]) -> Vec<Cow<'a, str>> {
+        let trailing_from = graphemes
+            .iter()
+            .rposition(|g| g.as_ref() != " ")
+            .map_or(0, |i| i + 1);
+        graphemes
+            .iter()
+            .enumerate()
+            .map(|(i, g)| self.display(Cow::Borrowed(g.as_ref()), i >= trailing_from))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::{GraphemeCache, TextViewport, WrapMode, snapshot_lines_wrapped_cached};
+    use editor_core::TextBuffer;
+
+    fn render(text: &str, scroll_x: usize) -> Vec<String> {
+        let buffer = TextBuffer::from_str(text);
+        let viewport = TextViewport {
+            scroll_x,
+            scroll_y: 0,
+            left: 0,
+            top: 0,
+            width: 20,
+            height: 5,
+            gutter_width: 0,
+            list_chars: Some(ListChars::default()),
+            wrap_marker: None,
+            wrap_width: None,
+            wrap_mode: WrapMode::Word,
+        };
+        snapshot_lines_wrapped_cached(&buffer, &viewport, &mut GraphemeCache::new(8)).lines
+    }
+
+    #[test]
+    fn leading_tab_and_trailing_spaces() {
+        assert_eq!(render("\tx = 1  ", 0), vec!["→   x = 1··"]);
+        // Inner spaces stay blank; an all-space line is all trailing.
+        assert_eq!(render("a b\n   ", 0), vec!["a b", "···"]);
+    }
+
+    #[test]
+    fn glyphs_keep_cell_widths() {
+        let glyphs = ListChars::default().apply(&["\t", "a", " "]);
+        let cells: Vec<usize> = glyphs.iter().map(|g| grapheme_cells(g)).collect();
+        assert_eq!(cells, vec![4, 1, 1]);
+        // Scrolling past the tab still lands on the text after it.
+        assert_eq!(render("\tab ", 2), vec!["ab·"]);
+    }
+}
@@ -14,63 +14,353 @@
 //!   every frame when you are not editing the buffer.
 //!
 //! Future work:
-//! - Cursor rendering, selection, and incremental updates.
+//! - Incremental updates.
 
-use editor_core::TextBuffer;
+use std::borrow::Cow;
+use std::ops::Range;
+
+use editor_core::text::hash64;
+use editor_core::{Pos, TextBuffer};
 use minui::{Window, cell_width};
+use ropey::RopeSlice;
 use unicode_segmentation::UnicodeSegmentation;
 
+mod cursor;
+mod graphemes;
+mod gutter;
+mod layout;
+mod list_chars;
+mod panes;
+mod scroll;
+mod search;
+mod selection;
+mod status;
+
+use layout::visual_row_to_line;
+
+pub use cursor::draw_cursor;
+pub use graphemes::RopeGraphemes;
+pub use gutter::{LineNumberMode, draw_snapshot_with_gutter};
+pub use list_chars::ListChars;
+pub use panes::{Layout, Pane};
+pub use scroll::{clamp_scroll, follow_cursor};
+pub use search::draw_search_matches;
+pub use selection::draw_selection;
+pub use status::{StatusLine, draw_status_line};
+
 /// Viewport parameters for rendering a slice of the buffer.
 ///
-/// `scroll_x` is measured in **grapheme clusters**.
+/// `scroll_x` is measured in **cells**: graphemes are skipped until that many
+/// cells have scrolled off. A grapheme straddling the offset (typically a tab) is
+/// skipped whole, so the view starts at its far edge (see [`skip_cells`]).
+///
+/// `gutter_width` cells on the left are reserved for line numbers; text is laid
+/// out in the remaining [`TextViewport::text_width`] cells.
+///
+/// `list_chars`, when set, draws tabs and trailing spaces as visible glyphs.
+///
+/// `wrap_marker`, when set, reserves one more column right of the gutter where
+/// soft-wrap continuation rows show the marker (e.g. `↪`).
+///
+/// `wrap_mode` picks where soft-wrapped rows break (see [`WrapMode`]).
+///
+/// `left` and `top` are the screen cell of the viewport's top-left corner; they're
+/// only non-zero for a split pane that doesn't start at the window's corner.
 ///
 /// NOTE: once soft-wrapping is enabled, `scroll_y` will be a bit more tricky. For wrapped
 /// rendering this interprets `scroll_y` as a **visual row offset** (wrapped rows),
 /// not as a rope line index.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct TextViewport {
     pub scroll_x: usize,
     pub scroll_y: usize,
+    pub left: u16,
+    pub top: u16,
     pub width: u16,
     pub height: u16,
+    pub gutter_width: usize,
+    pub list_chars: Option<ListChars>,
+    pub wrap_marker: Option<char>,
+    /// Soft-wrap at this many text cells instead of the window width (eg. 80 for
+    /// prose). Rows never get wider than the window, so a wider `wrap_width`
+    /// has no effect.
+    pub wrap_width: Option<u16>,
+    pub wrap_mode: WrapMode,
+}
+
+/// How a line too long for the text area is broken into rows.
+///
+/// Not user-selectable yet (that needs `:set`), so only the default is constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    /// Break at the last space that fits; that space isn't drawn (good for prose).
+    #[default]
+    Word,
+    /// Fill each row to the cell limit, breaking mid-word and keeping every
+    /// space (good for code, where whitespace matters).
+    Char,
+}
+
+impl WrapMode {
+    /// Whether spaces at the start of a continuation row are dropped.
+    ///
+    /// Nice for prose (the space a row broke at would otherwise start the next
+    /// row), but in code it would eat indentation, so only word wrap does it.
+    pub fn skips_leading_spaces(self) -> bool {
+        self == WrapMode::Word
+    }
 }
 
 impl TextViewport {
-    /// Build a viewport using the current window size.
+    /// Build a viewport using the current window size (no gutter).
+    ///
+    /// Currently unused; the editor sizes its viewports from the pane layout.
+    #[allow(dead_code)]
     pub fn from_window(window: &dyn Window, scroll_x: usize, scroll_y: usize) -> Self {
         let (width, height) = window.get_size();
         Self {
             scroll_x,
             scroll_y,
+            left: 0,
+            top: 0,
             width,
             height,
+            gutter_width: 0,
+            list_chars: None,
+            wrap_marker: None,
+            wrap_width: None,
+            wrap_mode: WrapMode::Word,
         }
     }
+
+    /// Screen column where text starts: after the gutter and wrap-marker column.
+    pub fn text_x(&self) -> usize {
+        self.gutter_width + usize::from(self.wrap_marker.is_some())
+    }
+
+    /// Cells available for text once the gutter is taken out.
+    pub fn text_width(&self) -> usize {
+        (self.width as usize).saturating_sub(self.text_x())
+    }
+
+    /// Cells per soft-wrapped row: `wrap_width`, clipped to the text area.
+    pub fn wrap_cells(&self) -> usize {
+        let text_width = self.text_width();
+        self.wrap_width
+            .map_or(text_width, |w| text_width.min(w as usize))
+    }
 }
 
 /// Snapshot of visible text lines for the current frame.
 ///
 /// `first_line` is the document line index corresponding to `lines[0]`.
+///
+/// `rows` runs parallel to `lines`, recording which document line each visual row
+/// came from, whether it's a soft-wrap continuation, and enough layout to map a
+/// screen cell back to a document position (see [`RenderSnapshot::screen_to_pos`]).
+///
+/// `first_row_offset` is how many wrapped rows of `first_line` were scrolled off
+/// the top (0 unless `scroll_y` lands mid-line).
 #[derive(Debug, Clone)]
 pub struct RenderSnapshot {
-    #[allow(dead_code)]
     pub first_line: usize,
     pub lines: Vec<String>,
+    pub rows: Vec<SnapshotRow>,
+    pub first_row_offset: usize,
+}
+
+/// Where one visual row of a [`RenderSnapshot`] came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotRow {
+    /// Document line index.
+    pub line: usize,
+    /// True for the second and later rows of a soft-wrapped line.
+    pub continuation: bool,
+    /// Char column (within `line`) of the row's first grapheme.
+    pub start_col: usize,
+    /// `(cells, chars)` for each grapheme drawn on the row, in order.
+    ///
+    /// Chars are counted in the document, so a tab drawn as several glyphs is
+    /// still one char.
+    pub segments: Vec<(usize, usize)>,
 }
 
 impl RenderSnapshot {
+    /// Snapshot with one visual row per document line, starting at `first_line`.
     pub fn new(first_line: usize, lines: Vec<String>) -> Self {
-        Self { first_line, lines }
+        let rows = lines
+            .iter()
+            .enumerate()
+            .map(|(i, text)| SnapshotRow {
+                line: first_line + i,
+                continuation: false,
+                start_col: 0,
+                segments: text
+                    .graphemes(true)
+                    .map(|g| (grapheme_cells(g), g.chars().count()))
+                    .collect(),
+            })
+            .collect();
+        Self {
+            first_line,
+            lines,
+            rows,
+            first_row_offset: 0,
+        }
+    }
+
+    /// Document position drawn at visual `row`, `col` cells into the text area
+    /// (ie. not counting the gutter).
+    ///
+    /// A cell inside a wide grapheme maps to that grapheme's start; a cell past the
+    /// end of the row maps to the end of the row's text. Returns `None` for rows
+    /// outside the snapshot.
+    pub fn screen_to_pos(&self, row: usize, col: usize) -> Option<Pos> {
+        let info = self.rows.get(row)?;
+        let mut x = 0usize;
+        let mut char_col = info.start_col;
+        for &(cells, chars) in &info.segments {
+            if col < x + cells {
+                break;
+            }
+            x += cells;
+            char_col += chars;
+        }
+        Some(Pos::new(info.line, char_col))
+    }
+}
+
+/// One grapheme as it's drawn, plus where it came from in its document line.
+struct Glyph<'a> {
+    text: Cow<'a, str>,
+    /// Char column of the source grapheme.
+    col: usize,
+    /// Chars in the source grapheme (a tab drawn as `→   ` is still one char).
+    chars: usize,
+}
+
+impl AsRef<str> for Glyph<'_> {
+    fn as_ref(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Glyphs for a line's cached graphemes, with `list` substitutions applied.
+fn line_glyphs<'a>(graphemes: &'a [Box<str>], list: Option<&ListChars>) -> Vec<Glyph<'a>> {
+    let texts: Vec<Cow<'a, str>> = match list {
+        Some(list) => list.apply(graphemes),
+        None => graphemes
+            .iter()
+            .map(|g| Cow::Borrowed(g.as_ref()))
+            .collect(),
+    };
+    let mut col = 0usize;
+    graphemes
+        .iter()
+        .zip(texts)
+        .map(|(g, text)| {
+            let chars = g.chars().count();
+            col += chars;
+            Glyph {
+                text,
+                col: col - chars,
+                chars,
+            }
+        })
+        .collect()
+}
+
+/// Collects wrapped rows for a snapshot, discarding the first `skip` of them
+/// (the rows above `scroll_y`).
+struct RowSink {
+    skip: usize,
+    max_rows: usize,
+    first_line: Option<usize>,
+    lines: Vec<String>,
+    rows: Vec<SnapshotRow>,
+    // (line, rows of it skipped so far)
+    skipped_in_line: (usize, usize),
+}
+
+impl RowSink {
+    fn new(skip: usize, max_rows: usize) -> Self {
+        Self {
+            skip,
+            max_rows,
+            first_line: None,
+            lines: Vec::with_capacity(max_rows),
+            rows: Vec::with_capacity(max_rows),
+            skipped_in_line: (0, 0),
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.lines.len() >= self.max_rows
+    }
+
+    /// How many more rows could still be skipped or shown.
+    fn rows_wanted(&self) -> usize {
+        self.skip + (self.max_rows - self.lines.len())
+    }
+
+    /// Record one visual row of document line `line_idx`.
+    ///
+    /// `glyphs` are the row's graphemes; `end_col` is the line's length in chars,
+    /// used as the start of a row with nothing on it.
+    fn push(&mut self, line_idx: usize, first_row: bool, glyphs: &[Glyph], end_col: usize) {
+        if self.skip > 0 {
+            self.skip -= 1;
+            if first_row {
+                self.skipped_in_line = (line_idx, 0);
+            }
+            self.skipped_in_line.1 += 1;
+            return;
+        }
+        if self.is_full() {
+            return;
+        }
+        self.first_line.get_or_insert(line_idx);
+        self.lines.push(glyphs.iter().map(AsRef::as_ref).collect());
+        self.rows.push(SnapshotRow {
+            line: line_idx,
+            continuation: !first_row,
+            start_col: glyphs.first().map_or(end_col, |g| g.col),
+            segments: glyphs
+                .iter()
+                .map(|g| (grapheme_cells(g.as_ref()), g.chars))
+                .collect(),
+        });
+    }
+
+    fn finish(self) -> RenderSnapshot {
+        let first_line = self.first_line.unwrap_or(0);
+        let first_row_offset = match self.rows.first() {
+            Some(row) if row.continuation && self.skipped_in_line.0 == first_line => {
+                self.skipped_in_line.1
+            }
+            _ => 0,
+        };
+        RenderSnapshot {
+            first_line,
+            lines: self.lines,
+            rows: self.rows,
+            first_row_offset,
+        }
     }
 }
 
 /// Cache for grapheme boundary segmentation.
 ///
-/// This is a simple LRU-ish cache keyed by `(line_idx, line_hash)`.
-/// It’s designed for the current “read-only rendering” stage where the buffer
-/// doesn’t change during runtime (so the cache stays hot).
+/// This is a simple LRU-ish cache keyed by line index. Each entry remembers the
+/// buffer generation it was computed at (see `TextBuffer::generation`):
+/// - if the generation still matches, the entry is returned without touching the
+///   line text at all (the common "nothing was edited" case)
+/// - if it doesn't, the line is FNV-hashed as a fallback, so lines untouched by an
+///   edit elsewhere in the buffer stay cached
 ///
-/// When editing is added, the caller can invalidate the cache when a line changes.
+/// The editor also drops the entries around each edit directly via
+/// [`GraphemeCache::invalidate_line`] and [`GraphemeCache::invalidate_from`].
 #[derive(Debug, Default)]
 pub struct GraphemeCache {
     max_entries: usize,
@@ -81,6 +371,7 @@ pub struct GraphemeCache {
 #[derive(Debug, Clone)]
 struct CacheEntry {
     line_idx: usize,
+    generation: u64,
     hash: u64,
     graphemes: Vec<Box<str>>,
     last_used_tick: u64,
@@ -103,32 +394,53 @@ impl GraphemeCache {
         self.tick = 0;
     }
 
+    /// Drop the cached entry for a single line (eg. after editing within it).
+    pub fn invalidate_line(&mut self, line_idx: usize) {
+        self.entries.retain(|e| e.line_idx != line_idx);
+    }
+
+    /// Drop cached entries for `line_idx` and every line after it.
+    ///
+    /// Use this when an edit inserts or removes lines, since the following line
+    /// indices no longer refer to the same text.
+    pub fn invalidate_from(&mut self, line_idx: usize) {
+        self.entries.retain(|e| e.line_idx < line_idx);
+    }
+
     /// Get grapheme slices for `line_text`.
     ///
+    /// `generation` should be the buffer's current `generation()`; when it matches
+    /// the cached entry, `line_text` is not inspected.
+    ///
     /// Returned as a slice of `Box<str>` stored in the cache.
     pub fn graphemes_for_line<'a>(
         &'a mut self,
         line_idx: usize,
+        generation: u64,
         line_text: &str,
     ) -> &'a [Box<str>] {
         self.tick = self.tick.wrapping_add(1);
-        let h = hash64(line_text);
 
-        if let Some(pos) = self
-            .entries
-            .iter()
-            .position(|e| e.line_idx == line_idx && e.hash == h)
-        {
-            // Bump usage
-            self.entries[pos].last_used_tick = self.tick;
+        if let Some(pos) = self.entries.iter().position(|e| e.line_idx == line_idx) {
+            let tick = self.tick;
+            let entry = &mut self.entries[pos];
+            entry.last_used_tick = tick;
+
+            if entry.generation != generation {
+                // The buffer changed somewhere; only re-segment if this line did.
+                let h = hash64(line_text);
+                if entry.hash != h {
+                    entry.hash = h;
+                    entry.graphemes = segment_graphemes(line_text);
+                }
+                entry.generation = generation;
+            }
+
             return &self.entries[pos].graphemes;
         }
 
         // Miss: segment and insert.
-        let graphemes: Vec<Box<str>> = line_text
-            .graphemes(true)
-            .map(|g| g.to_owned().into_boxed_str())
-            .collect();
+        let graphemes = segment_graphemes(line_text);
 
         if self.entries.len() >= self.max_entries {
             // Evict least recently used
@@ -144,7 +456,8 @@ impl GraphemeCache {
 
         self.entries.push(CacheEntry {
             line_idx,
-            hash: h,
+            generation,
+            hash: hash64(line_text),
             graphemes,
             last_used_tick: self.tick,
         });
@@ -155,7 +468,18 @@ impl GraphemeCache {
     }
 }
 
+/// Split a line into owned grapheme clusters for caching.
+fn segment_graphemes(line_text: &str) -> Vec<Box<str>> {
+    line_text
+        .graphemes(true)
+        .map(|g| g.to_owned().into_boxed_str())
+        .collect()
+}
+
 /// Draw a snapshot into the window.
+///
+/// Currently unused; `main.rs` draws through [`draw_snapshot_with_gutter`].
+#[allow(dead_code)]
 pub fn draw_snapshot(snapshot: &RenderSnapshot, window: &mut dyn Window) -> minui::Result<()> {
     for (row, line) in snapshot.lines.iter().enumerate() {
         window.write_str(row as u16, 0, line)?;
@@ -163,87 +487,120 @@ pub fn draw_snapshot(snapshot: &RenderSnapshot, window: &mut dyn Window) -> minu
     Ok(())
 }
 
+/// Lines longer than this (in chars) skip the grapheme cache and are segmented
+/// lazily straight from the rope, so only the visible part is ever touched.
+const STREAMING_LINE_CHARS: usize = 16 * 1024;
+
 /// Build a *soft-wrapped* snapshot of visible rows.
 ///
 /// - Soft wrap is visual-only: it does not modify the underlying buffer.
-/// - Horizontal scrolling is applied first (in grapheme units), then wrap the
-///   remaining content into rows of at most `viewport.width` cells.
+/// - Horizontal scrolling is applied first (in cells), then wrap the
+///   remaining content into rows of at most `viewport.wrap_cells()` cells.
 /// - `viewport.scroll_y` is interpreted as a visual row offset into the wrapped
 ///   row stream.
+/// - Very long lines (see `STREAMING_LINE_CHARS`) bypass the cache and are
+///   segmented from a `RopeSlice` only until enough rows exist.
+///
+/// A `scroll_y` past the last row shows the last row rather than nothing.
 ///
-/// TODO:
-/// - For now this still allocates `String` per *source line* via `line_string`.
-///   For very large single-line files, that's still expensive; later, this should
-///   avoid allocating the full line when we only need a window into it.
+/// NOTE: the top line is found with `visual_row_to_line`, which only counts the
+/// rows above `scroll_y` instead of building them, but still walks every line
+/// before it.
 pub fn snapshot_lines_wrapped_cached(
     buffer: &TextBuffer,
     viewport: &TextViewport,
     cache: &mut GraphemeCache,
 ) -> RenderSnapshot {
-    let max_cells = viewport.width as usize;
+    let max_cells = viewport.wrap_cells();
     let max_rows = viewport.height as usize;
 
     if max_cells == 0 || max_rows == 0 {
         return RenderSnapshot::new(0, Vec::new());
     }
 
-    // Generate wrapped rows for the whole document, skipping until scroll_y.
-    let mut skipped_rows = 0usize;
-    let mut out_rows: Vec<String> = Vec::with_capacity(max_rows);
+    // Start at the line holding row scroll_y, skipping its rows above that.
+    let (top_line, row_in_line) = visual_row_to_line(buffer, viewport, viewport.scroll_y);
+    let mut sink = RowSink::new(row_in_line, max_rows);
 
-    // Start from a line that could contribute to visible rows after scrolling.
-    // This optimization avoids iterating through all lines when scroll_y is large.
-    let start_line_estimate = if viewport.scroll_x == 0 {
-        // When not horizontally scrolled, estimate starting line by scroll_y
-        viewport.scroll_y.min(buffer.len_lines())
-    } else {
-        // With horizontal scroll, lines might wrap differently, start from beginning
-        0
-    };
-
-    for line_idx in start_line_estimate..buffer.len_lines() {
+    for line_idx in top_line..buffer.len_lines() {
+        if sink.is_full() {
+            break;
         }
 
-        while !remaining.is_empty() {
-            if out_rows.len() >= max_rows {
-                break;
+        if buffer.line_len_chars(line_idx) > STREAMING_LINE_CHARS {
+            // Only wrap as many rows as we could still skip or show.
+            let slice = buffer.rope().slice(buffer.line_char_range(line_idx));
+            let glyphs = visible_glyphs(slice, viewport);
+            let rows =
+                wrap_grapheme_stream(glyphs, max_cells, sink.rows_wanted(), viewport.wrap_mode);
+            for (i, row) in rows.iter().enumerate() {
+                sink.push(line_idx, i == 0, row, slice.len_chars());
             }
+            continue;
+        }
 
-            // Consume up to `max_cells` worth of graphemes, preferring to wrap on spaces.
-            // Ensure forward progress even if a single grapheme is wider than the viewport.
-            let consumed = if consumed == 0 && !remaining.is_empty() {
-                1
-            } else {
-                consumed
-            };
-            // Ensure forward progress even if a single grapheme is wider than the viewport.
-            let consumed = if consumed == 0 {
-                1.min(remaining.len())
-            } else {
-                consumed
-            };
+        // Rope -> String allocation for the line (no trailing '\n').
+        let line_text = buffer.line_string(line_idx);
+        let graphemes = cache.graphemes_for_line(line_idx, buffer.generation(), &line_text);
 
-            if skipped_rows < viewport.scroll_y {
-                skipped_rows += 1;
-            } else {
-                out_rows.push(row);
-            }
+        let glyphs = line_glyphs(graphemes, viewport.list_chars.as_ref());
+        push_wrapped_line(&mut sink, line_idx, &glyphs, viewport);
+    }
 
-            remaining = &remaining[consumed..];
+    sink.finish()
+}
 
-            // Skip leading spaces on the next visual row.
-            while let Some(g) = remaining.first() {
-                if g.as_ref() == " " {
-                    remaining = &remaining[1..];
-                } else {
-                    break;
-                }
-            }
+/// A line's glyphs (listchars applied) streamed straight off the rope, starting
+/// after the graphemes horizontal scroll hides.
+fn visible_glyphs<'a>(
+    slice: RopeSlice<'a>,
+    viewport: &TextViewport,
+) -> impl Iterator<Item = Glyph<'a>> + use<'a> {
+    let list = viewport
+        .list_chars
+        .map(|list| (list, trailing_spaces_from(slice)));
+    let scroll_x = viewport.scroll_x;
+    let mut char_idx = 0usize;
+    let mut skipped = 0usize;
+    RopeGraphemes::new(slice)
+        .map(move |g| {
+            let col = char_idx;
+            let chars = g.len_chars();
+            char_idx += chars;
+            let text = match list {
+                Some((list, trailing_from)) => list.display(Cow::from(g), col >= trailing_from),
+                None => Cow::from(g),
+            };
+            Glyph { text, col, chars }
+        })
+        .skip_while(move |g| {
+            let skip = skipped < scroll_x;
+            skipped += grapheme_cells(g.as_ref());
+            skip
+        })
+}
+
+/// Scroll and word-wrap one line's graphemes, pushing its rows into `sink`.
+///
+/// Empty (or fully scrolled-past) lines still occupy one visual row.
+fn push_wrapped_line(
+    sink: &mut RowSink,
+    line_idx: usize,
+    glyphs: &[Glyph],
+    viewport: &TextViewport,
+) {
+    let end_col = glyphs.last().map_or(0, |g| g.col + g.chars);
+    let visible = &glyphs[skip_cells(glyphs, viewport.scroll_x)..];
+
+    for (i, span) in wrap_row_spans(visible, viewport.wrap_cells(), viewport.wrap_mode)
+        .into_iter()
+        .enumerate()
+    {
+        if sink.is_full() {
+            break;
         }
+        sink.push(line_idx, i == 0, &visible[span], end_col);
     }
-
-    // first_line is not super meaningful for wrapped mode yet so keep as 0 for now.
-    RenderSnapshot::new(0, out_rows)
 }
 
 /// Build a grapheme-aware + cell-width-clipped snapshot of visible lines.
@@ -261,7 +618,7 @@ pub fn snapshot_lines_cached(
     let first_line = viewport.scroll_y;
     let last_line = first_line.saturating_add(viewport.height as usize);
 
-    let max_cells = viewport.width as usize;
+    let max_cells = viewport.text_width();
 
     for line_idx in first_line..last_line {
         if line_idx >= buffer.len_lines() {
@@ -271,10 +628,9 @@ pub fn snapshot_lines_cached(
         // Rope -> String allocation for the line (no trailing '\n').
         let line_text = buffer.line_string(line_idx);
 
-        let graphemes = cache.graphemes_for_line(line_idx, &line_text);
+        let graphemes = cache.graphemes_for_line(line_idx, buffer.generation(), &line_text);
 
-        // Horizontal scroll is in grapheme units.
-        let start_g = viewport.scroll_x.min(graphemes.len());
+        let start_g = skip_cells(graphemes, viewport.scroll_x);
 
         let visible = clip_graphemes_to_cells(&graphemes[start_g..], max_cells);
         lines.push(visible);
@@ -290,7 +646,7 @@ pub fn snapshot_lines_uncached(buffer: &TextBuffer, viewport: &TextViewport) ->
     let first_line = viewport.scroll_y;
     let last_line = first_line.saturating_add(viewport.height as usize);
 
-    let max_cells = viewport.width as usize;
+    let max_cells = viewport.text_width();
 
     for line_idx in first_line..last_line {
         if line_idx >= buffer.len_lines() {
@@ -300,7 +656,7 @@ pub fn snapshot_lines_uncached(buffer: &TextBuffer, viewport: &TextViewport) ->
         let line_text = buffer.line_string(line_idx);
         let graphemes: Vec<&str> = line_text.graphemes(true).collect();
 
-        let start_g = viewport.scroll_x.min(graphemes.len());
+        let start_g = skip_cells(&graphemes, viewport.scroll_x);
         let visible = clip_graphemes_to_cells_ref(&graphemes[start_g..], max_cells);
 
         lines.push(visible);
@@ -319,6 +675,55 @@ pub fn snapshot_lines(buffer: &TextBuffer, viewport: &TextViewport) -> RenderSna
     snapshot_lines_uncached(buffer, viewport)
 }
 
+/// Char index where `slice`'s run of trailing spaces starts (its length if there
+/// are none). Scans back from the end, so it only touches the trailing spaces.
+fn trailing_spaces_from(slice: RopeSlice) -> usize {
+    (0..slice.len_chars())
+        .rev()
+        .find(|&i| slice.char(i) != ' ')
+        .map_or(0, |i| i + 1)
+}
+
+/// Terminal cells taken by one grapheme (tabs count as 4).
+fn grapheme_cells(g: &str) -> usize {
+    cell_width(g, minui::prelude::TabPolicy::Fixed(4)) as usize
+}
+
+/// The graphemes of a drawn row that lie entirely within `cells`, padded with
+/// spaces where `cells` runs past the end of the row.
+///
+/// Used to re-draw part of a row in a highlight color.
+fn row_text_in_cells(row: &str, cells: Range<usize>) -> String {
+    let mut text = String::new();
+    let mut x = 0usize;
+    for g in row.graphemes(true) {
+        let w = grapheme_cells(g);
+        if x >= cells.start && x + w <= cells.end {
+            text.push_str(g);
+        }
+        x += w;
+    }
+    for _ in x.max(cells.start)..cells.end {
+        text.push(' ');
+    }
+    text
+}
+
+/// Index of the first grapheme starting at or after `cells` cells into a line.
+///
+/// This is how `scroll_x` (in cells) becomes a grapheme offset. A grapheme that
+/// straddles the offset is skipped whole rather than drawn cut in half.
+fn skip_cells<G: AsRef<str>>(graphemes: &[G], cells: usize) -> usize {
+    let mut x = 0usize;
+    for (i, g) in graphemes.iter().enumerate() {
+        if x >= cells {
+            return i;
+        }
+        x += grapheme_cells(g.as_ref());
+    }
+    graphemes.len()
+}
+
 /// Clip cached graphemes (`Box<str>`) to a maximum number of terminal cells.
 ///
 /// - Does **not** split graphemes.
@@ -354,71 +759,238 @@ fn clip_graphemes_to_cells(graphemes: &[Box<str>], max_cells: usize) -> String {
     out
 }
 
-/// Take as many graphemes as fit within `max_cells`, returning:
-/// - the concatenated row string
-/// - the number of graphemes consumed
+/// How many graphemes fit within `max_cells`.
 ///
 /// This does not split graphemes and stops before the first non-fitting grapheme.
-fn take_graphemes_by_cells(graphemes: &[Box<str>], max_cells: usize) -> (String, usize) {
-    if max_cells == 0 || graphemes.is_empty() {
-        return (String::new(), 0);
-    }
-
-    let mut out = String::new();
+/// Only counts: nothing is copied, so row layout can be measured without
+/// building row strings.
+fn take_graphemes_by_cells<G: AsRef<str>>(graphemes: &[G], max_cells: usize) -> usize {
     let mut used_cells = 0usize;
     let mut consumed = 0usize;
 
     for g in graphemes {
-        let w = cell_width(g, minui::prelude::TabPolicy::Fixed(4)) as usize;
-
+        if used_cells >= max_cells {
+            break;
+        }
+        let w = grapheme_cells(g.as_ref());
         if w > 0 && used_cells + w > max_cells {
             break;
         }
-
-        out.push_str(g);
         used_cells = used_cells.saturating_add(w);
         consumed += 1;
+    }
 
-        if used_cells >= max_cells {
-            break;
+    consumed
+}
+
+/// Like `take_graphemes_by_cells`, but prefers wrapping on spaces within the chunk.
+///
+/// Returns the number of graphemes consumed from the input, including the space
+/// the row was broken at (which isn't drawn).
+fn take_graphemes_by_cells_word_wrap<G: AsRef<str>>(graphemes: &[G], max_cells: usize) -> usize {
+    let consumed = take_graphemes_by_cells(graphemes, max_cells);
+    // Nothing to wrap if it's empty or the rest of the line fits.
+    if consumed == 0 || consumed == graphemes.len() {
+        return consumed;
+    }
+
+    // Cut at the last space if possible, otherwise hard wrap at cell boundary.
+    // Consume through the space so the next row starts after it.
+    graphemes[..consumed]
+        .iter()
+        .rposition(|g| g.as_ref() == " ")
+        .map_or(consumed, |space_idx| space_idx + 1)
+}
+
+/// Like `take_graphemes_by_cells`, but always consumes at least one grapheme.
+///
+/// This is the `WrapMode::Char` row breaker: rows are filled right up to
+/// `max_cells` and spaces are kept like any other grapheme. A single grapheme
+/// wider than `max_cells` gets a row of its own rather than stalling the wrap.
+fn take_graphemes_by_cells_hard<G: AsRef<str>>(graphemes: &[G], max_cells: usize) -> usize {
+    take_graphemes_by_cells(graphemes, max_cells).max(usize::from(!graphemes.is_empty()))
+}
+
+/// How many graphemes the next row takes under `mode`.
+fn take_row<G: AsRef<str>>(graphemes: &[G], max_cells: usize, mode: WrapMode) -> usize {
+    match mode {
+        WrapMode::Word => take_graphemes_by_cells_word_wrap(graphemes, max_cells),
+        WrapMode::Char => take_graphemes_by_cells_hard(graphemes, max_cells),
+    }
+}
+
+/// Grapheme index ranges covered by each visual row when `graphemes` is
+/// wrapped to `max_cells`.
+///
+/// Mirrors the row loop in [`snapshot_lines_wrapped_cached`] (including, for word
+/// wrap, skipping leading spaces on continuation rows, which fall between two
+/// spans), but only records boundaries instead of building strings. An empty
+/// input is one empty row.
+fn wrap_row_spans<G: AsRef<str>>(
+    graphemes: &[G],
+    max_cells: usize,
+    mode: WrapMode,
+) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = 0usize;
+    while start < graphemes.len() {
+        let consumed = take_row(&graphemes[start..], max_cells, mode).max(1);
+
+        // A word-wrap cut consumes the space it broke at; don't count it as visible.
+        let mut end = start + consumed;
+        if mode == WrapMode::Word && end > start + 1 && graphemes[end - 1].as_ref() == " " {
+            end -= 1;
+        }
+        spans.push(start..end);
+
+        start += consumed;
+        while mode.skips_leading_spaces() && graphemes.get(start).is_some_and(|g| g.as_ref() == " ")
+        {
+            start += 1;
         }
     }
 
-    (out, consumed)
+    if spans.is_empty() {
+        spans.push(0..0);
+    }
+    spans
 }
 
-/// Like `take_graphemes_by_cells`, but prefers wrapping on spaces within the chunk.
+/// How many visual rows [`wrap_row_spans`] would produce for `graphemes`,
+/// counted straight off an iterator.
 ///
-/// Returns:
-/// - row text (with any trailing space removed if we wrapped at a space)
-/// - number of graphemes consumed from the input (including the space we wrapped at)
-fn take_graphemes_by_cells_word_wrap(graphemes: &[Box<str>], max_cells: usize) -> (String, usize) {
-    let (chunk, consumed) = take_graphemes_by_cells(graphemes, max_cells);
-    if consumed == 0 {
-        return (chunk, consumed);
+/// Nothing is buffered: for word wrap it's enough to remember the cells and
+/// graphemes after the row's last space, since that's the part a cut carries
+/// over to the next row.
+fn count_wrapped_rows<G, I>(graphemes: I, max_cells: usize, mode: WrapMode) -> usize
+where
+    G: AsRef<str>,
+    I: IntoIterator<Item = G>,
+{
+    let mut rows = 0usize;
+    // The row being filled: cells used, graphemes taken, and (cells, graphemes)
+    // after its last space.
+    let mut used = 0usize;
+    let mut taken = 0usize;
+    let mut after_space: Option<(usize, usize)> = None;
+    let mut skipping = false;
+
+    for g in graphemes {
+        let g = g.as_ref();
+        let w = grapheme_cells(g);
+        loop {
+            if skipping && g == " " {
+                break;
+            }
+            skipping = false;
+
+            if used < max_cells && (w == 0 || used + w <= max_cells) {
+                used += w;
+                taken += 1;
+                after_space = match after_space {
+                    _ if g == " " => Some((0, 0)),
+                    Some((cells, count)) => Some((cells + w, count + 1)),
+                    None => None,
+                };
+                break;
+            }
+
+            rows += 1;
+            skipping = mode.skips_leading_spaces();
+            if taken == 0 {
+                // Too wide for any row: it gets one to itself.
+                break;
+            }
+            // Word wrap cuts after the last space, so what follows it moves down.
+            (used, taken) = match after_space {
+                Some(carried) if mode == WrapMode::Word => carried,
+                _ => (0, 0),
+            };
+            after_space = None;
+            if taken > 0 {
+                skipping = false;
+            }
+        }
     }
 
-    // Find last space within the consumed graphemes.
-    let mut last_space: Option<usize> = None;
-    for i in 0..consumed {
-        if graphemes[i].as_ref() == " " {
-            last_space = Some(i);
+    if taken > 0 || rows == 0 {
+        rows += 1;
+    }
+    rows
+}
+
+/// Wrap a *stream* of graphemes into at most `max_rows` rows of graphemes.
+///
+/// Unlike the slice-based helpers above, this pulls graphemes lazily: it only keeps
+/// about one row's worth of lookahead buffered and stops as soon as `max_rows` rows
+/// exist. Paired with [`RopeGraphemes`], rendering the top of an enormous line
+/// costs O(visible cells) instead of O(line length).
+///
+/// A line with no graphemes still occupies one (empty) row. With word wrap,
+/// leading spaces on continuation rows are skipped, same as the cached wrap path.
+fn wrap_grapheme_stream<G, I>(
+    graphemes: I,
+    max_cells: usize,
+    max_rows: usize,
+    mode: WrapMode,
+) -> Vec<Vec<G>>
+where
+    G: AsRef<str>,
+    I: IntoIterator<Item = G>,
+{
+    let mut rows = Vec::new();
+    if max_cells == 0 || max_rows == 0 {
+        return rows;
+    }
+
+    let mut source = graphemes.into_iter();
+    let mut window: Vec<G> = Vec::with_capacity(max_cells + 1);
+    let mut exhausted = false;
+
+    // Top up `window` so it holds enough graphemes to fill a full row, plus one
+    // so the word wrap can tell "the rest fits" from "the row is exactly full".
+    let mut refill = |window: &mut Vec<G>, exhausted: &mut bool| {
+        while !*exhausted && window.len() <= max_cells {
+            match source.next() {
+                Some(g) => window.push(g),
+                None => *exhausted = true,
+            }
         }
+    };
+
+    refill(&mut window, &mut exhausted);
+    if window.is_empty() {
+        rows.push(Vec::new());
+        return rows;
     }
 
-    // Cut at the last space if possible, otherwise hard wrap at cell boundary.
-    if let Some(space_idx) = last_space {
-        // Build string from graphemes[0..space_idx]
-        let mut out = String::new();
-        for g in &graphemes[..space_idx] {
-            out.push_str(g);
+    while rows.len() < max_rows && !window.is_empty() {
+        let consumed = take_row(&window, max_cells, mode);
+        // Ensure forward progress even if a single grapheme is wider than the viewport.
+        let consumed = consumed.max(1).min(window.len());
+        let mut row: Vec<G> = window.drain(..consumed).collect();
+        // A word-wrap cut consumes the space it broke at; it isn't drawn.
+        if mode == WrapMode::Word && row.len() > 1 && row.last().is_some_and(|g| g.as_ref() == " ")
+        {
+            row.pop();
+        }
+        rows.push(row);
+
+        // Skip leading spaces on the next visual row (refilling as we go, since a
+        // run of spaces can be longer than the buffered window).
+        while mode.skips_leading_spaces() {
+            let spaces = window.iter().take_while(|g| g.as_ref() == " ").count();
+            window.drain(..spaces);
+            if !window.is_empty() || exhausted {
+                break;
+            }
+            refill(&mut window, &mut exhausted);
         }
-        // Consume through the space so the next row starts after it.
-        return (out, space_idx + 1);
+
+        refill(&mut window, &mut exhausted);
     }
 
-    // No spaces: hard wrap at cell boundary.
-    (chunk, consumed)
+    rows
 }
 
 /// Clip uncached graphemes (`&str`) to a maximum number of terminal cells.
@@ -450,17 +1022,418 @@ fn clip_graphemes_to_cells_ref(graphemes: &[&str], max_cells: usize) -> String {
     out
 }
 
-/// Simple 64-bit FNV-1a hash for strings.
+/// Which part of a string [`truncate_to_cells`] cuts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TruncateMode {
+    /// Keep the start: `"src/editor/ma…"`.
+    #[default]
+    End,
+    /// Keep both ends around the ellipsis: `"src/…/main.rs"`. The tail gets the
+    /// extra cell when the budget is odd, since that's where a path's file name is.
+    /// Not used by the status line, which keeps the end instead.
+    #[allow(dead_code)]
+    Middle,
+    /// Keep the end: `"…editor/main.rs"`.
+    Start,
+}
+
+/// Shorten `s` to at most `max_cells` cells, putting `ellipsis` where text was
+/// cut (see [`TruncateMode`]).
 ///
-/// Not cryptographic but good enough.
-fn hash64(s: &str) -> u64 {
-    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
-    const FNV_PRIME: u64 = 0x100000001b3;
+/// Cuts on grapheme boundaries, so a wide char that would straddle the limit is
+/// dropped whole. Text that already fits is returned unchanged; if not even the
+/// ellipsis fits, it's clipped itself.
+pub fn truncate_to_cells(s: &str, max_cells: usize, ellipsis: &str, mode: TruncateMode) -> String {
+    let graphemes: Vec<&str> = s.graphemes(true).collect();
+    if take_graphemes_by_cells(&graphemes, max_cells) == graphemes.len() {
+        return s.to_string();
+    }
+
+    let ellipsis: Vec<&str> = ellipsis.graphemes(true).collect();
+    let ellipsis_cells = cells_of(&ellipsis);
+    if ellipsis_cells > max_cells {
+        return clip_graphemes_to_cells_ref(&ellipsis, max_cells);
+    }
+    let budget = max_cells - ellipsis_cells;
+
+    let (head_cells, tail_cells) = match mode {
+        TruncateMode::End => (budget, 0),
+        TruncateMode::Middle => (budget / 2, budget - budget / 2),
+        TruncateMode::Start => (0, budget),
+    };
+    let head = take_graphemes_by_cells(&graphemes, head_cells);
+    // In the middle, whatever the head left unused goes to the tail.
+    let tail_cells = match mode {
+        TruncateMode::Middle => tail_cells + head_cells - cells_of(&graphemes[..head]),
+        _ => tail_cells,
+    };
+    let rev: Vec<&str> = graphemes.iter().rev().copied().collect();
+    let tail = take_graphemes_by_cells(&rev, tail_cells);
+
+    let mut out: String = graphemes[..head].concat();
+    out.extend(ellipsis);
+    out.push_str(&graphemes[graphemes.len() - tail..].concat());
+    out
+}
+
+/// Total cells of `graphemes`.
+fn cells_of(graphemes: &[&str]) -> usize {
+    graphemes.iter().map(|g| grapheme_cells(g)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn joined(graphemes: &[Box<str>]) -> String {
+        graphemes.iter().map(|g| g.as_ref()).collect()
+    }
+
+    #[test]
+    fn cache_returns_fresh_graphemes_after_edit() {
+        let mut buffer = TextBuffer::from_str("abc\nxyz");
+        let mut cache = GraphemeCache::new(8);
+
+        let g0 = cache.graphemes_for_line(0, buffer.generation(), &buffer.line_string(0));
+        assert_eq!(joined(g0), "abc");
+
+        buffer.insert(editor_core::Pos::new(0, 3), "d");
+        let g1 = cache.graphemes_for_line(0, buffer.generation(), &buffer.line_string(0));
+        assert_eq!(joined(g1), "abcd");
+
+        // Untouched line still resolves correctly under the new generation.
+        let g2 = cache.graphemes_for_line(1, buffer.generation(), &buffer.line_string(1));
+        assert_eq!(joined(g2), "xyz");
+    }
+
+    #[test]
+    fn matching_generation_skips_line_text() {
+        let mut cache = GraphemeCache::new(8);
+        cache.graphemes_for_line(0, 7, "abc");
+
+        // Same generation: the cached entry wins even if the text passed differs.
+        let g = cache.graphemes_for_line(0, 7, "zzz");
+        assert_eq!(joined(g), "abc");
+    }
+
+    #[test]
+    fn invalidate_drops_entries() {
+        let mut cache = GraphemeCache::new(8);
+        cache.graphemes_for_line(0, 1, "a");
+        cache.graphemes_for_line(1, 1, "b");
+        cache.graphemes_for_line(2, 1, "c");
+
+        cache.invalidate_line(0);
+        assert_eq!(joined(cache.graphemes_for_line(0, 1, "A")), "A");
+
+        cache.invalidate_from(1);
+        assert_eq!(joined(cache.graphemes_for_line(1, 1, "B")), "B");
+        assert_eq!(joined(cache.graphemes_for_line(2, 1, "C")), "C");
+        assert_eq!(joined(cache.graphemes_for_line(0, 1, "?")), "A");
+    }
+
+    #[test]
+    fn streaming_wrap_touches_bounded_graphemes_of_huge_line() {
+        let line = "word ".repeat(200_000);
+        let buffer = TextBuffer::from_str(&line);
+        let slice = buffer.rope().slice(buffer.line_char_range(0));
+
+        let mut pulled = 0usize;
+        let stream = RopeGraphemes::new(slice).inspect(|_| pulled += 1);
+        let rows = wrap_grapheme_stream(stream.map(Cow::from), 12, 3, WrapMode::Word);
+
+        assert_eq!(join_rows(rows), vec!["word word", "word word", "word word"]);
+        // Three rows of 12 cells plus one row of lookahead, nowhere near 1M graphemes.
+        assert!(pulled <= 4 * 12, "pulled {pulled} graphemes");
+    }
+
+    #[test]
+    fn rope_graphemes_keep_clusters_intact() {
+        // "e" + combining acute accent is one grapheme.
+        let buffer = TextBuffer::from_str("ae\u{301}b");
+        let got: Vec<String> = RopeGraphemes::new(buffer.rope().slice(..))
+            .map(|g| g.to_string())
+            .collect();
+        assert_eq!(got, vec!["a", "e\u{301}", "b"]);
+    }
+
+    fn join_rows<G: AsRef<str>>(rows: Vec<Vec<G>>) -> Vec<String> {
+        rows.iter()
+            .map(|row| row.iter().map(AsRef::as_ref).collect())
+            .collect()
+    }
+
+    fn viewport(width: u16, height: u16, scroll_y: usize) -> TextViewport {
+        TextViewport {
+            scroll_x: 0,
+            scroll_y,
+            left: 0,
+            top: 0,
+            width,
+            height,
+            gutter_width: 0,
+            list_chars: None,
+            wrap_marker: None,
+            wrap_width: None,
+            wrap_mode: WrapMode::Word,
+        }
+    }
+
+    #[test]
+    fn horizontal_scroll_is_in_cells() {
+        // The tab is 4 cells wide: scrolling 2 cells lands past it, not after "a".
+        let buffer = TextBuffer::from_str("\tabc");
+        let mut cache = GraphemeCache::new(8);
+        let snap_at = |scroll_x, cache: &mut GraphemeCache| {
+            let vp = TextViewport {
+                scroll_x,
+                ..viewport(20, 1, 0)
+            };
+            snapshot_lines_wrapped_cached(&buffer, &vp, cache).lines
+        };
+
+        assert_eq!(snap_at(2, &mut cache), vec!["abc"]);
+        assert_eq!(snap_at(4, &mut cache), vec!["abc"]);
+        assert_eq!(snap_at(5, &mut cache), vec!["bc"]);
+        assert_eq!(skip_cells(&["\t", "a"], 1), 1);
+        assert_eq!(skip_cells(&["\t", "a"], 0), 0);
+        assert_eq!(skip_cells(&["\t", "a"], 9), 2);
+    }
+
+    #[test]
+    fn wrapped_snapshot_wraps_long_line() {
+        let buffer = TextBuffer::from_str("alpha beta gamma\nx");
+        let mut cache = GraphemeCache::new(8);
+        let snap = snapshot_lines_wrapped_cached(&buffer, &viewport(11, 10, 0), &mut cache);
+        assert_eq!(snap.lines, vec!["alpha beta", "gamma", "x"]);
+    }
+
+    #[test]
+    fn char_wrap_fills_rows_and_keeps_spaces() {
+        let text = "ab cd  ef gh";
+        let buffer = TextBuffer::from_str(text);
+        let vp = TextViewport {
+            wrap_mode: WrapMode::Char,
+            ..viewport(4, 10, 0)
+        };
+        let snap = snapshot_lines_wrapped_cached(&buffer, &vp, &mut GraphemeCache::new(8));
+
+        assert_eq!(snap.lines, vec!["ab c", "d  e", "f gh"]);
+        assert_eq!(snap.lines.concat(), text);
+
+        // The streaming path for huge lines wraps the same way.
+        let rows = wrap_grapheme_stream(text.graphemes(true), 4, 10, WrapMode::Char);
+        assert_eq!(join_rows(rows), snap.lines);
+        // A grapheme wider than the row still makes progress.
+        assert_eq!(take_graphemes_by_cells_hard(&["\t", "a"], 2), 1);
+    }
+
+    #[test]
+    fn leading_spaces_are_kept_only_in_char_wrap() {
+        let buffer = TextBuffer::from_str("    let v = [1,   2];");
+        let wrapped = |wrap_mode| {
+            let vp = TextViewport {
+                wrap_mode,
+                ..viewport(8, 10, 0)
+            };
+            snapshot_lines_wrapped_cached(&buffer, &vp, &mut GraphemeCache::new(8)).lines
+        };
+
+        // Code: the continuation keeps the spaces it starts with.
+        assert_eq!(
+            wrapped(WrapMode::Char),
+            vec!["    let ", "v = [1, ", "  2];"]
+        );
+        // Prose: continuation rows never start with a space.
+        let prose = wrapped(WrapMode::Word);
+        assert_eq!(prose[0], "    let");
+        assert!(prose[1..].iter().all(|row| !row.starts_with(' ')));
+    }
+
+    #[test]
+    fn wrap_width_is_independent_of_window_width() {
+        let text = "lorem ipsum ".repeat(20);
+        let buffer = TextBuffer::from_str(text.trim_end());
+        let mut cache = GraphemeCache::new(8);
+        let wrapped_at_80 = |width, cache: &mut GraphemeCache| {
+            let vp = TextViewport {
+                wrap_width: Some(80),
+                ..viewport(width, 10, 0)
+            };
+            snapshot_lines_wrapped_cached(&buffer, &vp, cache)
+        };
+
+        let narrow = wrapped_at_80(120, &mut cache);
+        let wide = wrapped_at_80(200, &mut cache);
+        assert_eq!(narrow.lines, wide.lines);
+        assert_eq!(narrow.rows, wide.rows);
+        assert!(narrow.lines.iter().all(|l| l.len() <= 80));
+        assert_eq!(narrow.lines.len(), 4);
+
+        // A window narrower than `wrap_width` still clips rows to fit.
+        let clipped = wrapped_at_80(40, &mut cache);
+        assert!(clipped.lines.iter().all(|l| l.len() <= 40));
+    }
+
+    #[test]
+    fn wrapped_snapshot_keeps_a_fitting_line_whole() {
+        let buffer = TextBuffer::from_str("hello world");
+        let mut cache = GraphemeCache::new(8);
+        let snap = snapshot_lines_wrapped_cached(&buffer, &viewport(20, 10, 0), &mut cache);
+        assert_eq!(snap.lines, vec!["hello world"]);
+    }
+
+    #[test]
+    fn wrapped_snapshot_skips_leading_spaces_on_continuation_rows() {
+        let buffer = TextBuffer::from_str("aaaa   bbbb");
+        let mut cache = GraphemeCache::new(8);
+        let snap = snapshot_lines_wrapped_cached(&buffer, &viewport(5, 10, 0), &mut cache);
+        assert_eq!(snap.lines, vec!["aaaa", "bbbb"]);
+    }
+
+    #[test]
+    fn wrapped_snapshot_flags_continuation_rows() {
+        let buffer = TextBuffer::from_str("aaaa bbbb cccc\nd");
+        let mut cache = GraphemeCache::new(8);
+        let snap = snapshot_lines_wrapped_cached(&buffer, &viewport(5, 10, 0), &mut cache);
+        assert_eq!(snap.lines, vec!["aaaa", "bbbb", "cccc", "d"]);
+        let flags: Vec<bool> = snap.rows.iter().map(|r| r.continuation).collect();
+        assert_eq!(flags, vec![false, true, true, false]);
+        assert!(snap.rows[..3].iter().all(|r| r.line == 0));
+    }
+
+    #[test]
+    fn screen_to_pos_on_wrapped_continuation_row() {
+        let buffer = TextBuffer::from_str("aaaa bbbb cccc\nd");
+        let mut cache = GraphemeCache::new(8);
+        let snap = snapshot_lines_wrapped_cached(&buffer, &viewport(5, 10, 0), &mut cache);
+
+        // Row 1 is "bbbb", starting at char 5 of line 0.
+        assert_eq!(snap.rows[1].start_col, 5);
+        assert_eq!(snap.screen_to_pos(1, 0), Some(Pos::new(0, 5)));
+        assert_eq!(snap.screen_to_pos(1, 2), Some(Pos::new(0, 7)));
+        // Past the end of the row: the space the row was broken at.
+        assert_eq!(snap.screen_to_pos(1, 4), Some(Pos::new(0, 9)));
+        assert_eq!(snap.screen_to_pos(2, 1), Some(Pos::new(0, 11)));
+        assert_eq!(snap.screen_to_pos(3, 3), Some(Pos::new(1, 1)));
+        assert_eq!(snap.screen_to_pos(4, 0), None);
+    }
+
+    #[test]
+    fn screen_to_pos_counts_tabs_as_one_char() {
+        let buffer = TextBuffer::from_str("\tab");
+        let mut cache = GraphemeCache::new(8);
+        let mut vp = viewport(20, 2, 0);
+        vp.list_chars = Some(ListChars::default());
+        let snap = snapshot_lines_wrapped_cached(&buffer, &vp, &mut cache);
+
+        assert_eq!(snap.screen_to_pos(0, 2), Some(Pos::new(0, 0)));
+        assert_eq!(snap.screen_to_pos(0, 4), Some(Pos::new(0, 1)));
+        assert_eq!(snap.screen_to_pos(0, 5), Some(Pos::new(0, 2)));
+    }
+
+    #[test]
+    fn wrapped_snapshot_scroll_y_lands_mid_line() {
+        let buffer = TextBuffer::from_str("one two three\n\nlast");
+        let mut cache = GraphemeCache::new(8);
+        // Line 0 wraps into "one two" / "three"; skip the first visual row.
+        let snap = snapshot_lines_wrapped_cached(&buffer, &viewport(8, 3, 1), &mut cache);
+        assert_eq!(snap.lines, vec!["three", "", "last"]);
+        let starts: Vec<_> = snap.rows.iter().map(|r| (r.line, r.continuation)).collect();
+        assert_eq!(starts, vec![(0, true), (1, false), (2, false)]);
+        assert_eq!(snap.first_line, 0);
+    }
+
+    #[test]
+    fn wrapped_snapshot_streams_huge_lines() {
+        let mut text = "word ".repeat(STREAMING_LINE_CHARS);
+        text.push_str("\nnext");
+        let buffer = TextBuffer::from_str(&text);
+        let mut cache = GraphemeCache::new(8);
+        let snap = snapshot_lines_wrapped_cached(&buffer, &viewport(12, 2, 1), &mut cache);
+        assert_eq!(snap.lines, vec!["word word", "word word"]);
+    }
+
+    #[test]
+    fn streaming_wrap_empty_line_is_one_row() {
+        let rows = wrap_grapheme_stream(std::iter::empty::<&str>(), 10, 5, WrapMode::Word);
+        assert_eq!(join_rows(rows), vec![String::new()]);
+    }
+
+    #[test]
+    fn counted_rows_match_wrapped_spans() {
+        let lines = [
+            "",
+            "   ",
+            "aaa bbb ccc",
+            "  lead  and trail  ",
+            "averyveryverylongword then short",
+            "a\tb\tc 漢字漢字 e\u{301}x",
+            "ab  cd   ef    gh",
+        ];
+        for line in lines {
+            let graphemes: Vec<&str> = line.graphemes(true).collect();
+            for max_cells in 1..12 {
+                for mode in [WrapMode::Word, WrapMode::Char] {
+                    assert_eq!(
+                        count_wrapped_rows(line.graphemes(true), max_cells, mode),
+                        wrap_row_spans(&graphemes, max_cells, mode).len(),
+                        "{line:?} at {max_cells} cells, {mode:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn truncate_to_cells_keeps_fitting_text() {
+        let end = TruncateMode::End;
+        assert_eq!(truncate_to_cells("main.rs", 7, "…", end), "main.rs");
+        assert_eq!(truncate_to_cells("", 0, "…", end), "");
+    }
+
+    #[test]
+    fn truncate_to_cells_stays_within_budget() {
+        let cells = |s: &str| s.graphemes(true).map(grapheme_cells).sum::<usize>();
+        let end = TruncateMode::End;
+
+        // 2 cells each; with a 1-cell ellipsis only three fit in 7.
+        let cut = truncate_to_cells("日本語のテキスト", 7, "…", end);
+        assert_eq!(cut, "日本語…");
+        assert_eq!(cells(&cut), 7);
+
+        let cut = truncate_to_cells("日本語のテキスト", 8, "...", end);
+        assert_eq!(cut, "日本...");
+        assert!(cells(&cut) <= 8);
+
+        assert_eq!(
+            truncate_to_cells("src/editor/main.rs", 8, "…", end),
+            "src/edi…"
+        );
+        assert_eq!(truncate_to_cells("abcdef", 2, "...", end), "..");
+
+        // A wide char that doesn't fit at the cut leaves its cell empty.
+        let cut = truncate_to_cells("漢字漢字ab", 4, "…", end);
+        assert_eq!(cut, "漢…");
+        assert_eq!(cells(&cut), 3);
+    }
+
+    #[test]
+    fn middle_truncation_keeps_the_file_name() {
+        let path = "src/editor/buffer/text_buffer/main.rs";
+        let cut = truncate_to_cells(path, 20, "…", TruncateMode::Middle);
+        assert_eq!(cut, "src/edito…er/main.rs");
+        assert_eq!(cut.chars().count(), 20);
+        assert!(cut.ends_with("main.rs"));
+
+        // A wide char the head can't fit leaves its cell to the tail.
+        let cut = truncate_to_cells("日本語/abcdef", 6, "…", TruncateMode::Middle);
+        assert_eq!(cut, "日…def");
+    }
 
-    let mut h = FNV_OFFSET;
-    for &b in s.as_bytes() {
-        h ^= b as u64;
-        h = h.wrapping_mul(FNV_PRIME);
+    #[test]
+    fn start_truncation_keeps_the_end() {
+        let cut = truncate_to_cells("src/editor/main.rs", 10, "…", TruncateMode::Start);
+        assert_eq!(cut, "…r/main.rs");
     }
-    h
 }
@@ -0,0 +1,366 @@
+//! Split-view layout: a tree of panes sharing the text area.
+//!
+//! Each leaf is a [`Pane`] showing one buffer (by index into the buffer list)
+//! through its own [`TextViewport`]. Splitting a pane replaces it with a two-way
+//! split of itself and a copy, like Vim's `:split` / `:vsplit`: the new pane
+//! comes first (above / left) and takes focus.
+//!
+//! Panes are numbered in reading order (depth first, first child first), which
+//! is also the order `focus_next` cycles through and `rects` reports.
+//!
+//! Side-by-side panes are separated by a one-column border; stacked panes aren't
+//! (there's one status line for the whole window, not one per pane). When a
+//! size doesn't split evenly, the first pane gets the extra cell.
+
+use super::TextViewport;
+
+/// A screen rectangle, in cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+/// One view onto a buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct Pane {
+    /// Index of the shown buffer in the buffer list.
+    pub buffer: usize,
+    /// Scroll offsets and display options; its size is set from the pane's
+    /// rectangle when laying out.
+    pub viewport: TextViewport,
+}
+
+impl Pane {
+    /// A pane onto `buffer`, scrolled to the top.
+    pub fn new(buffer: usize) -> Self {
+        Self {
+            buffer,
+            viewport: TextViewport::default(),
+        }
+    }
+}
+
+/// Which way a split divides its area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDirection {
+    /// Stacked top and bottom (`:split`).
+    Horizontal,
+    /// Side by side (`:vsplit`).
+    Vertical,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Pane(Pane),
+    Split {
+        direction: SplitDirection,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+/// The pane tree plus which pane has focus.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    root: Node,
+    /// Index of the focused pane in reading order.
+    focus: usize,
+}
+
+impl Layout {
+    /// A single pane filling the area.
+    pub fn new(pane: Pane) -> Self {
+        Self {
+            root: Node::Pane(pane),
+            focus: 0,
+        }
+    }
+
+    /// Number of panes.
+    pub fn len(&self) -> usize {
+        self.root.panes()
+    }
+
+    pub fn focus(&self) -> usize {
+        self.focus
+    }
+
+    pub fn focused(&self) -> &Pane {
+        self.root
+            .pane(self.focus)
+            .expect("focus is always a valid pane index")
+    }
+
+    pub fn focused_mut(&mut self) -> &mut Pane {
+        self.root
+            .pane_mut(self.focus)
+            .expect("focus is always a valid pane index")
+    }
+
+    /// Split the focused pane into two stacked panes (`:split`).
+    pub fn split_horizontal(&mut self) {
+        self.split(SplitDirection::Horizontal);
+    }
+
+    /// Split the focused pane into two side-by-side panes (`:vsplit`).
+    pub fn split_vertical(&mut self) {
+        self.split(SplitDirection::Vertical);
+    }
+
+    /// Replace the focused pane with a split of a copy (first, focused) and
+    /// itself (second).
+    fn split(&mut self, direction: SplitDirection) {
+        let node = self
+            .root
+            .node_mut(self.focus)
+            .expect("focus is always a valid pane index");
+        let Node::Pane(pane) = *node else {
+            unreachable!("node_mut only returns leaves");
+        };
+        *node = Node::Split {
+            direction,
+            first: Box::new(Node::Pane(pane)),
+            second: Box::new(Node::Pane(pane)),
+        };
+    }
+
+    /// Move focus to the next pane in reading order, wrapping around (`Ctrl-W w`).
+    pub fn focus_next(&mut self) {
+        self.focus = (self.focus + 1) % self.len();
+    }
+
+    /// Each pane's rectangle within a `width` x `height` area, in reading order.
+    pub fn rects(&self, width: u16, height: u16) -> Vec<Rect> {
+        let mut rects = Vec::with_capacity(self.len());
+        self.root.rects(
+            Rect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            },
+            &mut rects,
+        );
+        rects
+    }
+
+    /// Each pane's rectangle and its viewport placed and sized to fit it, in
+    /// reading order.
+    ///
+    /// Draw a pane by snapshotting its buffer with the viewport; the draw
+    /// functions offset their rows and columns by its `left`/`top`.
+    pub fn viewports(&self, width: u16, height: u16) -> Vec<(Rect, Pane)> {
+        let rects = self.rects(width, height);
+        (0..self.len())
+            .zip(rects)
+            .map(|(i, rect)| {
+                let mut pane = *self.root.pane(i).expect("index is below len");
+                pane.viewport.left = rect.x;
+                pane.viewport.top = rect.y;
+                pane.viewport.width = rect.width;
+                pane.viewport.height = rect.height;
+                (rect, pane)
+            })
+            .collect()
+    }
+}
+
+impl Node {
+    fn panes(&self) -> usize {
+        match self {
+            Node::Pane(_) => 1,
+            Node::Split { first, second, .. } => first.panes() + second.panes(),
+        }
+    }
+
+    /// The leaf node of pane `index` (in reading order).
+    fn node_mut(&mut self, index: usize) -> Option<&mut Node> {
+        match self {
+            Node::Pane(_) if index == 0 => Some(self),
+            Node::Pane(_) => None,
+            Node::Split { first, second, .. } => {
+                let in_first = first.panes();
+                if index < in_first {
+                    first.node_mut(index)
+                } else {
+                    second.node_mut(index - in_first)
+                }
+            }
+        }
+    }
+
+    fn pane(&self, index: usize) -> Option<&Pane> {
+        match self {
+            Node::Pane(pane) if index == 0 => Some(pane),
+            Node::Pane(_) => None,
+            Node::Split { first, second, .. } => {
+                let in_first = first.panes();
+                if index < in_first {
+                    first.pane(index)
+                } else {
+                    second.pane(index - in_first)
+                }
+            }
+        }
+    }
+
+    fn pane_mut(&mut self, index: usize) -> Option<&mut Pane> {
+        match self.node_mut(index)? {
+            Node::Pane(pane) => Some(pane),
+            Node::Split { .. } => None,
+        }
+    }
+
+    fn rects(&self, area: Rect, out: &mut Vec<Rect>) {
+        let Node::Split {
+            direction,
+            first,
+            second,
+        } = self
+        else {
+            out.push(area);
+            return;
+        };
+
+        let (a, b) = match direction {
+            SplitDirection::Horizontal => {
+                let top = area.height - area.height / 2;
+                (
+                    Rect {
+                        height: top,
+                        ..area
+                    },
+                    Rect {
+                        y: area.y + top,
+                        height: area.height / 2,
+                        ..area
+                    },
+                )
+            }
+            SplitDirection::Vertical => {
+                // One column between the two is the border.
+                let available = area.width.saturating_sub(1);
+                let left = available - available / 2;
+                (
+                    Rect {
+                        width: left,
+                        ..area
+                    },
+                    Rect {
+                        x: area.x + left + 1,
+                        width: available / 2,
+                        ..area
+                    },
+                )
+            }
+        };
+        first.rects(a, out);
+        second.rects(b, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::WrapMode;
+
+    fn pane(buffer: usize) -> Pane {
+        Pane {
+            buffer,
+            viewport: TextViewport {
+                scroll_x: 0,
+                scroll_y: 7,
+                left: 0,
+                top: 0,
+                width: 0,
+                height: 0,
+                gutter_width: 0,
+                list_chars: None,
+                wrap_marker: None,
+                wrap_width: None,
+                wrap_mode: WrapMode::Word,
+            },
+        }
+    }
+
+    fn rect(x: u16, y: u16, width: u16, height: u16) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn single_pane_fills_the_area() {
+        assert_eq!(Layout::new(pane(0)).rects(80, 24), vec![rect(0, 0, 80, 24)]);
+    }
+
+    #[test]
+    fn horizontal_split_stacks_panes() {
+        let mut layout = Layout::new(pane(0));
+        layout.split_horizontal();
+        assert_eq!(
+            layout.rects(80, 23),
+            vec![rect(0, 0, 80, 12), rect(0, 12, 80, 11)]
+        );
+    }
+
+    #[test]
+    fn vertical_split_leaves_a_border_column() {
+        let mut layout = Layout::new(pane(0));
+        layout.split_vertical();
+        assert_eq!(
+            layout.rects(80, 24),
+            vec![rect(0, 0, 40, 24), rect(41, 0, 39, 24)]
+        );
+    }
+
+    #[test]
+    fn nested_split_divides_the_focused_pane() {
+        let mut layout = Layout::new(pane(0));
+        layout.split_vertical();
+        layout.focus_next();
+        layout.focused_mut().buffer = 1;
+        layout.split_horizontal();
+
+        assert_eq!(layout.len(), 3);
+        assert_eq!(layout.focus(), 1);
+        assert_eq!(
+            layout.rects(81, 20),
+            vec![
+                rect(0, 0, 40, 20),
+                rect(41, 0, 40, 10),
+                rect(41, 10, 40, 10)
+            ]
+        );
+
+        let panes = layout.viewports(81, 20);
+        let buffers: Vec<usize> = panes.iter().map(|(_, p)| p.buffer).collect();
+        assert_eq!(buffers, vec![0, 1, 1]);
+        assert_eq!(
+            (panes[2].1.viewport.width, panes[2].1.viewport.height),
+            (40, 10)
+        );
+        assert_eq!(
+            (panes[2].1.viewport.left, panes[2].1.viewport.top),
+            (41, 10)
+        );
+        // Splits copy the view, scroll included.
+        assert_eq!(panes[1].1.viewport.scroll_y, 7);
+    }
+
+    #[test]
+    fn focus_next_wraps() {
+        let mut layout = Layout::new(pane(0));
+        layout.split_vertical();
+        assert_eq!(layout.focus(), 0);
+        layout.focus_next();
+        layout.focus_next();
+        assert_eq!(layout.focus(), 0);
+    }
+}
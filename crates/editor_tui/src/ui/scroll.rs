@@ -0,0 +1,212 @@
+//! Keeping the cursor on screen.
+//!
+//! Pure arithmetic over the viewport size: callers work out where the cursor sits
+//! in the wrapped document and get back the scroll offsets that keep it visible.
+//!
+//! After a resize, [`clamp_scroll`] pulls offsets back inside the re-wrapped
+//! document so the view doesn't hang past its end.
+
+use std::borrow::Cow;
+
+use editor_core::{Pos, TextBuffer};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::layout::{LineLayout, line_to_visual_row, wrapped_row_count};
+use super::{RopeGraphemes, TextViewport, grapheme_cells};
+
+/// Scroll offsets `(scroll_x, scroll_y)` that keep the cursor inside `viewport`.
+///
+/// - `cursor_row` is the cursor's visual (wrapped) row counted from the top of the
+///   document, the same unit as `viewport.scroll_y`.
+/// - `cursor_col` is the cell offset where the cursor's grapheme starts, the same
+///   unit as `viewport.scroll_x`.
+/// - `scrolloff` rows of context are kept above and below the cursor where
+///   possible (Vim's `scrolloff`). It's capped so the margins never overlap.
+///
+/// Rows soft-wrap, so the cursor can only leave the viewport horizontally by
+/// falling off the left edge. Offsets are left alone while the cursor is visible.
+pub fn scroll_to_cursor(
+    viewport: &TextViewport,
+    cursor_row: usize,
+    cursor_col: usize,
+    scrolloff: usize,
+) -> (usize, usize) {
+    let height = viewport.height as usize;
+    let scrolloff = scrolloff.min(height.saturating_sub(1) / 2);
+
+    let mut scroll_y = viewport.scroll_y;
+    let top = cursor_row.saturating_sub(scrolloff);
+    if scroll_y > top {
+        scroll_y = top;
+    } else if height > 0 && cursor_row + scrolloff >= scroll_y + height {
+        scroll_y = cursor_row + scrolloff + 1 - height;
+    }
+
+    let scroll_x = viewport.scroll_x.min(cursor_col);
+
+    (scroll_x, scroll_y)
+}
+
+/// Scroll offsets that keep `cursor` visible, working out its visual position first.
+///
+/// NOTE: finding the cursor's visual row means wrapping every line above it, the
+/// same cost the wrapped snapshot already pays to skip rows above `scroll_y`.
+pub fn follow_cursor(
+    buffer: &TextBuffer,
+    viewport: &TextViewport,
+    cursor: Pos,
+    scrolloff: usize,
+) -> (usize, usize) {
+    let cursor = buffer.clamp_pos(cursor);
+
+    // Cells taken by the graphemes that end at or before the cursor's column.
+    let mut chars = 0usize;
+    let col = RopeGraphemes::new(buffer.rope().slice(buffer.line_char_range(cursor.line)))
+        .take_while(|g| {
+            chars += g.len_chars();
+            chars <= cursor.col
+        })
+        .map(|g| grapheme_cells(&Cow::from(g)))
+        .sum();
+
+    // Settle horizontal scroll first; it changes how lines wrap.
+    let mut viewport = *viewport;
+    viewport.scroll_x = viewport.scroll_x.min(col);
+
+    let rows_above = line_to_visual_row(buffer, &viewport, cursor.line);
+    let row_in_line = LineLayout::new(buffer, &viewport, cursor.line, cursor.col, usize::MAX)
+        .locate(cursor.col)
+        .map_or(0, |(row, _)| row);
+
+    scroll_to_cursor(&viewport, rows_above + row_in_line, col, scrolloff)
+}
+
+/// `(scroll_x, scroll_y)` from `viewport`, clamped to the document's extent at
+/// the viewport's current size.
+///
+/// - `scroll_x` stops where the widest line's end reaches the right edge of the
+///   text area (so it's 0 when every line fits).
+/// - `scroll_y` stops where the last visual row is at the bottom of the viewport.
+///
+/// NOTE: this wraps every line (O(lines)); it's meant for resizes, not every frame.
+pub fn clamp_scroll(buffer: &TextBuffer, viewport: &TextViewport) -> (usize, usize) {
+    let widest = (0..buffer.len_lines())
+        .map(|line| {
+            buffer
+                .line_string(line)
+                .graphemes(true)
+                .map(grapheme_cells)
+                .sum::<usize>()
+        })
+        .max()
+        .unwrap_or(0);
+    let scroll_x = viewport
+        .scroll_x
+        .min(widest.saturating_sub(viewport.wrap_cells()));
+
+    // Rows depend on horizontal scroll, so count them with the clamped offset.
+    let mut viewport = *viewport;
+    viewport.scroll_x = scroll_x;
+    let rows: usize = (0..buffer.len_lines())
+        .map(|line| wrapped_row_count(buffer, &viewport, line))
+        .sum();
+    let scroll_y = viewport
+        .scroll_y
+        .min(rows.saturating_sub(viewport.height as usize));
+
+    (scroll_x, scroll_y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::WrapMode;
+
+    fn viewport(scroll_x: usize, scroll_y: usize, height: u16) -> TextViewport {
+        TextViewport {
+            scroll_x,
+            scroll_y,
+            left: 0,
+            top: 0,
+            width: 20,
+            height,
+            gutter_width: 0,
+            list_chars: None,
+            wrap_marker: None,
+            wrap_width: None,
+            wrap_mode: WrapMode::Word,
+        }
+    }
+
+    #[test]
+    fn cursor_above_the_top_scrolls_up() {
+        assert_eq!(scroll_to_cursor(&viewport(0, 10, 5), 8, 0, 0), (0, 8));
+        assert_eq!(scroll_to_cursor(&viewport(0, 10, 5), 8, 0, 2), (0, 6));
+        // Can't scroll past the start of the document.
+        assert_eq!(scroll_to_cursor(&viewport(0, 10, 5), 1, 0, 2), (0, 0));
+    }
+
+    #[test]
+    fn cursor_below_the_bottom_scrolls_down() {
+        // Rows 0..5 are visible; row 7 needs the last row to be 7.
+        assert_eq!(scroll_to_cursor(&viewport(0, 0, 5), 7, 0, 0), (0, 3));
+        assert_eq!(scroll_to_cursor(&viewport(0, 0, 5), 7, 0, 1), (0, 4));
+    }
+
+    #[test]
+    fn scrolloff_band_pulls_the_view_along() {
+        // Visible rows 10..20. Row 12 sits inside a 3-row top margin.
+        assert_eq!(scroll_to_cursor(&viewport(0, 10, 10), 12, 0, 3), (0, 9));
+        // Row 17 sits inside the bottom margin.
+        assert_eq!(scroll_to_cursor(&viewport(0, 10, 10), 17, 0, 3), (0, 11));
+        // Row 14 is clear of both margins: nothing moves.
+        assert_eq!(scroll_to_cursor(&viewport(0, 10, 10), 14, 0, 3), (0, 10));
+    }
+
+    #[test]
+    fn huge_scrolloff_centers_the_cursor() {
+        assert_eq!(scroll_to_cursor(&viewport(0, 0, 5), 10, 0, 99), (0, 8));
+    }
+
+    #[test]
+    fn follow_cursor_counts_wrapped_rows() {
+        // Text width 8: line 0 wraps to "one two" / "three", so line 1 is row 2.
+        let buffer = TextBuffer::from_str("one two three\nfour\nfive");
+        let mut vp = viewport(0, 0, 2);
+        vp.width = 8;
+        assert_eq!(follow_cursor(&buffer, &vp, Pos::new(1, 2), 0), (0, 1));
+        assert_eq!(follow_cursor(&buffer, &vp, Pos::new(2, 0), 0), (0, 2));
+    }
+
+    #[test]
+    fn cursor_left_of_horizontal_scroll() {
+        assert_eq!(scroll_to_cursor(&viewport(8, 0, 5), 0, 3, 0), (3, 0));
+        assert_eq!(scroll_to_cursor(&viewport(8, 0, 5), 0, 30, 0), (8, 0));
+    }
+
+    #[test]
+    fn clamp_scroll_after_shrinking_the_window() {
+        let text: String = (0..10).map(|i| format!("line {i}\n")).collect();
+        let buffer = TextBuffer::from_str(text.trim_end());
+
+        // Scrolled well past the end: the last row ends up at the bottom.
+        assert_eq!(clamp_scroll(&buffer, &viewport(0, 50, 4)), (0, 6));
+        // Offsets that are still in range are kept.
+        assert_eq!(clamp_scroll(&buffer, &viewport(0, 3, 4)), (0, 3));
+        // Taller than the document: nothing to scroll.
+        assert_eq!(clamp_scroll(&buffer, &viewport(0, 3, 20)), (0, 0));
+
+        // Narrower: "line N" wraps to "line" / "N", so there are 20 rows.
+        let mut narrow = viewport(0, 50, 4);
+        narrow.width = 5;
+        assert_eq!(clamp_scroll(&buffer, &narrow), (0, 16));
+    }
+
+    #[test]
+    fn clamp_scroll_x_to_the_widest_line() {
+        let buffer = TextBuffer::from_str("short\n0123456789abcdefghijklmnopqrstuvwxyz");
+        // 36 cells in a 20-cell viewport: at most 16 can scroll off.
+        assert_eq!(clamp_scroll(&buffer, &viewport(30, 0, 5)), (16, 0));
+        assert_eq!(clamp_scroll(&buffer, &viewport(4, 0, 5)), (4, 0));
+    }
+}
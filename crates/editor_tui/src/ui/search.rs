@@ -0,0 +1,192 @@
+//! Search-match highlighting (Vim's `hlsearch`).
+//!
+//! Every match of the active search term that's on screen is re-drawn in a
+//! highlight color. Geometry comes from the snapshot's per-row segments, so
+//! tabs, wide glyphs and soft-wrapped rows line up with what was drawn, and a
+//! match split across a wrap gets a span on each row.
+
+use std::ops::Range;
+
+use editor_core::TextBuffer;
+use editor_core::buffer::SearchOptions;
+use minui::Window;
+use minui::prelude::{Color, ColorPair};
+
+use super::{RenderSnapshot, TextViewport, row_text_in_cells};
+
+/// For each snapshot row, the cell ranges (relative to the text area) covered by
+/// matches of `term`.
+///
+/// No term (or an empty one) does no work at all. Matches are scanned lazily,
+/// starting just far enough before the first visible row to catch a match that
+/// runs into it, and the scan stops at the first match past the last visible row.
+pub fn match_row_spans(
+    buffer: &TextBuffer,
+    snapshot: &RenderSnapshot,
+    term: Option<&str>,
+    opts: SearchOptions,
+) -> Vec<Vec<Range<usize>>> {
+    let mut spans = vec![Vec::new(); snapshot.rows.len()];
+    let Some(term) = term.filter(|t| !t.is_empty()) else {
+        return spans;
+    };
+
+    // Absolute char range drawn on each row.
+    let row_chars: Vec<Range<usize>> = snapshot
+        .rows
+        .iter()
+        .map(|row| {
+            let start = buffer.line_to_char(row.line) + row.start_col;
+            let len: usize = row.segments.iter().map(|&(_, chars)| chars).sum();
+            start..start + len
+        })
+        .collect();
+    let (Some(first), Some(last)) = (row_chars.first(), row_chars.last()) else {
+        return spans;
+    };
+    let (visible_start, visible_end) = (first.start, last.end);
+
+    let from = visible_start.saturating_sub(term.chars().count() - 1);
+    let visible = buffer
+        .matches_from(term, opts, from)
+        .map(|m| m.start.get()..m.end.get())
+        .skip_while(|m| m.end <= visible_start)
+        .take_while(|m| m.start < visible_end);
+    for m in visible {
+        for (row, chars) in row_chars.iter().enumerate() {
+            if let Some(cells) = row_cells(&snapshot.rows[row].segments, chars.start, &m) {
+                spans[row].push(cells);
+            }
+        }
+    }
+    spans
+}
+
+/// Cells of a row (whose first segment starts at char `start`) covered by `m`.
+fn row_cells(segments: &[(usize, usize)], start: usize, m: &Range<usize>) -> Option<Range<usize>> {
+    let mut covered: Option<Range<usize>> = None;
+    let (mut c, mut x) = (start, 0);
+    for &(cells, chars) in segments {
+        if c < m.end && m.start < c + chars {
+            covered = Some(covered.map_or(x..x + cells, |r| r.start..x + cells));
+        }
+        c += chars;
+        x += cells;
+    }
+    covered
+}
+
+/// Re-draw the visible matches of `term` in the search highlight color.
+pub fn draw_search_matches(
+    buffer: &TextBuffer,
+    viewport: &TextViewport,
+    snapshot: &RenderSnapshot,
+    term: Option<&str>,
+    window: &mut dyn Window,
+) -> minui::Result<()> {
+    let colors = ColorPair::new(Color::Black, Color::Yellow);
+    let text_x = viewport.left as usize + viewport.text_x();
+
+    let spans = match_row_spans(buffer, snapshot, term, SearchOptions::default());
+    for (row, spans) in spans.into_iter().enumerate() {
+        for cells in spans {
+            let x = (text_x + cells.start) as u16;
+            let text = row_text_in_cells(&snapshot.lines[row], cells);
+            window.write_str_colored(viewport.top + row as u16, x, &text, colors)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::{GraphemeCache, WrapMode, snapshot_lines_wrapped_cached};
+
+    fn spans(text: &str, width: u16, term: Option<&str>) -> Vec<Vec<Range<usize>>> {
+        let buffer = TextBuffer::from_str(text);
+        let viewport = TextViewport {
+            scroll_x: 0,
+            scroll_y: 0,
+            left: 0,
+            top: 0,
+            width,
+            height: 10,
+            gutter_width: 0,
+            list_chars: None,
+            wrap_marker: None,
+            wrap_width: None,
+            wrap_mode: WrapMode::Word,
+        };
+        let snap = snapshot_lines_wrapped_cached(&buffer, &viewport, &mut GraphemeCache::new(8));
+        match_row_spans(&buffer, &snap, term, SearchOptions::default())
+    }
+
+    #[test]
+    fn two_matches_on_one_line() {
+        assert_eq!(
+            spans("let foo = foo + 1;\nfoo", 40, Some("foo")),
+            vec![vec![4..7, 10..13], vec![0..3]]
+        );
+    }
+
+    #[test]
+    fn tabs_and_wrapped_rows_use_drawn_cells() {
+        // The tab is 4 cells wide.
+        assert_eq!(spans("\tab ab", 40, Some("ab")), vec![vec![4..6, 7..9]]);
+        // Width 4 wraps "abcdef" as "abcd" / "ef"; "cde" spans both rows.
+        assert_eq!(
+            spans("abcdef", 4, Some("cde")),
+            vec![vec![2..4], vec![0..1]]
+        );
+    }
+
+    #[test]
+    fn matches_far_down_the_buffer() {
+        let mut text = "filler\n".repeat(5000);
+        text.push_str("xfoo foo\nfoo");
+        let buffer = TextBuffer::from_str(&text);
+        let viewport = TextViewport {
+            scroll_x: 0,
+            scroll_y: 5000,
+            left: 0,
+            top: 0,
+            width: 40,
+            height: 10,
+            gutter_width: 0,
+            list_chars: None,
+            wrap_marker: None,
+            wrap_width: None,
+            wrap_mode: WrapMode::Word,
+        };
+        let snap = snapshot_lines_wrapped_cached(&buffer, &viewport, &mut GraphemeCache::new(8));
+        assert_eq!(
+            match_row_spans(&buffer, &snap, Some("foo"), SearchOptions::default()),
+            vec![vec![1..4, 5..8], vec![0..3]]
+        );
+
+        // A match running into the top row from the line above is still found.
+        // Rows: "fil" / "ler" per filler line, then "xfo" / "o f" / "oo" / "foo".
+        let viewport = TextViewport {
+            width: 3,
+            scroll_y: 10_001,
+            wrap_mode: WrapMode::Char,
+            ..viewport
+        };
+        let snap = snapshot_lines_wrapped_cached(&buffer, &viewport, &mut GraphemeCache::new(8));
+        assert_eq!(snap.lines[0], "o f");
+        assert_eq!(
+            match_row_spans(&buffer, &snap, Some("foo"), SearchOptions::default())[0],
+            vec![0..1, 2..3]
+        );
+    }
+
+    #[test]
+    fn no_term_highlights_nothing() {
+        assert_eq!(spans("foo foo", 40, None), vec![Vec::<Range<usize>>::new()]);
+        assert_eq!(
+            spans("foo foo", 40, Some("")),
+            vec![Vec::<Range<usize>>::new()]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Selection highlighting.
+//!
+//! Selection geometry is computed as per-row cell ranges rather than by slicing
+//! and rebuilding row strings, so it stays cheap no matter how much is selected.
+//! Only the drawing step touches the row text, to re-emit the covered cells in the
+//! highlight color.
+
+use std::ops::Range;
+
+use editor_core::{Selection, TextBuffer};
+use minui::Window;
+use minui::prelude::{Color, ColorPair};
+
+use super::layout::{LineLayout, line_screen_row};
+use super::{RenderSnapshot, TextViewport, row_text_in_cells};
+
+/// For each snapshot row, the screen columns covered by `sel` (gutter and pane
+/// offsets included).
+///
+/// - Lines fully inside a multi-line selection are covered end to end, plus one
+///   cell standing in for the selected newline (so empty lines still show up).
+/// - The first and last lines are only covered from/to the selection ends.
+/// - An empty selection highlights nothing.
+pub fn selection_row_ranges(
+    buffer: &TextBuffer,
+    viewport: &TextViewport,
+    snapshot: &RenderSnapshot,
+    sel: Selection,
+) -> Vec<Option<Range<u16>>> {
+    let mut ranges = vec![None; snapshot.lines.len()];
+    let (start, end) = sel.ordered();
+    let (start, end) = (buffer.clamp_pos(start), buffer.clamp_pos(end));
+    if start == end || snapshot.lines.is_empty() {
+        return ranges;
+    }
+
+    let max_cells = viewport.wrap_cells();
+    let first = start.line.max(snapshot.first_line);
+    let last_visible = snapshot
+        .rows
+        .last()
+        .map_or(snapshot.first_line, |row| row.line);
+    let last = end.line.min(last_visible);
+
+    for line in first..=last {
+        let Some(line_row) = line_screen_row(snapshot, line) else {
+            continue;
+        };
+        let from = if line == start.line { start.col } else { 0 };
+        let to = if line == end.line {
+            end.col
+        } else {
+            buffer.line_len_chars(line)
+        };
+        let selects_newline = line < end.line;
+
+        let rows = (ranges.len() as isize - line_row).max(0) as usize;
+        let layout = LineLayout::new(buffer, viewport, line, to, rows);
+
+        for row_in_line in 0..layout.rows() {
+            let row = line_row + row_in_line as isize;
+            if row < 0 || row as usize >= ranges.len() {
+                continue;
+            }
+
+            let mut cells = layout.cell_range(row_in_line, from..to);
+            if selects_newline && layout.is_last_row(row_in_line) {
+                // Extend (or start) the highlight over one cell for the newline.
+                let eol = layout.row_cells(row_in_line);
+                if eol < max_cells {
+                    cells = Some(cells.map_or(eol..eol + 1, |c| c.start..eol + 1));
+                }
+            }
+
+            ranges[row as usize] = cells.map(|c| {
+                let x = viewport.left as usize + viewport.text_x();
+                (x + c.start) as u16..(x + c.end) as u16
+            });
+        }
+    }
+
+    ranges
+}
+
+/// Re-draw the selected cells of each row in the highlight color.
+pub fn draw_selection(
+    buffer: &TextBuffer,
+    viewport: &TextViewport,
+    snapshot: &RenderSnapshot,
+    sel: Selection,
+    window: &mut dyn Window,
+) -> minui::Result<()> {
+    let colors = ColorPair::new(Color::Black, Color::LightGray);
+    let text_x = viewport.left as usize + viewport.text_x();
+
+    for (row, range) in selection_row_ranges(buffer, viewport, snapshot, sel)
+        .into_iter()
+        .enumerate()
+    {
+        let Some(range) = range else {
+            continue;
+        };
+        let cells = range.start as usize - text_x..range.end as usize - text_x;
+        let text = row_text_in_cells(&snapshot.lines[row], cells);
+        window.write_str_colored(viewport.top + row as u16, range.start, &text, colors)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::{GraphemeCache, WrapMode, snapshot_lines_wrapped_cached};
+    use editor_core::Pos;
+
+    fn ranges(text: &str, vp: TextViewport, sel: Selection) -> Vec<Option<Range<u16>>> {
+        let buffer = TextBuffer::from_str(text);
+        let snap = snapshot_lines_wrapped_cached(&buffer, &vp, &mut GraphemeCache::new(8));
+        selection_row_ranges(&buffer, &vp, &snap, sel)
+    }
+
+    fn viewport(width: u16, height: u16) -> TextViewport {
+        TextViewport {
+            scroll_x: 0,
+            scroll_y: 0,
+            left: 0,
+            top: 0,
+            width,
+            height,
+            gutter_width: 2,
+            list_chars: None,
+            wrap_marker: None,
+            wrap_width: None,
+            wrap_mode: WrapMode::Word,
+        }
+    }
+
+    #[test]
+    fn multi_line_selection_covers_partial_ends() {
+        let sel = Selection::new(Pos::new(0, 2), Pos::new(2, 3));
+        let got = ranges("hello\n\nworld\nrest", viewport(20, 5), sel);
+        assert_eq!(got, vec![Some(4..8), Some(2..3), Some(2..5), None]);
+    }
+
+    #[test]
+    fn selection_across_wrapped_rows() {
+        // Text width 8: "one two" / "three"
+        let sel = Selection::new(Pos::new(0, 4), Pos::new(0, 11));
+        let got = ranges("one two three", viewport(10, 5), sel);
+        assert_eq!(got, vec![Some(6..9), Some(2..5)]);
+    }
+
+    #[test]
+    fn selection_with_scrolled_viewport() {
+        let mut vp = viewport(20, 2);
+        vp.scroll_y = 1;
+        vp.scroll_x = 2;
+        // Reversed anchor/cursor; line 0 is scrolled off the top.
+        let sel = Selection::new(Pos::new(2, 4), Pos::new(0, 1));
+        let got = ranges("abcdef\nabcdef\nabcdef", vp, sel);
+        assert_eq!(got, vec![Some(2..7), Some(2..4)]);
+    }
+
+    #[test]
+    fn empty_selection_highlights_nothing() {
+        let sel = Selection::empty(Pos::new(0, 1));
+        assert_eq!(ranges("abc", viewport(10, 2), sel), vec![None]);
+    }
+}
@@ -0,0 +1,176 @@
+//! Status line model and rendering.
+//!
+//! The status line is split into a left segment (mode, buffer number, file name,
+//! dirty marker) and a right segment (`line:col` and how far through the file the cursor is).
+//! When the window is narrow the file name gives way first, so the position info
+//! stays readable. Widths are terminal cells, so wide (CJK, emoji) names are
+//! measured the way they're drawn, and cuts fall between graphemes.
+
+use editor_core::Pos;
+use minui::Window;
+use minui::prelude::{Color, ColorPair};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{TruncateMode, clip_graphemes_to_cells_ref, grapheme_cells, truncate_to_cells};
+use crate::input::EditorMode;
+
+/// Everything the status line shows, captured from the editor state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusLine {
+    pub mode: EditorMode,
+    pub file_name: String,
+    pub cursor: Pos,
+    pub len_lines: usize,
+    pub dirty: bool,
+    /// 1-based number of the active buffer and how many are open; shown as
+    /// `[2/3]` when there's more than one.
+    pub buffer: (usize, usize),
+}
+
+impl StatusLine {
+    /// Percentage of the file at or above the cursor line.
+    fn percent(&self) -> usize {
+        (self.cursor.line + 1) * 100 / self.len_lines.max(1)
+    }
+
+    /// The `(left, right)` segments for a window `width` cells wide.
+    ///
+    /// The file name is shortened from the front (`<` marks the cut) to make room
+    /// for the right segment; if even that isn't enough it's dropped entirely.
+    pub fn segments(&self, width: usize) -> (String, String) {
+        let right = format!(
+            "{}:{} {:>3}% ",
+            self.cursor.line + 1,
+            self.cursor.col + 1,
+            self.percent()
+        );
+        let mode = format!(" {} ", self.mode.label());
+        let dirty = if self.dirty { " [+]" } else { "" };
+        let (number, count) = self.buffer;
+        let mode = if count > 1 {
+            format!("{mode}[{number}/{count}] ")
+        } else {
+            mode
+        };
+
+        // One space keeps the two segments apart.
+        let used = cells(&right) + cells(&mode) + cells(dirty) + 1;
+        let room = width.saturating_sub(used);
+
+        // A lone `<` says nothing, so below two cells the name goes entirely.
+        let name = if room >= 2 {
+            truncate_to_cells(&self.file_name, room, "<", TruncateMode::Start)
+        } else {
+            String::new()
+        };
+
+        let mut left = format!("{mode}{name}{dirty}");
+        if cells(&left) + cells(&right) > width {
+            left = clip_cells(&left, width.saturating_sub(cells(&right)));
+        }
+        (left, clip_cells(&right, width))
+    }
+
+    /// The full line, padded so the right segment is flush with `width`.
+    pub fn render(&self, width: usize) -> String {
+        let (left, right) = self.segments(width);
+        let gap = width.saturating_sub(cells(&left) + cells(&right));
+        format!("{left}{}{right}", " ".repeat(gap))
+    }
+}
+
+/// Terminal cells taken by `s`.
+fn cells(s: &str) -> usize {
+    s.graphemes(true).map(grapheme_cells).sum()
+}
+
+/// The longest prefix of `s` that fits in `max` cells.
+fn clip_cells(s: &str, max: usize) -> String {
+    let graphemes: Vec<&str> = s.graphemes(true).collect();
+    clip_graphemes_to_cells_ref(&graphemes, max)
+}
+
+/// Draw the status line across `row` in reverse video.
+pub fn draw_status_line(
+    status: &StatusLine,
+    row: u16,
+    width: u16,
+    window: &mut dyn Window,
+) -> minui::Result<()> {
+    let colors = ColorPair::new(Color::Black, Color::White);
+    window.write_str_colored(row, 0, &status.render(width as usize), colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status() -> StatusLine {
+        StatusLine {
+            mode: EditorMode::Insert,
+            file_name: "src/editor/main.rs".into(),
+            cursor: Pos::new(9, 4),
+            len_lines: 40,
+            dirty: true,
+            buffer: (1, 1),
+        }
+    }
+
+    #[test]
+    fn formats_known_state() {
+        let (left, right) = status().segments(80);
+        assert_eq!(left, " INSERT src/editor/main.rs [+]");
+        assert_eq!(right, "10:5  25% ");
+        assert_eq!(status().render(80).chars().count(), 80);
+    }
+
+    #[test]
+    fn narrow_width_truncates_file_name_first() {
+        // 8 (mode) + 4 (dirty) + 10 (right) + 1 (gap) leaves 7 cells for the name.
+        let (left, right) = status().segments(30);
+        assert_eq!(left, " INSERT <ain.rs [+]");
+        assert_eq!(right, "10:5  25% ");
+        assert_eq!(status().render(30), " INSERT <ain.rs [+] 10:5  25% ");
+    }
+
+    #[test]
+    fn wide_file_names_are_measured_in_cells() {
+        let status = StatusLine {
+            file_name: "文書/日本語.txt".into(),
+            ..status()
+        };
+        // 7 cells for the name: `<` plus `.txt` leaves 2, one wide char.
+        let line = status.render(30);
+        assert!(line.starts_with(" INSERT <語.txt [+]"));
+        assert_eq!(cells(&line), 30);
+    }
+
+    #[test]
+    fn very_narrow_width_keeps_position() {
+        let (left, right) = status().segments(12);
+        assert_eq!(right, "10:5  25% ");
+        assert_eq!(left, " I");
+    }
+
+    #[test]
+    fn shows_the_buffer_number_with_several_open() {
+        let status = StatusLine {
+            buffer: (2, 3),
+            ..status()
+        };
+        assert_eq!(
+            status.segments(80).0,
+            " INSERT [2/3] src/editor/main.rs [+]"
+        );
+    }
+
+    #[test]
+    fn clean_buffer_has_no_marker() {
+        let status = StatusLine {
+            dirty: false,
+            mode: EditorMode::Normal,
+            ..status()
+        };
+        assert_eq!(status.segments(80).0, " NORMAL src/editor/main.rs");
+    }
+}